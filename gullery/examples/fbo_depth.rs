@@ -91,7 +91,7 @@ fn main() {
         }
 
         let mut fbo_attached = FramebufferObjectAttached {
-            fbo: FramebufferObject::new(state.clone()),
+            fbo: FramebufferObject::new(state.clone()).unwrap(),
             attachments: Attachments {
                 color: &mut color_texture,
                 depth: &mut depth_texture,
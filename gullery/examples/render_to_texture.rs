@@ -81,7 +81,7 @@ fn main() {
     let mut texture =
         Texture::with_mip_count(GLVec2::new(size_x, size_y), 1, state.clone()).unwrap();
     let mut fbo_attached = FramebufferObjectAttached {
-        fbo: FramebufferObject::new(state.clone()),
+        fbo: FramebufferObject::new(state.clone()).unwrap(),
         attachments: Attachments {
             color: &mut texture,
             color_inverted: Texture::with_mip_count(GLVec2::new(size_x, size_y), 1, state.clone())
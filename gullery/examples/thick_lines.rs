@@ -0,0 +1,187 @@
+#![feature(never_type)]
+
+extern crate gullery;
+#[macro_use]
+extern crate gullery_macros;
+extern crate glutin;
+
+use gullery::{
+    buffer::*,
+    framebuffer::{render_state::*, *},
+    geometry::GLVec2,
+    image_format::*,
+    program::*,
+    vertex::VertexArrayObject,
+    ContextState,
+};
+
+use glutin::{
+    dpi::LogicalSize, ContextBuilder, ControlFlow, Event, EventsLoop, GlContext, GlProfile,
+    GlRequest, GlWindow, WindowBuilder, WindowEvent,
+};
+
+#[derive(Vertex, Clone, Copy)]
+struct Vertex {
+    pos: GLVec2<f32>,
+}
+
+#[derive(Clone, Copy, Uniforms)]
+struct LineUniforms {
+    viewport_size: GLVec2<f32>,
+    half_width: f32,
+}
+
+const SEGMENTS: usize = 64;
+
+fn main() {
+    let mut events_loop = EventsLoop::new();
+    let window = GlWindow::new(
+        WindowBuilder::new().with_dimensions(LogicalSize::new(512.0, 512.0)),
+        ContextBuilder::new()
+            .with_gl_profile(GlProfile::Core)
+            .with_gl(GlRequest::GlThenGles {
+                opengl_version: (3, 2),
+                opengles_version: (3, 0),
+            })
+            .with_srgb(true),
+        &events_loop,
+    )
+    .unwrap();
+    unsafe { window.context().make_current().unwrap() };
+    let state = unsafe { ContextState::new(|addr| window.context().get_proc_address(addr)) };
+
+    // A sine wave, with one extra vertex on either end that only provides adjacency for the
+    // geometry shader's corner joins.
+    let vertices = (0..SEGMENTS + 3)
+        .map(|i| {
+            let t = (i as f32 - 1.0) / SEGMENTS as f32;
+            Vertex {
+                pos: GLVec2::new(t * 1.6 - 0.8, (t * std::f32::consts::PI * 4.0).sin() * 0.5),
+            }
+        })
+        .collect::<Vec<_>>();
+    let vertex_buffer = Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone());
+    let vao = VertexArrayObject::<_, !>::new(vertex_buffer, None);
+
+    let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+    let geometry_shader = Shader::new(GEOMETRY_SHADER, state.clone()).unwrap();
+    let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+    let (program, _) =
+        Program::new(&vertex_shader, Some(&geometry_shader), &fragment_shader).unwrap();
+
+    let mut render_state = RenderState {
+        srgb: true,
+        viewport: GLVec2::new(0, 0)..=GLVec2::new(512, 512),
+        ..RenderState::default()
+    };
+
+    let mut default_framebuffer = FramebufferDefault::new(state.clone()).unwrap();
+    events_loop.run_forever(|event| {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::Resized(logical_size) => {
+                    let physical_size = logical_size.to_physical(window.get_hidpi_factor());
+                    window.resize(physical_size);
+                    let uniform = LineUniforms {
+                        viewport_size: GLVec2::new(
+                            physical_size.width as f32,
+                            physical_size.height as f32,
+                        ),
+                        half_width: 8.0,
+                    };
+                    render_state.viewport = GLVec2::new(0, 0)
+                        ..=GLVec2::new(physical_size.width as u32, physical_size.height as u32);
+                    default_framebuffer.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 1.0));
+                    default_framebuffer.draw(
+                        DrawMode::LineStripAdjacency,
+                        ..,
+                        &vao,
+                        &program,
+                        &uniform,
+                        &render_state,
+                    );
+
+                    window.swap_buffers().unwrap();
+                }
+                WindowEvent::CloseRequested => return ControlFlow::Break,
+                _ => (),
+            },
+            _ => (),
+        }
+
+        ControlFlow::Continue
+    });
+}
+
+const VERTEX_SHADER: &str = r#"
+    #version 330
+
+    in vec2 pos;
+
+    void main() {
+        gl_Position = vec4(pos, 0.0, 1.0);
+    }
+"#;
+
+// Expands each segment into a quad in pixel space, so the line has the same thickness regardless
+// of the window's aspect ratio. The ends of each quad are mitered with the neighboring segments,
+// which the `lines_adjacency` input makes available.
+const GEOMETRY_SHADER: &str = r#"
+    #version 330
+
+    layout(lines_adjacency) in;
+    layout(triangle_strip, max_vertices = 4) out;
+
+    uniform vec2 viewport_size;
+    uniform float half_width;
+
+    vec2 to_pixels(vec4 clip) {
+        return clip.xy / clip.w * viewport_size * 0.5;
+    }
+
+    vec4 to_clip(vec2 pixels) {
+        return vec4(pixels / viewport_size * 2.0, 0.0, 1.0);
+    }
+
+    vec2 perp(vec2 v) {
+        return vec2(-v.y, v.x);
+    }
+
+    vec2 miter(vec2 prev_dir, vec2 next_dir) {
+        vec2 normal = perp(next_dir);
+        vec2 miter = perp(normalize(prev_dir + next_dir));
+        // Clamp the miter length so sharp corners don't produce long spikes.
+        return miter * min(half_width / dot(miter, normal), half_width * 4.0);
+    }
+
+    void main() {
+        vec2 p0 = to_pixels(gl_in[0].gl_Position);
+        vec2 p1 = to_pixels(gl_in[1].gl_Position);
+        vec2 p2 = to_pixels(gl_in[2].gl_Position);
+        vec2 p3 = to_pixels(gl_in[3].gl_Position);
+
+        vec2 dir = normalize(p2 - p1);
+        vec2 miter_start = miter(normalize(p1 - p0), dir);
+        vec2 miter_end = miter(dir, normalize(p3 - p2));
+
+        gl_Position = to_clip(p1 - miter_start);
+        EmitVertex();
+        gl_Position = to_clip(p2 - miter_end);
+        EmitVertex();
+        gl_Position = to_clip(p1 + miter_start);
+        EmitVertex();
+        gl_Position = to_clip(p2 + miter_end);
+        EmitVertex();
+        EndPrimitive();
+    }
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+    #version 330
+
+    out vec4 color;
+
+    void main() {
+        color = vec4(1.0, 0.6, 0.1, 1.0);
+    }
+"#;
@@ -16,13 +16,28 @@
 
 mod raw;
 
-pub use self::raw::BufferUsage;
 pub(crate) use self::raw::RawBindTarget;
 use self::raw::{targets, RawBuffer};
+pub use self::raw::{BufferStorageFlags, BufferUsage};
 
-use crate::{gl::Gl, ContextState, GLObject, Handle};
+#[cfg(debug_assertions)]
+use crate::Diagnostic;
+use crate::{
+    gl::{self, types::*, Gl},
+    ContextState, GLObject, Handle,
+};
 
-use std::{ops::RangeBounds, rc::Rc};
+#[cfg(debug_assertions)]
+use std::time::{Duration, Instant};
+use std::{
+    cell::Cell,
+    error::Error,
+    fmt, io, mem,
+    ops::{Deref, DerefMut, RangeBounds},
+    ptr::NonNull,
+    rc::Rc,
+    slice,
+};
 
 pub(crate) struct BufferBinds {
     copy_read: targets::RawCopyRead,
@@ -37,6 +52,12 @@ impl BufferBinds {
         }
     }
 
+    /// Forget which buffers are bound, so the next use of each target re-binds.
+    pub(crate) fn invalidate(&self) {
+        self.copy_read.bound_buffer().set(None);
+        self.copy_write.bound_buffer().set(None);
+    }
+
     unsafe fn unbind<T: Copy>(&self, buf: &RawBuffer<T>, gl: &Gl) {
         if self.copy_read.bound_buffer().get() == Some(buf.handle()) {
             self.copy_read.reset_bind(gl);
@@ -50,16 +71,176 @@ impl BufferBinds {
 /// The GPU data buffer type.
 pub struct Buffer<T: 'static + Copy> {
     raw: RawBuffer<T>,
+    /// `Some` if the buffer was created with immutable storage.
+    storage_flags: Option<BufferStorageFlags>,
+    /// `None` if the buffer was created with immutable storage.
+    usage: Option<BufferUsage>,
+    static_writes: StaticWriteCheck,
+    /// Set while a `PersistentMap` of the buffer exists.
+    persistent_map: Cell<bool>,
     state: Rc<ContextState>,
 }
 
+/// Tracks writes to `StaticDraw` buffers in debug builds, to report buffers that get updated often
+/// enough that they should use a different usage hint.
+#[derive(Default)]
+struct StaticWriteCheck {
+    #[cfg(debug_assertions)]
+    last_write: Option<Instant>,
+    #[cfg(debug_assertions)]
+    reported: bool,
+}
+
+impl StaticWriteCheck {
+    #[inline]
+    fn record(&mut self, usage: Option<BufferUsage>, buffer: Handle, state: &ContextState) {
+        #[cfg(debug_assertions)]
+        {
+            // Roughly one frame at 60 FPS.
+            const FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+            if usage != Some(BufferUsage::StaticDraw) || self.reported {
+                return;
+            }
+            let now = Instant::now();
+            if let Some(last_write) = self.last_write {
+                if now.duration_since(last_write) < FRAME_TIME {
+                    state.report(|| Diagnostic::StaticBufferRewritten { buffer });
+                    self.reported = true;
+                }
+            }
+            self.last_write = Some(now);
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = (usage, buffer, state);
+    }
+}
+
+/// Error returned by [`Buffer::try_with_data`] when the GPU runs out of memory.
+///
+/// [`Buffer::try_with_data`]: ./struct.Buffer.html#method.try_with_data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError {
+    /// The size of the requested allocation, in bytes.
+    pub bytes: usize,
+}
+
+/// Error returned by [`Buffer::map`] and [`Buffer::map_mut`] when OpenGL fails to map the buffer.
+///
+/// [`Buffer::map`]: ./struct.Buffer.html#method.map
+/// [`Buffer::map_mut`]: ./struct.Buffer.html#method.map_mut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapError {
+    /// The error OpenGL reported when `glMapBufferRange` returned null.
+    pub gl_error: GLenum,
+}
+
+/// A range of a [`Buffer`], mapped into client memory for reading.
+///
+/// Returned by [`Buffer::map`]. The buffer gets unmapped when this is dropped.
+///
+/// [`Buffer`]: ./struct.Buffer.html
+/// [`Buffer::map`]: ./struct.Buffer.html#method.map
+pub struct MappedBuffer<'a, T: 'static + Copy> {
+    buffer: &'a Buffer<T>,
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+/// A range of a [`Buffer`], mapped into client memory for writing.
+///
+/// Returned by [`Buffer::map_mut`]. The buffer gets unmapped when this is dropped.
+///
+/// [`Buffer`]: ./struct.Buffer.html
+/// [`Buffer::map_mut`]: ./struct.Buffer.html#method.map_mut
+pub struct MappedBufferMut<'a, T: 'static + Copy> {
+    buffer: &'a mut Buffer<T>,
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+/// A whole [`Buffer`], persistently mapped into client memory for writing.
+///
+/// Returned by [`Buffer::map_persistent`]. The buffer gets unmapped when this is dropped, so new
+/// data can be written every frame without re-mapping the buffer. The map borrows the buffer, so
+/// the buffer can't be dropped while it's mapped:
+///
+/// ```rust,compile_fail
+/// # use gullery::buffer::Buffer;
+/// fn write_after_drop(buffer: Buffer<u32>) {
+///     let mut map = unsafe { buffer.map_persistent() }.unwrap();
+///     drop(buffer);
+///     map.as_mut_slice()[0] = 1;
+/// }
+/// ```
+///
+/// [`Buffer`]: ./struct.Buffer.html
+/// [`Buffer::map_persistent`]: ./struct.Buffer.html#method.map_persistent
+pub struct PersistentMap<'a, T: 'static + Copy> {
+    buffer: &'a Buffer<T>,
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+/// A [`Buffer`] holding data for a uniform block, which can be bound to a program with
+/// [`Program::bind_uniform_block`].
+///
+/// OpenGL reads the buffer with the block's memory layout, so `T` must be `#[repr(C)]` and match
+/// the `std140` layout of the block it's bound to. `std140` aligns `vec3` and `vec4` members to 16
+/// bytes, rounds the stride of every array element up to 16 bytes, and pads structs to a multiple
+/// of 16 bytes, so members that would be tightly packed in Rust often need explicit padding.
+/// Gullery can't check member offsets, but binding a buffer that's smaller than the block returns
+/// an error.
+///
+/// `UniformBuffer` dereferences to `Buffer`, so the data can be updated with the usual buffer
+/// methods.
+///
+/// [`Buffer`]: ./struct.Buffer.html
+/// [`Program::bind_uniform_block`]: ../program/struct.Program.html#method.bind_uniform_block
+pub struct UniformBuffer<T: 'static + Copy> {
+    buffer: Buffer<T>,
+}
+
+/// Error returned by [`Buffer::with_storage`].
+///
+/// [`Buffer::with_storage`]: ./struct.Buffer.html#method.with_storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferStorageError {
+    /// The context supports neither OpenGL 4.4 nor `ARB_buffer_storage`.
+    Unsupported,
+    /// `MAP_PERSISTENT` was requested without `MAP_READ` or `MAP_WRITE`.
+    PersistentWithoutMapAccess,
+    /// `MAP_COHERENT` was requested without `MAP_PERSISTENT`.
+    CoherentWithoutPersistent,
+    /// Immutable storage can't be created with a size of zero.
+    Empty,
+}
+
 impl<T: 'static + Copy> Buffer<T> {
     /// Create a new buffer and upload the provided data to the buffer.
     ///
     /// ## Panics
-    /// Panics is GPU is out of memory.
+    /// Panics if the GPU is out of memory. See [`try_with_data`] for a non-panicking version.
+    ///
+    /// [`try_with_data`]: #method.try_with_data
     #[inline]
     pub fn with_data(usage: BufferUsage, data: &[T], state: Rc<ContextState>) -> Buffer<T> {
+        match Buffer::try_with_data(usage, data, state) {
+            Ok(buffer) => buffer,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a new buffer and upload the provided data to the buffer, returning an error if the
+    /// GPU is out of memory.
+    ///
+    /// This lets applications free up other resources and try again instead of crashing.
+    #[inline]
+    pub fn try_with_data(
+        usage: BufferUsage,
+        data: &[T],
+        state: Rc<ContextState>,
+    ) -> Result<Buffer<T>, AllocError> {
         let raw = {
             let ContextState {
                 ref buffer_binds,
@@ -68,20 +249,88 @@ impl<T: 'static + Copy> Buffer<T> {
             } = *state;
 
             let mut raw = RawBuffer::new(gl);
-            {
+            let result = {
                 let mut bind = unsafe { buffer_binds.copy_write.bind_mut(&mut raw, gl) };
                 bind.alloc_upload(data, usage)
+            };
+            if let Err(err) = result {
+                unsafe { raw.delete(&state) };
+                return Err(err);
             }
             raw
         };
 
-        Buffer { raw, state }
+        Ok(Buffer {
+            raw,
+            storage_flags: None,
+            usage: Some(usage),
+            static_writes: StaticWriteCheck::default(),
+            persistent_map: Cell::new(false),
+            state,
+        })
+    }
+
+    /// Create a new buffer with immutable storage, initialized with the provided data.
+    ///
+    /// The buffer's size can't change after creation, and `flags` control how the buffer may be
+    /// accessed afterwards. Immutable storage is needed for persistent mapping, and gives the
+    /// driver more information about where to place the buffer than a [`BufferUsage`] hint does.
+    /// Requires OpenGL 4.4 or `ARB_buffer_storage`.
+    ///
+    /// ## Panics
+    /// Panics if the GPU is out of memory.
+    ///
+    /// [`BufferUsage`]: ./enum.BufferUsage.html
+    pub fn with_storage(
+        flags: BufferStorageFlags,
+        data: &[T],
+        state: Rc<ContextState>,
+    ) -> Result<Buffer<T>, BufferStorageError> {
+        if !state.buffer_storage {
+            return Err(BufferStorageError::Unsupported);
+        }
+        let map_access = BufferStorageFlags::MAP_READ | BufferStorageFlags::MAP_WRITE;
+        if flags.contains(BufferStorageFlags::MAP_PERSISTENT) && !flags.intersects(map_access) {
+            return Err(BufferStorageError::PersistentWithoutMapAccess);
+        }
+        if flags.contains(BufferStorageFlags::MAP_COHERENT)
+            && !flags.contains(BufferStorageFlags::MAP_PERSISTENT)
+        {
+            return Err(BufferStorageError::CoherentWithoutPersistent);
+        }
+        if data.is_empty() {
+            return Err(BufferStorageError::Empty);
+        }
+
+        let raw = {
+            let ContextState {
+                ref buffer_binds,
+                ref gl,
+                ..
+            } = *state;
+
+            let mut raw = RawBuffer::new(gl);
+            unsafe {
+                let mut bind = buffer_binds.copy_write.bind_mut(&mut raw, gl);
+                bind.alloc_storage(data, flags)
+            }
+            raw
+        };
+
+        Ok(Buffer {
+            raw,
+            storage_flags: Some(flags),
+            usage: None,
+            static_writes: StaticWriteCheck::default(),
+            persistent_map: Cell::new(false),
+            state,
+        })
     }
 
     /// Creates a new buffer that can hold the specified number of elements.
     ///
     /// ## Panics
-    /// Panics is GPU is out of memory.
+    /// Panics if the GPU is out of memory.
     #[inline]
     pub fn with_size(usage: BufferUsage, size: usize, state: Rc<ContextState>) -> Buffer<T> {
         let raw = {
@@ -99,7 +348,14 @@ impl<T: 'static + Copy> Buffer<T> {
             raw
         };
 
-        Buffer { raw, state }
+        Buffer {
+            raw,
+            storage_flags: None,
+            usage: Some(usage),
+            static_writes: StaticWriteCheck::default(),
+            persistent_map: Cell::new(false),
+            state,
+        }
     }
 
     /// Returns the number of elements in the buffer.
@@ -108,6 +364,23 @@ impl<T: 'static + Copy> Buffer<T> {
         self.raw.size()
     }
 
+    /// The usage hint the buffer was created with, or `None` if it was created with
+    /// [`with_storage`].
+    ///
+    /// [`with_storage`]: #method.with_storage
+    #[inline]
+    pub fn usage(&self) -> Option<BufferUsage> {
+        self.usage
+    }
+
+    /// The flags the buffer was created with, if it was created with [`with_storage`].
+    ///
+    /// [`with_storage`]: #method.with_storage
+    #[inline]
+    pub fn storage_flags(&self) -> Option<BufferStorageFlags> {
+        self.storage_flags
+    }
+
     /// Reads data from the GPU into `buf`, starting at `offset` elements into the buffer.
     ///
     /// ## Safety
@@ -131,23 +404,290 @@ impl<T: 'static + Copy> Buffer<T> {
         bind.get_data(offset, buf);
     }
 
+    /// Reads the entire buffer from the GPU into a newly allocated `Vec`.
+    ///
+    /// ## Safety
+    /// Has the same requirements as [`get_data`](#method.get_data).
+    #[inline]
+    pub(crate) unsafe fn get_data_vec(&self) -> Vec<T> {
+        let ContextState {
+            ref buffer_binds,
+            ref gl,
+            ..
+        } = *self.state;
+
+        let bind = buffer_binds.copy_read.bind(&self.raw, gl);
+        bind.get_data_vec()
+    }
+
     /// Writes data from `data` into the GPU buffer, starting the write at `offset` elements into
     /// the buffer.
     ///
     /// ## Panics
-    /// Panics if `offset + buf.len() > self.len()`
+    /// Panics if `offset + buf.len() > self.len()`, or if the buffer has immutable storage
+    /// without [`BufferStorageFlags::DYNAMIC_STORAGE`].
+    ///
+    /// [`BufferStorageFlags::DYNAMIC_STORAGE`]: ./struct.BufferStorageFlags.html#associatedconstant.DYNAMIC_STORAGE
     #[inline]
     pub fn sub_data(&mut self, offset: usize, data: &[T]) {
+        if let Some(flags) = self.storage_flags {
+            assert!(
+                flags.contains(BufferStorageFlags::DYNAMIC_STORAGE),
+                "buffer storage wasn't created with `DYNAMIC_STORAGE`"
+            );
+        }
         let ContextState {
             ref buffer_binds,
             ref gl,
             ..
         } = *self.state;
 
+        self.static_writes
+            .record(self.usage, self.raw.handle(), &self.state);
         let mut bind = unsafe { buffer_binds.copy_write.bind_mut(&mut self.raw, gl) };
         bind.sub_data(offset, data);
     }
 
+    /// Set every element in the buffer to `value`.
+    ///
+    /// See [`clear_range`] for details.
+    ///
+    /// [`clear_range`]: #method.clear_range
+    #[inline]
+    pub fn clear(&mut self, value: T) {
+        self.clear_range(.., value);
+    }
+
+    /// Set every element in `range` to `value`.
+    ///
+    /// On OpenGL 4.3 or with `ARB_clear_buffer_object`, the driver replicates `value` on the GPU
+    /// without uploading anything else from the CPU. This works for any `T` that's 1, 2, 4, 8, 12
+    /// or 16 bytes large, and for buffers with immutable storage regardless of
+    /// [`BufferStorageFlags::DYNAMIC_STORAGE`]. Otherwise, this falls back to uploading a
+    /// `Vec` filled with `value` through [`sub_data`].
+    ///
+    /// ## Panics
+    /// Panics if the range ends past `self.len()` or starts after it ends, or if the fallback
+    /// upload is used on a buffer with immutable storage without `DYNAMIC_STORAGE`.
+    ///
+    /// [`sub_data`]: #method.sub_data
+    /// [`BufferStorageFlags::DYNAMIC_STORAGE`]: ./struct.BufferStorageFlags.html#associatedconstant.DYNAMIC_STORAGE
+    pub fn clear_range<R: RangeBounds<usize>>(&mut self, range: R, value: T) {
+        let start = crate::bound_to_num_start(range.start_bound(), 0);
+        let end = crate::bound_to_num_end(range.end_bound(), self.len());
+        if end > self.len() {
+            panic!(
+                "Clear range end {} out of range for buffer of length {}",
+                end,
+                self.len()
+            );
+        }
+        let len = end
+            .checked_sub(start)
+            .unwrap_or_else(|| panic!("Clear range starts at {} but ends at {}", start, end));
+        if mem::size_of::<T>() == 0 {
+            return;
+        }
+
+        if self.state.clear_buffer {
+            let ContextState {
+                ref buffer_binds,
+                ref gl,
+                ..
+            } = *self.state;
+
+            let cleared = unsafe {
+                let mut bind = buffer_binds.copy_write.bind_mut(&mut self.raw, gl);
+                bind.clear_sub_data(start, len, &value)
+            };
+            if cleared {
+                self.static_writes
+                    .record(self.usage, self.raw.handle(), &self.state);
+                return;
+            }
+        }
+        self.sub_data(start, &vec![value; len]);
+    }
+
+    /// Replace the buffer's data store with a new, uninitialized one of the same length and usage.
+    ///
+    /// Draws that are still reading the old store keep using it, while the driver hands out fresh
+    /// memory for the new one. Orphaning a buffer before rewriting all of it with [`sub_data`]
+    /// lets the upload start right away, rather than stalling until the GPU is done with the
+    /// previous contents. The new store's contents are unspecified until they're written to.
+    ///
+    /// ## Panics
+    /// Panics if the buffer was created with immutable storage, which can't be reallocated.
+    ///
+    /// [`sub_data`]: #method.sub_data
+    pub fn orphan(&mut self) {
+        let usage = self
+            .usage
+            .expect("buffers with immutable storage can't be orphaned");
+        let ContextState {
+            ref buffer_binds,
+            ref gl,
+            ..
+        } = *self.state;
+
+        let len = self.raw.size();
+        unsafe {
+            let mut bind = buffer_binds.copy_write.bind_mut(&mut self.raw, gl);
+            bind.alloc_size(len, usage);
+        }
+    }
+
+    /// Map the elements in `range` into client memory for reading.
+    ///
+    /// `range` gets clamped to the buffer's length. The buffer stays mapped until the returned
+    /// [`MappedBuffer`] is dropped, and drawing with a mapped buffer is an OpenGL error.
+    ///
+    /// ## Safety
+    /// The same as [`get_data`]: if no data has been uploaded to the mapped range, its contents
+    /// are unspecified.
+    ///
+    /// ## Panics
+    /// Panics if the buffer has immutable storage without [`BufferStorageFlags::MAP_READ`].
+    ///
+    /// [`MappedBuffer`]: ./struct.MappedBuffer.html
+    /// [`get_data`]: #method.get_data
+    /// [`BufferStorageFlags::MAP_READ`]: ./struct.BufferStorageFlags.html#associatedconstant.MAP_READ
+    pub unsafe fn map<R: RangeBounds<usize>>(
+        &self,
+        range: R,
+    ) -> Result<MappedBuffer<'_, T>, MapError> {
+        let (ptr, len) = self.map_raw(range, BufferStorageFlags::MAP_READ)?;
+        Ok(MappedBuffer {
+            buffer: self,
+            ptr,
+            len,
+        })
+    }
+
+    /// Map the elements in `range` into client memory for writing.
+    ///
+    /// `range` gets clamped to the buffer's length. Writes become visible to OpenGL when the
+    /// returned [`MappedBufferMut`] is dropped and the buffer gets unmapped.
+    ///
+    /// ## Safety
+    /// The range is mapped without `GL_MAP_READ_BIT`, so reading from it returns unspecified
+    /// data. Only write to the mapping unless every bit pattern is a valid `T`.
+    ///
+    /// ## Panics
+    /// Panics if the buffer has immutable storage without [`BufferStorageFlags::MAP_WRITE`].
+    ///
+    /// [`MappedBufferMut`]: ./struct.MappedBufferMut.html
+    /// [`BufferStorageFlags::MAP_WRITE`]: ./struct.BufferStorageFlags.html#associatedconstant.MAP_WRITE
+    pub unsafe fn map_mut<R: RangeBounds<usize>>(
+        &mut self,
+        range: R,
+    ) -> Result<MappedBufferMut<'_, T>, MapError> {
+        let (ptr, len) = self.map_raw(range, BufferStorageFlags::MAP_WRITE)?;
+        self.static_writes
+            .record(self.usage, self.raw.handle(), &self.state);
+        Ok(MappedBufferMut {
+            buffer: self,
+            ptr,
+            len,
+        })
+    }
+
+    /// Map the whole buffer into client memory for writing, and keep it mapped until the returned
+    /// [`PersistentMap`] is dropped.
+    ///
+    /// The buffer must have been created by [`with_storage`] with the [`MAP_WRITE`],
+    /// [`MAP_PERSISTENT`], and [`MAP_COHERENT`] flags. Since the mapping is coherent, writes
+    /// become visible to the GPU without any explicit flushing, and the buffer can be drawn
+    /// from while it's mapped. OpenGL doesn't stop the CPU from overwriting data the GPU is still
+    /// reading, though, so streaming uploads should rotate between several regions of the buffer
+    /// and only reuse one after the draws reading from it have finished.
+    ///
+    /// ## Safety
+    /// The same as [`map_mut`]: reading from the mapping returns unspecified data.
+    ///
+    /// ## Panics
+    /// Panics if the buffer wasn't created with the flags listed above, or if it's already
+    /// persistently mapped.
+    ///
+    /// [`PersistentMap`]: ./struct.PersistentMap.html
+    /// [`with_storage`]: #method.with_storage
+    /// [`map_mut`]: #method.map_mut
+    /// [`MAP_WRITE`]: ./struct.BufferStorageFlags.html#associatedconstant.MAP_WRITE
+    /// [`MAP_PERSISTENT`]: ./struct.BufferStorageFlags.html#associatedconstant.MAP_PERSISTENT
+    /// [`MAP_COHERENT`]: ./struct.BufferStorageFlags.html#associatedconstant.MAP_COHERENT
+    pub unsafe fn map_persistent(&self) -> Result<PersistentMap<'_, T>, MapError> {
+        let access = BufferStorageFlags::MAP_WRITE
+            | BufferStorageFlags::MAP_PERSISTENT
+            | BufferStorageFlags::MAP_COHERENT;
+        assert!(
+            self.storage_flags
+                .is_some_and(|flags| flags.contains(access)),
+            "persistent mapping requires a buffer created by `with_storage` with `{:?}`",
+            access
+        );
+        assert!(
+            !self.persistent_map.get(),
+            "buffer is already persistently mapped"
+        );
+
+        let (ptr, len) = self.map_raw(.., access)?;
+        self.persistent_map.set(true);
+        Ok(PersistentMap {
+            buffer: self,
+            ptr,
+            len,
+        })
+    }
+
+    unsafe fn map_raw<R: RangeBounds<usize>>(
+        &self,
+        range: R,
+        access: BufferStorageFlags,
+    ) -> Result<(NonNull<T>, usize), MapError> {
+        if let Some(flags) = self.storage_flags {
+            assert!(
+                flags.contains(access),
+                "buffer storage wasn't created with `{:?}`",
+                access
+            );
+        }
+        let end = crate::bound_to_num_end(range.end_bound(), self.len()).min(self.len());
+        let start = crate::bound_to_num_start(range.start_bound(), 0).min(end);
+        let len = end - start;
+
+        // OpenGL can't map an empty range, so there's nothing to map or unmap.
+        if len * mem::size_of::<T>() == 0 {
+            return Ok((NonNull::dangling(), len));
+        }
+
+        // Which target the buffer gets mapped through doesn't matter, so both kinds of mapping go
+        // through `GL_COPY_READ_BUFFER`.
+        let ContextState {
+            ref buffer_binds,
+            ref gl,
+            ..
+        } = *self.state;
+        let bind = buffer_binds.copy_read.bind(&self.raw, gl);
+        let ptr = bind
+            .map_range(start, len, access.bits())
+            .map_err(|gl_error| MapError { gl_error })?;
+        Ok((NonNull::new_unchecked(ptr), len))
+    }
+
+    /// Unmap a range mapped by `map_raw`.
+    unsafe fn unmap_raw(&self, len: usize) {
+        if len * mem::size_of::<T>() == 0 {
+            return;
+        }
+        let ContextState {
+            ref buffer_binds,
+            ref gl,
+            ..
+        } = *self.state;
+        let bind = buffer_binds.copy_read.bind(&self.raw, gl);
+        assert!(bind.unmap(), "buffer contents were corrupted while mapped");
+    }
+
     #[inline]
     pub fn copy_to<R: RangeBounds<usize>>(
         &self,
@@ -165,6 +705,148 @@ impl<T: 'static + Copy> Buffer<T> {
         let mut dest_bind = unsafe { buffer_binds.copy_write.bind_mut(&mut dest_buf.raw, gl) };
         src_bind.copy_to(&mut dest_bind, self_range, write_offset);
     }
+
+    /// Bind the whole buffer to binding point `index` of the indexed `target`.
+    ///
+    /// Indexed binding points aren't tracked by `BufferBinds`, so this doesn't touch the cache.
+    #[inline]
+    pub(crate) unsafe fn bind_base(&self, target: GLenum, index: GLuint) {
+        self.state
+            .gl
+            .BindBufferBase(target, index, self.raw.handle().get());
+    }
+}
+
+impl Buffer<u32> {
+    /// Bind the buffer to the atomic counter binding point `binding`, so that shaders can use it
+    /// through `layout(binding = N) uniform atomic_uint`.
+    ///
+    /// Each element of the buffer is one counter, so a counter declared with `offset = 4` reads the
+    /// buffer's second element. The buffer stays bound to `binding` until another buffer gets bound
+    /// there or the buffer is dropped. Requires OpenGL 4.2 or `ARB_shader_atomic_counters`.
+    ///
+    /// ## Panics
+    /// Panics if the context doesn't support atomic counters, or if `binding` is greater than or
+    /// equal to `GL_MAX_ATOMIC_COUNTER_BUFFER_BINDINGS`.
+    #[inline]
+    pub fn bind_atomic_counter(&self, binding: u32) {
+        assert!(
+            self.state.atomic_counters,
+            "atomic counters are unsupported; requires OpenGL 4.2 or ARB_shader_atomic_counters"
+        );
+        let gl = &self.state.gl;
+        unsafe {
+            let mut max_bindings = 0;
+            gl.GetIntegerv(gl::MAX_ATOMIC_COUNTER_BUFFER_BINDINGS, &mut max_bindings);
+            if binding >= max_bindings as u32 {
+                panic!(
+                    "atomic counter binding {} exceeds maximum of {}",
+                    binding,
+                    max_bindings - 1
+                );
+            }
+            self.bind_base(gl::ATOMIC_COUNTER_BUFFER, binding);
+        }
+    }
+
+    /// Set every counter in the buffer to zero.
+    ///
+    /// Call this before each pass that accumulates into the counters.
+    #[inline]
+    pub fn reset_atomic_counters(&mut self) {
+        self.clear(0);
+    }
+}
+
+impl<'a, T: 'static + Copy> Deref for MappedBuffer<'a, T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T: 'static + Copy> Drop for MappedBuffer<'a, T> {
+    fn drop(&mut self) {
+        unsafe { self.buffer.unmap_raw(self.len) }
+    }
+}
+
+impl<'a, T: 'static + Copy> Deref for MappedBufferMut<'a, T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T: 'static + Copy> DerefMut for MappedBufferMut<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T: 'static + Copy> Drop for MappedBufferMut<'a, T> {
+    fn drop(&mut self) {
+        unsafe { self.buffer.unmap_raw(self.len) }
+    }
+}
+
+impl<'a, T: 'static + Copy> PersistentMap<'a, T> {
+    /// The mapped contents of the buffer.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T: 'static + Copy> Drop for PersistentMap<'a, T> {
+    fn drop(&mut self) {
+        unsafe { self.buffer.unmap_raw(self.len) };
+        self.buffer.persistent_map.set(false);
+    }
+}
+
+impl<T: 'static + Copy> UniformBuffer<T> {
+    /// Create a uniform buffer holding a single `value`.
+    #[inline]
+    pub fn new(usage: BufferUsage, value: T, state: Rc<ContextState>) -> UniformBuffer<T> {
+        UniformBuffer::from_buffer(Buffer::with_data(usage, &[value], state))
+    }
+
+    /// Use an existing buffer as a uniform buffer. Each element can be bound to a block on its
+    /// own with [`Program::bind_uniform_block_range`], as long as `size_of::<T>()` is a multiple
+    /// of `GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+    ///
+    /// [`Program::bind_uniform_block_range`]: ../program/struct.Program.html#method.bind_uniform_block_range
+    #[inline]
+    pub fn from_buffer(buffer: Buffer<T>) -> UniformBuffer<T> {
+        UniformBuffer { buffer }
+    }
+
+    #[inline]
+    pub fn into_buffer(self) -> Buffer<T> {
+        self.buffer
+    }
+}
+
+impl<T: 'static + Copy> Deref for UniformBuffer<T> {
+    type Target = Buffer<T>;
+
+    #[inline]
+    fn deref(&self) -> &Buffer<T> {
+        &self.buffer
+    }
+}
+
+impl<T: 'static + Copy> DerefMut for UniformBuffer<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Buffer<T> {
+        &mut self.buffer
+    }
 }
 
 impl<T: 'static + Copy> GLObject for Buffer<T> {
@@ -178,6 +860,60 @@ impl<T: 'static + Copy> GLObject for Buffer<T> {
     }
 }
 
+impl From<AllocError> for io::Error {
+    fn from(err: AllocError) -> io::Error {
+        io::Error::new(io::ErrorKind::OutOfMemory, err)
+    }
+}
+
+impl Error for AllocError {}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "OpenGL out of memory allocating {} bytes", self.bytes)
+    }
+}
+
+impl From<MapError> for io::Error {
+    fn from(err: MapError) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+impl Error for MapError {}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "OpenGL failed to map buffer (error {:#x})", self.gl_error)
+    }
+}
+
+impl From<BufferStorageError> for io::Error {
+    fn from(err: BufferStorageError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl Error for BufferStorageError {}
+
+impl fmt::Display for BufferStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            BufferStorageError::Unsupported => write!(
+                f,
+                "immutable buffer storage requires OpenGL 4.4 or ARB_buffer_storage"
+            ),
+            BufferStorageError::PersistentWithoutMapAccess => {
+                write!(f, "MAP_PERSISTENT requires MAP_READ or MAP_WRITE")
+            }
+            BufferStorageError::CoherentWithoutPersistent => {
+                write!(f, "MAP_COHERENT requires MAP_PERSISTENT")
+            }
+            BufferStorageError::Empty => write!(f, "immutable buffer storage can't be empty"),
+        }
+    }
+}
+
 impl<T: 'static + Copy> Drop for Buffer<T> {
     fn drop(&mut self) {
         unsafe {
@@ -202,4 +938,185 @@ mod tests {
             })
         }
     }
+
+    #[test]
+    fn orphan_keeps_len_and_usage() {
+        CONTEXT_STATE.with(|state| {
+            let mut buffer = Buffer::with_data(BufferUsage::StreamDraw, &[1u32; 4], state.clone());
+            for frame in 2..4 {
+                buffer.orphan();
+                assert_eq!(4, buffer.len());
+                assert_eq!(Some(BufferUsage::StreamDraw), buffer.usage());
+                buffer.sub_data(0, &[frame; 4]);
+
+                let mut read = [0; 4];
+                unsafe { buffer.get_data(0, &mut read) };
+                assert_eq!([frame; 4], read);
+            }
+        })
+    }
+
+    #[test]
+    fn clear_fills_ranges() {
+        CONTEXT_STATE.with(|state| {
+            let mut buffer = Buffer::with_data(BufferUsage::StaticDraw, &[1u32; 8], state.clone());
+            buffer.clear_range(2..5, 7);
+            let mut read = [0; 8];
+            unsafe { buffer.get_data(0, &mut read) };
+            assert_eq!([1, 1, 7, 7, 7, 1, 1, 1], read);
+
+            buffer.clear(0);
+            unsafe { buffer.get_data(0, &mut read) };
+            assert_eq!([0; 8], read);
+
+            // Three bytes has no matching integer format, so this takes the fallback upload.
+            let mut bytes = Buffer::with_size(BufferUsage::StaticDraw, 4, state.clone());
+            bytes.clear([1u8, 2, 3]);
+            let mut read = [[0u8; 3]; 4];
+            unsafe { bytes.get_data(0, &mut read) };
+            assert_eq!([[1, 2, 3]; 4], read);
+        })
+    }
+
+    #[test]
+    #[should_panic]
+    fn clear_range_out_of_bounds() {
+        CONTEXT_STATE.with(|state| {
+            let mut buffer = Buffer::with_data(BufferUsage::StaticDraw, &[0u32; 4], state.clone());
+            buffer.clear_range(2..5, 1);
+        })
+    }
+
+    #[test]
+    fn map_buffer_ranges() {
+        CONTEXT_STATE.with(|state| {
+            let data: Vec<u32> = (0..8).collect();
+            let mut buffer = Buffer::with_data(BufferUsage::DynamicDraw, &data, state.clone());
+
+            unsafe {
+                let mapped = buffer.map(2..6).unwrap();
+                assert_eq!(&[2, 3, 4, 5], &*mapped);
+                drop(mapped);
+
+                // Ranges past the end of the buffer get clamped.
+                let mut mapped = buffer.map_mut(4..100).unwrap();
+                assert_eq!(4, mapped.len());
+                mapped.copy_from_slice(&[40, 41, 42, 43]);
+                drop(mapped);
+
+                assert_eq!(0, buffer.map_mut(8..).unwrap().len());
+
+                let mut read = vec![0; 8];
+                buffer.get_data(0, &mut read);
+                assert_eq!(vec![0, 1, 2, 3, 40, 41, 42, 43], read);
+            }
+        })
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn static_rewrites_are_reported() {
+        use std::cell::RefCell;
+
+        CONTEXT_STATE.with(|state| {
+            let reports = Rc::new(RefCell::new(Vec::new()));
+            let callback_reports = reports.clone();
+            state.set_diagnostic_callback(Some(Box::new(move |diagnostic| {
+                callback_reports.borrow_mut().push(diagnostic.clone())
+            })));
+
+            let mut dynamic =
+                Buffer::with_data(BufferUsage::DynamicDraw, &[0u32; 4], state.clone());
+            dynamic.sub_data(0, &[1; 4]);
+            dynamic.sub_data(0, &[2; 4]);
+            assert!(reports.borrow().is_empty());
+
+            // Only the second write within a frame gets reported, and only once.
+            let mut buffer = Buffer::with_data(BufferUsage::StaticDraw, &[0u32; 4], state.clone());
+            buffer.sub_data(0, &[1; 4]);
+            assert!(reports.borrow().is_empty());
+            buffer.sub_data(0, &[2; 4]);
+            buffer.sub_data(0, &[3; 4]);
+            state.set_diagnostic_callback(None);
+            assert_eq!(
+                vec![Diagnostic::StaticBufferRewritten {
+                    buffer: buffer.handle()
+                }],
+                *reports.borrow()
+            );
+        })
+    }
+
+    #[test]
+    fn storage_flags_are_validated() {
+        CONTEXT_STATE.with(|state| {
+            use self::BufferStorageFlags as F;
+            let with_storage =
+                |flags, data: &[u32]| Buffer::with_storage(flags, data, state.clone()).map(|_| ());
+            if !state.buffer_storage {
+                assert_eq!(
+                    Err(BufferStorageError::Unsupported),
+                    with_storage(F::empty(), &[0])
+                );
+                return;
+            }
+
+            assert_eq!(Ok(()), with_storage(F::empty(), &[0]));
+            assert_eq!(
+                Ok(()),
+                with_storage(F::MAP_READ | F::MAP_PERSISTENT | F::MAP_COHERENT, &[0])
+            );
+            assert_eq!(
+                Err(BufferStorageError::PersistentWithoutMapAccess),
+                with_storage(F::MAP_PERSISTENT, &[0])
+            );
+            assert_eq!(
+                Err(BufferStorageError::PersistentWithoutMapAccess),
+                with_storage(
+                    F::DYNAMIC_STORAGE | F::MAP_PERSISTENT | F::MAP_COHERENT,
+                    &[0]
+                )
+            );
+            assert_eq!(
+                Err(BufferStorageError::CoherentWithoutPersistent),
+                with_storage(F::MAP_WRITE | F::MAP_COHERENT, &[0])
+            );
+            assert_eq!(
+                Err(BufferStorageError::Empty),
+                with_storage(F::MAP_WRITE, &[])
+            );
+        })
+    }
+
+    #[test]
+    fn persistent_map_writes_are_visible() {
+        CONTEXT_STATE.with(|state| {
+            let flags = BufferStorageFlags::MAP_WRITE
+                | BufferStorageFlags::MAP_PERSISTENT
+                | BufferStorageFlags::MAP_COHERENT;
+            let buffer = match Buffer::with_storage(flags, &[0u32; 4], state.clone()) {
+                Ok(buffer) => buffer,
+                Err(BufferStorageError::Unsupported) => return,
+                Err(e) => panic!("{}", e),
+            };
+
+            unsafe {
+                let mut map = buffer.map_persistent().unwrap();
+                for frame in 1..3 {
+                    map.as_mut_slice().copy_from_slice(&[frame; 4]);
+
+                    let mut read = [0; 4];
+                    buffer.get_data(0, &mut read);
+                    assert_eq!([frame; 4], read);
+                }
+
+                // Dropping the map unmaps the buffer, so it can be mapped again.
+                drop(map);
+                buffer.map_persistent().unwrap().as_mut_slice()[0] = 7;
+                let mut read = [0; 4];
+                buffer.get_data(0, &mut read);
+                assert_eq!([7, 2, 2, 2], read);
+            }
+        })
+    }
 }
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::AllocError;
 use crate::{ContextState, Handle};
 
 use crate::gl::{self, types::*, Gl};
@@ -63,20 +64,83 @@ const USAGE_OFFSET: GLenum = 35039;
 ///
 /// This doesn't actually change how the buffer behaves, but can potentially impact how the GPU
 /// stores a buffer and how fast certain operations are.
+///
+/// The first half of each variant's name says how often the contents change:
+///
+/// * `Stream`: written once, and used at most a few times before being rewritten.
+/// * `Static`: written once, and used many times.
+/// * `Dynamic`: rewritten repeatedly, and used many times between writes.
+///
+/// The second half says who writes and reads them:
+///
+/// * `Draw`: the CPU writes the contents, and the GPU reads them.
+/// * `Read`: the GPU writes the contents, and the CPU reads them back.
+/// * `Copy`: the GPU both writes and reads the contents.
+///
+/// | Use case                                      | Usage         |
+/// |-----------------------------------------------|---------------|
+/// | Static mesh geometry                          | `StaticDraw`  |
+/// | Per-frame uniforms or instance data           | `DynamicDraw` |
+/// | UI geometry rebuilt for every draw            | `StreamDraw`  |
+/// | Screenshot or occlusion readback              | `StreamRead`  |
+/// | GPU compute output read by the CPU            | `DynamicRead` |
+/// | Transform feedback output fed back into draws | `DynamicCopy` |
+/// | Lookup tables generated once on the GPU       | `StaticCopy`  |
+///
+/// In debug builds, writing a `StaticDraw` buffer more than once within a frame's time (1/60th of
+/// a second) reports [`Diagnostic::StaticBufferRewritten`] to the context's diagnostic callback,
+/// since that usually means `DynamicDraw` or `StreamDraw` is a better fit.
+///
+/// [`Diagnostic::StaticBufferRewritten`]: ../enum.Diagnostic.html#variant.StaticBufferRewritten
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum BufferUsage {
+    /// Written by the CPU once, and drawn from by the GPU at most a few times.
     StreamDraw = (gl::STREAM_DRAW - USAGE_OFFSET) as u8,
+    /// Written by the GPU once, and read back by the CPU at most a few times.
     StreamRead = (gl::STREAM_READ - USAGE_OFFSET) as u8,
+    /// Written by the GPU once, and read by the GPU at most a few times.
     StreamCopy = (gl::STREAM_COPY - USAGE_OFFSET) as u8,
+    /// Written by the CPU once, and drawn from by the GPU many times.
     StaticDraw = (gl::STATIC_DRAW - USAGE_OFFSET) as u8,
+    /// Written by the GPU once, and read back by the CPU many times.
     StaticRead = (gl::STATIC_READ - USAGE_OFFSET) as u8,
+    /// Written by the GPU once, and read by the GPU many times.
     StaticCopy = (gl::STATIC_COPY - USAGE_OFFSET) as u8,
+    /// Rewritten by the CPU repeatedly, and drawn from by the GPU many times.
     DynamicDraw = (gl::DYNAMIC_DRAW - USAGE_OFFSET) as u8,
+    /// Rewritten by the GPU repeatedly, and read back by the CPU many times.
     DynamicRead = (gl::DYNAMIC_READ - USAGE_OFFSET) as u8,
+    /// Rewritten by the GPU repeatedly, and read by the GPU many times.
     DynamicCopy = (gl::DYNAMIC_COPY - USAGE_OFFSET) as u8,
 }
 
+bitflags! {
+    /// Flags for buffers created with immutable storage.
+    ///
+    /// Unlike [`BufferUsage`], these are requirements rather than hints: OpenGL raises an error
+    /// if the buffer gets accessed in a way its flags don't allow.
+    ///
+    /// [`BufferUsage`]: ./enum.BufferUsage.html
+    pub struct BufferStorageFlags: u32 {
+        /// Allow the buffer's contents to be modified with `sub_data`. Copying into the buffer
+        /// with `copy_to` is always allowed.
+        const DYNAMIC_STORAGE = gl::DYNAMIC_STORAGE_BIT;
+        /// Allow the buffer to be mapped for reading.
+        const MAP_READ = gl::MAP_READ_BIT;
+        /// Allow the buffer to be mapped for writing.
+        const MAP_WRITE = gl::MAP_WRITE_BIT;
+        /// Allow the buffer to stay mapped while the GPU uses it. Requires `MAP_READ` or
+        /// `MAP_WRITE`.
+        const MAP_PERSISTENT = gl::MAP_PERSISTENT_BIT;
+        /// Make writes to a persistent mapping visible to the GPU without an explicit barrier.
+        /// Requires `MAP_PERSISTENT`.
+        const MAP_COHERENT = gl::MAP_COHERENT_BIT;
+        /// Hint that the buffer should be stored in client memory instead of GPU memory.
+        const CLIENT_STORAGE = gl::CLIENT_STORAGE_BIT;
+    }
+}
+
 pub unsafe trait RawBindTarget: 'static + Sized {
     const TARGET: GLenum;
     fn bound_buffer(&self) -> &Cell<Option<Handle>>;
@@ -227,6 +291,57 @@ where
         }
     }
 
+    /// Read the entire buffer into a newly allocated `Vec`.
+    ///
+    /// OpenGL writes every element before the length gets set, so no placeholder values are
+    /// needed to fill the `Vec` beforehand.
+    #[inline]
+    pub(crate) unsafe fn get_data_vec(&self) -> Vec<T> {
+        let len = self.buffer.size;
+        let mut buf = Vec::with_capacity(len);
+        if mem::size_of::<T>() != 0 {
+            self.gl.GetBufferSubData(
+                B::TARGET,
+                0,
+                (len * mem::size_of::<T>()) as GLsizeiptr,
+                buf.as_mut_ptr() as *mut GLvoid,
+            );
+        }
+        buf.set_len(len);
+        buf
+    }
+
+    /// Map `len` elements, starting at `offset`, into client memory.
+    ///
+    /// Returns `Err` with the OpenGL error if the driver couldn't map the range.
+    #[inline]
+    pub(crate) unsafe fn map_range(
+        &self,
+        offset: usize,
+        len: usize,
+        access: GLbitfield,
+    ) -> Result<*mut T, GLenum> {
+        assert!(offset + len <= self.buffer.size);
+        let ptr = self.gl.MapBufferRange(
+            B::TARGET,
+            (offset * mem::size_of::<T>()) as GLintptr,
+            (len * mem::size_of::<T>()) as GLsizeiptr,
+            access,
+        );
+        if ptr.is_null() {
+            Err(self.gl.GetError())
+        } else {
+            Ok(ptr as *mut T)
+        }
+    }
+
+    /// Unmap the buffer. Returns `false` if the buffer's contents got corrupted while it was
+    /// mapped.
+    #[inline]
+    pub(crate) unsafe fn unmap(&self) -> bool {
+        self.gl.UnmapBuffer(B::TARGET) == gl::TRUE
+    }
+
     #[inline]
     pub(crate) fn copy_to<C, R>(
         &self,
@@ -296,6 +411,36 @@ where
         }
     }
 
+    /// Fill `len` elements, starting at `offset`, with `value`.
+    ///
+    /// Returns `false` without touching the buffer if there's no integer format the size of `T`
+    /// that OpenGL could use to replicate `value`.
+    #[inline]
+    pub(crate) unsafe fn clear_sub_data(&mut self, offset: usize, len: usize, value: &T) -> bool {
+        assert!(offset + len <= self.buffer.size);
+        let (internal_format, format, ty) = match mem::size_of::<T>() {
+            1 => (gl::R8UI, gl::RED_INTEGER, gl::UNSIGNED_BYTE),
+            2 => (gl::R16UI, gl::RED_INTEGER, gl::UNSIGNED_SHORT),
+            4 => (gl::R32UI, gl::RED_INTEGER, gl::UNSIGNED_INT),
+            8 => (gl::RG32UI, gl::RG_INTEGER, gl::UNSIGNED_INT),
+            12 => (gl::RGB32UI, gl::RGB_INTEGER, gl::UNSIGNED_INT),
+            16 => (gl::RGBA32UI, gl::RGBA_INTEGER, gl::UNSIGNED_INT),
+            _ => return false,
+        };
+        if len > 0 {
+            self.gl.ClearBufferSubData(
+                B::TARGET,
+                internal_format,
+                (offset * mem::size_of::<T>()) as GLintptr,
+                (len * mem::size_of::<T>()) as GLsizeiptr,
+                format,
+                ty,
+                value as *const T as *const GLvoid,
+            );
+        }
+        true
+    }
+
     #[inline]
     pub(crate) unsafe fn alloc_size(&mut self, size: usize, usage: BufferUsage) {
         assert!(size <= isize::max_value() as usize);
@@ -320,8 +465,35 @@ where
         }
     }
 
+    /// Allocate immutable storage for the buffer, initialized with `data`.
+    #[inline]
+    pub(crate) unsafe fn alloc_storage(&mut self, data: &[T], flags: BufferStorageFlags) {
+        assert!(data.len() <= isize::MAX as usize);
+        if mem::size_of::<T>() != 0 {
+            self.gl.BufferStorage(
+                B::TARGET,
+                mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                flags.bits(),
+            );
+
+            let error = self.gl.GetError();
+            if error == 0 {
+                self.buffer.size = data.len();
+            } else if error == gl::OUT_OF_MEMORY {
+                panic!("OpenGL out of memory!");
+            } else {
+                panic!("Unexpected OpenGL error: {}", error);
+            }
+        }
+    }
+
     #[inline]
-    pub(crate) fn alloc_upload(&mut self, data: &[T], usage: BufferUsage) {
+    pub(crate) fn alloc_upload(
+        &mut self,
+        data: &[T],
+        usage: BufferUsage,
+    ) -> Result<(), AllocError> {
         assert!(data.len() <= isize::max_value() as usize);
         if mem::size_of::<T>() != 0 {
             unsafe {
@@ -336,14 +508,15 @@ where
             let error = unsafe { self.gl.GetError() };
             if error == 0 {
                 self.buffer.size = data.len();
+            } else if error == gl::OUT_OF_MEMORY {
+                return Err(AllocError {
+                    bytes: mem::size_of_val(data),
+                });
             } else {
-                if error == gl::OUT_OF_MEMORY {
-                    panic!("OpenGL out of memory!");
-                } else {
-                    panic!("Unexpected OpenGL error: {}", error);
-                }
+                panic!("Unexpected OpenGL error: {}", error);
             }
         }
+        Ok(())
     }
 }
 
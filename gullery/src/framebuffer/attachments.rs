@@ -16,12 +16,13 @@
 
 use crate::{
     framebuffer::Renderbuffer,
-    geometry::Dimension,
+    geometry::{Dimension, D3},
+    gl::{self, types::*},
     image_format::{FormatType, FormatTypeTag, ImageFormatRenderable},
-    texture::{MipSelector, Texture, TextureType},
-    GLObject, Handle,
+    texture::{Dims, MipSelector, Texture, TextureType},
+    ContextState, GLObject, Handle,
 };
-use std::marker::PhantomData;
+use std::{marker::PhantomData, rc::Rc};
 
 /// A Rust type that can be used as a [`FramebufferObject`] attachment.
 ///
@@ -55,6 +56,27 @@ pub trait AttachmentType: GLObject {
 /// - Color outputs, for shaders
 /// - Depth attachments, for the depth test
 /// - Stencil attachments, for the stencil test
+///
+/// A framebuffer has a single depth attachment point, so deriving `Attachments` on a struct with
+/// more than one depth or depth-stencil attachment is a compile error:
+///
+/// ```rust,compile_fail
+/// # extern crate gullery;
+/// # #[macro_use]
+/// # extern crate gullery_macros;
+/// use gullery::{
+///     geometry::D2,
+///     image_format::{Depth24Stencil8, Depth32F},
+///     texture::Texture,
+/// };
+///
+/// #[derive(Attachments)]
+/// struct TwoDepthAttachments {
+///     depth: Texture<D2, Depth32F>,
+///     depth_stencil: Texture<D2, Depth24Stencil8>,
+/// }
+/// # fn main() {}
+/// ```
 pub trait Attachments: Sized {
     type AHC: AttachmentHandleContainer;
     type Static: 'static + Attachments<AHC = Self::AHC>;
@@ -83,6 +105,30 @@ pub trait Attachments: Sized {
         num
     }
 
+    /// The number of members that have a color image format.
+    #[inline]
+    fn num_color_members() -> usize {
+        struct ColorMemberCounter<'a, A>(&'a mut usize, PhantomData<A>);
+        impl<'a, A: Attachments> AttachmentsMemberRegistryNoSpecifics for ColorMemberCounter<'a, A> {
+            type Attachments = A;
+            #[inline(always)]
+            fn add_member<At: AttachmentType>(
+                &mut self,
+                _: &str,
+                _: impl FnOnce(&Self::Attachments) -> &At,
+            ) {
+                let image_type = <At::Format as ImageFormatRenderable>::FormatType::FORMAT_TYPE;
+                if image_type == FormatTypeTag::Color {
+                    *self.0 += 1;
+                }
+            }
+        }
+
+        let mut num = 0;
+        Self::members(AMRNSImpl(ColorMemberCounter::<Self>(&mut num, PhantomData)));
+        num
+    }
+
     fn color_attachments(&self, for_each: impl FnMut(u8)) {
         struct AttachmentRefMatcher<'a, A: 'a, F: FnMut(u8)> {
             color_index: u8,
@@ -113,11 +159,45 @@ pub trait Attachments: Sized {
     }
 }
 
+/// Renders into a specific mip level and layer of a texture.
+///
+/// Attaching a [`Texture`] directly renders into its base mip level and, if the texture is layered,
+/// into all of its layers. Wrapping the texture in this renders into the selected mip level
+/// instead, which is useful for manually generating mipmaps. Passing a layer renders into a single
+/// layer of an array, cubemap, or 3D texture, which is useful for rendering each cascade of a
+/// cascaded shadow map into its own array layer.
+///
+/// For cubemap textures, the layer selects the cube face, in the order `+X`, `-X`, `+Y`, `-Y`,
+/// `+Z`, `-Z`. For 3D textures, the layer selects the Z-slice, which lets volumetric effects
+/// render into each slice in turn.
+///
+/// [`Texture`]: ../../texture/struct.Texture.html
+pub struct TextureAttachment<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: TextureType<D>,
+    T::Format: ImageFormatRenderable,
+{
+    texture: &'a mut Texture<D, T>,
+    mip_level: T::MipSelector,
+    layer: Option<u32>,
+}
+
+/// The image attached to a single attachment point, as last uploaded to OpenGL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttachedImage {
+    handle: Handle,
+    mip_level: GLint,
+    layer: Option<u32>,
+}
+
 /// Container of raw OpenGL attachment handles.
 ///
 /// Can generally be ignored by the end user. Is used as optimization for reducing the number
 /// of state-change calls to OpenGL.
-pub trait AttachmentHandleContainer: AsRef<[Option<Handle>]> + AsMut<[Option<Handle>]> {
+pub trait AttachmentHandleContainer:
+    AsRef<[Option<AttachedImage>]> + AsMut<[Option<AttachedImage>]>
+{
     fn new_zeroed() -> Self;
 }
 
@@ -131,11 +211,14 @@ pub trait AttachmentsMemberRegistry {
         name: &str,
         get_member: impl FnOnce(&Self::Attachments) -> &Renderbuffer<I>,
     );
+    /// Add a texture attachment.
+    ///
+    /// `get_member` returns the texture, the mip level to render into, and the layer to render
+    /// into. If the layer is `None`, all of the texture's layers get attached.
     fn add_texture<D, T>(
         &mut self,
         name: &str,
-        get_member: impl FnOnce(&Self::Attachments) -> &Texture<D, T>,
-        texture_level: T::MipSelector,
+        get_member: impl FnOnce(&Self::Attachments) -> (&Texture<D, T>, T::MipSelector, Option<u32>),
     ) where
         D: Dimension<u32>,
         T: TextureType<D>,
@@ -170,22 +253,21 @@ where
     fn add_texture<D, T>(
         &mut self,
         name: &str,
-        get_member: impl FnOnce(&Self::Attachments) -> &Texture<D, T>,
-        _: T::MipSelector,
+        get_member: impl FnOnce(&Self::Attachments) -> (&Texture<D, T>, T::MipSelector, Option<u32>),
     ) where
         D: Dimension<u32>,
         T: TextureType<D>,
         T::Format: ImageFormatRenderable,
     {
-        self.0.add_member(name, get_member);
+        self.0.add_member(name, |a| get_member(a).0);
     }
 }
 
 macro_rules! impl_attachment_array {
     ($($len:expr),*) => {$(
-        impl AttachmentHandleContainer for [Option<Handle>; $len] {
+        impl AttachmentHandleContainer for [Option<AttachedImage>; $len] {
             #[inline]
-            fn new_zeroed() -> [Option<Handle>; $len] {
+            fn new_zeroed() -> [Option<AttachedImage>; $len] {
                 [None; $len]
             }
         }
@@ -198,7 +280,7 @@ impl_attachment_array! {
 }
 
 impl Attachments for () {
-    type AHC = [Option<Handle>; 0];
+    type AHC = [Option<AttachedImage>; 0];
     type Static = Self;
 
     fn members<R>(_reg: R)
@@ -241,7 +323,139 @@ where
     ) where
         R: AttachmentsMemberRegistry,
     {
-        registry.add_texture(name, |r| get_member(r), mip);
+        registry.add_texture(name, |r| (get_member(r), mip, None));
+    }
+}
+
+impl<'a, D, T> TextureAttachment<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: TextureType<D>,
+    T::Format: ImageFormatRenderable,
+{
+    /// Select a mip level and, optionally, a single layer of `texture` to render into.
+    ///
+    /// ## Panics
+    /// Panics if `layer` is `Some` and either the texture isn't layered, or `layer` is greater than
+    /// or equal to the number of layers in the selected mip level.
+    pub fn new(
+        texture: &'a mut Texture<D, T>,
+        mip_level: T::MipSelector,
+        layer: Option<u32>,
+    ) -> TextureAttachment<'a, D, T> {
+        if let Some(layer) = layer {
+            let dims = T::mip_dims(texture.dims(), mip_level);
+            let num_layers = match T::BIND_TARGET {
+                gl::TEXTURE_1D_ARRAY => dims.height(),
+                gl::TEXTURE_2D_ARRAY | gl::TEXTURE_2D_MULTISAMPLE_ARRAY | gl::TEXTURE_3D => {
+                    dims.depth()
+                }
+                gl::TEXTURE_CUBE_MAP => 6,
+                _ => panic!("attempted to attach a layer of a texture that isn't layered"),
+            };
+            assert!(
+                layer < num_layers,
+                "attempted to attach layer {}, but texture only has {} layers",
+                layer,
+                num_layers
+            );
+        }
+
+        TextureAttachment {
+            texture,
+            mip_level,
+            layer,
+        }
+    }
+
+    /// The mip level that gets rendered into.
+    #[inline]
+    pub fn mip_level(&self) -> T::MipSelector {
+        self.mip_level
+    }
+
+    /// The layer that gets rendered into, or `None` if all layers get rendered into.
+    #[inline]
+    pub fn layer(&self) -> Option<u32> {
+        self.layer
+    }
+}
+
+impl<'a, C> TextureAttachment<'a, D3, C>
+where
+    C: ImageFormatRenderable,
+{
+    /// Select a single Z-slice of a mip level in a 3D texture to render into.
+    ///
+    /// ## Panics
+    /// Panics if `z` is greater than or equal to the depth of the selected mip level.
+    #[inline]
+    pub fn slice(
+        texture: &'a mut Texture<D3, C>,
+        mip_level: u8,
+        z: u32,
+    ) -> TextureAttachment<'a, D3, C> {
+        TextureAttachment::new(texture, mip_level, Some(z))
+    }
+}
+
+impl<'a, D, T> GLObject for TextureAttachment<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: TextureType<D>,
+    T::Format: ImageFormatRenderable,
+{
+    #[inline(always)]
+    fn handle(&self) -> Handle {
+        self.texture.handle()
+    }
+    #[inline]
+    fn state(&self) -> &Rc<ContextState> {
+        self.texture.state()
+    }
+}
+
+impl<'a, D, T> AttachmentType for TextureAttachment<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: TextureType<D>,
+    T::Format: ImageFormatRenderable,
+{
+    type Format = T::Format;
+    type MipSelector = ();
+
+    fn add_to_registry<R>(
+        registry: &mut R,
+        name: &str,
+        get_member: impl FnOnce(&R::Attachments) -> &Self,
+        _: (),
+    ) where
+        R: AttachmentsMemberRegistry,
+    {
+        use std::mem;
+
+        registry.add_texture(name, |r| {
+            let member = get_member(r);
+            // Transmute for the same reason as the `&mut A` implementation below.
+            let texture =
+                unsafe { mem::transmute::<&Texture<D, T>, &Texture<D, T>>(&*member.texture) };
+            (texture, member.mip_level, member.layer)
+        });
+    }
+
+    fn resolve_reference(&self) -> *const () {
+        &*self.texture as *const Texture<D, T> as *const ()
+    }
+}
+
+impl AttachedImage {
+    #[inline]
+    pub(crate) fn new(handle: Handle, mip_level: GLint, layer: Option<u32>) -> AttachedImage {
+        AttachedImage {
+            handle,
+            mip_level,
+            layer,
+        }
     }
 }
 
@@ -0,0 +1,182 @@
+// Copyright 2018 Osspial
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Render targets for drawing into every face of a cubemap.
+//!
+//! Environment captures and point light shadows need the scene rendered six times, once per cube
+//! face, each with its own view matrix. [`CubemapRenderTarget::for_each_face`] attaches each face
+//! in turn and hands it to a closure that issues that face's draw calls.
+//!
+//! [`CubemapRenderTarget::for_each_face`]: ./struct.CubemapRenderTarget.html#method.for_each_face
+
+use super::{
+    attachments::{
+        AttachedImage, AttachmentType, Attachments, AttachmentsMemberRegistry, TextureAttachment,
+    },
+    FramebufferObject, FramebufferObjectAttached, Renderbuffer,
+};
+use crate::{
+    geometry::{GLVec2, D2},
+    image_format::{ConcreteImageFormat, Depth32F, ImageFormatRenderable},
+    texture::{types::CubemapTex, DimsSquare, Texture, TextureCreateError},
+    ContextState,
+};
+
+use std::rc::Rc;
+
+/// A face of a cubemap, in the order OpenGL stores them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+/// The attachments used when rendering into a single face of a [`CubemapRenderTarget`].
+///
+/// [`CubemapRenderTarget`]: ./struct.CubemapRenderTarget.html
+pub struct CubemapAttachments<'a, C: ImageFormatRenderable> {
+    pub color: TextureAttachment<'a, D2, CubemapTex<C>>,
+    pub depth: &'a mut Renderbuffer<Depth32F>,
+}
+
+/// A cubemap texture paired with a depth buffer and the framebuffer needed to render into each of
+/// its faces.
+pub struct CubemapRenderTarget<C: ImageFormatRenderable> {
+    texture: Texture<D2, CubemapTex<C>>,
+    depth: Renderbuffer<Depth32F>,
+    fbo: FramebufferObject<CubemapAttachments<'static, C>>,
+}
+
+impl CubeFace {
+    /// Every face, in the order OpenGL stores them.
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// The face's layer index, as used by [`TextureAttachment::new`].
+    ///
+    /// [`TextureAttachment::new`]: ../attachments/struct.TextureAttachment.html#method.new
+    #[inline]
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+impl<'a, C: ImageFormatRenderable> Attachments for CubemapAttachments<'a, C> {
+    type AHC = [Option<AttachedImage>; 2];
+    type Static = CubemapAttachments<'static, C>;
+
+    #[inline]
+    #[allow(clippy::unit_arg)]
+    fn members<M>(mut reg: M)
+    where
+        M: AttachmentsMemberRegistry<Attachments = Self>,
+    {
+        <TextureAttachment<'a, D2, CubemapTex<C>> as AttachmentType>::add_to_registry(
+            &mut reg,
+            "color",
+            |t| &t.color,
+            Default::default(),
+        );
+        <&'a mut Renderbuffer<Depth32F> as AttachmentType>::add_to_registry(
+            &mut reg,
+            "depth",
+            |t| &t.depth,
+            Default::default(),
+        );
+    }
+}
+
+impl<C> CubemapRenderTarget<C>
+where
+    C: ImageFormatRenderable + ConcreteImageFormat,
+{
+    /// Create a cubemap render target, with each face `dims.side` pixels wide and tall.
+    ///
+    /// The cubemap gets a single mip level. Call [`Texture::generate_mipmaps`] on [`texture_mut`]
+    /// after rendering if the cubemap will be sampled with mipmapped filtering.
+    ///
+    /// [`Texture::generate_mipmaps`]: ../../texture/struct.Texture.html#method.generate_mipmaps
+    /// [`texture_mut`]: #method.texture_mut
+    pub fn new(
+        dims: DimsSquare,
+        state: Rc<ContextState>,
+    ) -> Result<CubemapRenderTarget<C>, TextureCreateError<D2, CubemapTex<C>>> {
+        let texture = Texture::with_mip_count(dims, 1, state.clone())?;
+        let depth = Renderbuffer::new(GLVec2::new(dims.side, dims.side), 0, state.clone());
+        let fbo = FramebufferObject::new(state)
+            .expect("single color attachment can't exceed the color attachment limit");
+
+        Ok(CubemapRenderTarget {
+            texture,
+            depth,
+            fbo,
+        })
+    }
+}
+
+impl<C: ImageFormatRenderable> CubemapRenderTarget<C> {
+    /// Call `render` once for every cube face, with a framebuffer that renders into that face.
+    ///
+    /// The faces are visited in the order given by [`CubeFace::ALL`]. Each face shares the same
+    /// depth buffer, so `render` should clear depth before drawing.
+    ///
+    /// [`CubeFace::ALL`]: ./enum.CubeFace.html#associatedconstant.ALL
+    pub fn for_each_face<F>(&mut self, mut render: F)
+    where
+        F: FnMut(
+            CubeFace,
+            &mut FramebufferObjectAttached<
+                CubemapAttachments<'_, C>,
+                &mut FramebufferObject<CubemapAttachments<'static, C>>,
+            >,
+        ),
+    {
+        for &face in CubeFace::ALL.iter() {
+            let mut framebuffer = FramebufferObjectAttached {
+                fbo: &mut self.fbo,
+                attachments: CubemapAttachments {
+                    color: TextureAttachment::new(&mut self.texture, 0, Some(face.index())),
+                    depth: &mut self.depth,
+                },
+            };
+            render(face, &mut framebuffer);
+        }
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Texture<D2, CubemapTex<C>> {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn texture_mut(&mut self) -> &mut Texture<D2, CubemapTex<C>> {
+        &mut self.texture
+    }
+
+    /// Destroy the render target, returning the cubemap texture.
+    #[inline]
+    pub fn unwrap(self) -> Texture<D2, CubemapTex<C>> {
+        self.texture
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2018 Osspial
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helper for running a fragment shader over every pixel of a framebuffer.
+//!
+//! A [`FullscreenPass`] owns a single triangle large enough to cover the whole viewport, which is
+//! the usual way to run post-processing effects. Compile [`VERTEX_SHADER`] together with your own
+//! fragment shader, which receives the pixel's texture coordinates in `in vec2 uv`, then draw it
+//! with [`FullscreenPass::run`].
+//!
+//! [`FullscreenPass`]: ./struct.FullscreenPass.html
+//! [`VERTEX_SHADER`]: ./constant.VERTEX_SHADER.html
+//! [`FullscreenPass::run`]: ./struct.FullscreenPass.html#method.run
+
+use super::{render_state::RenderState, DrawMode, Framebuffer};
+use crate::{
+    buffer::{Buffer, BufferUsage},
+    geometry::{GLVec2, NonNormalized},
+    program::Program,
+    uniform::Uniforms,
+    vertex::{Vertex, VertexArrayObject, VertexMemberRegistry},
+    ContextState,
+};
+
+use std::rc::Rc;
+
+/// Vertex shader for use with [`FullscreenPass`].
+///
+/// Passes the position through unchanged, and outputs `vec2 uv` ranging from `(0, 0)` in the
+/// bottom-left corner of the viewport to `(1, 1)` in the top-right.
+///
+/// [`FullscreenPass`]: ./struct.FullscreenPass.html
+pub const VERTEX_SHADER: &str = "
+    #version 330
+
+    in vec2 position;
+    out vec2 uv;
+
+    void main() {
+        uv = position * 0.5 + 0.5;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+";
+
+/// The vertex type read by [`VERTEX_SHADER`]. Programs passed to [`FullscreenPass::run`] must be
+/// created with this vertex type.
+///
+/// [`VERTEX_SHADER`]: ./constant.VERTEX_SHADER.html
+/// [`FullscreenPass::run`]: ./struct.FullscreenPass.html#method.run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FullscreenVertex {
+    /// Position in normalized device coordinates.
+    pub position: GLVec2<f32>,
+}
+
+impl Vertex for FullscreenVertex {
+    #[inline]
+    fn members<M>(mut reg: M)
+    where
+        M: VertexMemberRegistry<Group = Self>,
+    {
+        reg.add_member("position", |t| unsafe { &(*t).position });
+    }
+}
+
+/// A triangle that covers the entire viewport, for running post-processing shaders.
+///
+/// One oversized triangle is used instead of a two-triangle quad, so no fragments get shaded
+/// twice along the diagonal seam. Create one `FullscreenPass` and reuse it for every pass.
+pub struct FullscreenPass {
+    vao: VertexArrayObject<FullscreenVertex, !>,
+}
+
+impl FullscreenPass {
+    pub fn new(state: Rc<ContextState>) -> FullscreenPass {
+        let vertices = [
+            FullscreenVertex {
+                position: GLVec2::new(-1.0, -1.0),
+            },
+            FullscreenVertex {
+                position: GLVec2::new(3.0, -1.0),
+            },
+            FullscreenVertex {
+                position: GLVec2::new(-1.0, 3.0),
+            },
+        ];
+        let vertex_buffer = Buffer::with_data(BufferUsage::StaticDraw, &vertices, state);
+        FullscreenPass {
+            vao: VertexArrayObject::new(vertex_buffer, None),
+        }
+    }
+
+    /// A render state suited to fullscreen passes over a framebuffer with the given dimensions.
+    ///
+    /// The viewport covers the whole framebuffer, and depth testing, depth writes, culling, and
+    /// blending are all disabled. Change the returned fields to, for example, blend the pass over
+    /// the framebuffer's existing contents.
+    pub fn render_state(dims: GLVec2<u32, NonNormalized>) -> RenderState {
+        RenderState {
+            cull: None,
+            depth_test: None,
+            depth_mask: false,
+            stencil_test: None,
+            viewport: GLVec2::new(0, 0)..=dims,
+            ..RenderState::default()
+        }
+    }
+
+    /// Run `program` over every pixel in `render_state`'s viewport.
+    ///
+    /// `program` must be created from [`VERTEX_SHADER`], or another vertex shader that reads
+    /// `in vec2 position`.
+    ///
+    /// [`VERTEX_SHADER`]: ./constant.VERTEX_SHADER.html
+    #[inline]
+    pub fn run<F, U>(
+        &self,
+        framebuffer: &mut F,
+        program: &Program<FullscreenVertex, U::Static, F::AttachmentsStatic>,
+        uniforms: &U,
+        render_state: &RenderState,
+    ) where
+        F: Framebuffer,
+        U: Uniforms,
+    {
+        framebuffer.draw(
+            DrawMode::Triangles,
+            ..,
+            &self.vao,
+            program,
+            uniforms,
+            render_state,
+        );
+    }
+}
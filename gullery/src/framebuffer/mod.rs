@@ -24,33 +24,49 @@
 //! [`ImageFormatRenderable`]: ../image_format/trait.ImageFormatRenderable.html
 
 pub mod attachments;
+pub mod cubemap_target;
+pub mod fullscreen_pass;
 mod raw;
 pub mod render_state;
 pub(crate) mod renderbuffer;
+pub mod shadow_map;
 
+pub(crate) use self::raw::draw_vao;
 use self::{attachments::*, raw::*};
-pub use self::{raw::DrawMode, renderbuffer::Renderbuffer};
+pub use self::{
+    raw::{DefaultBuffer, DrawMode},
+    renderbuffer::Renderbuffer,
+};
 use std::borrow::BorrowMut;
 
 use self::render_state::RenderState;
 use crate::{
+    buffer::Buffer,
+    geometry::{Dimension, GLVec2, NonNormalized, D2},
     gl::{self, types::*, Gl},
-    geometry::{GLVec2, NonNormalized},
     image_format::{ConcreteImageFormat, FormatType, FormatTypeTag, ImageFormatRenderable, Rgba},
     program::Program,
+    texture::{sample_parameters::FilterMag, types::MultisampleTex, Dims, Texture, TextureType},
     uniform::Uniforms,
     vertex::{Index, Vertex, VertexArrayObject},
-    ContextState, Handle,
+    ContextState, GLObject,
 };
 
 use std::{
+    cell::RefCell,
+    error::Error,
+    fmt, io,
     ops::{RangeBounds, RangeInclusive},
+    ptr,
     rc::Rc,
 };
 
 pub(crate) struct FramebufferTargets {
     read: RawFramebufferTargetRead,
     draw: RawFramebufferTargetDraw,
+    /// Read and draw framebuffers used for internal blits and queries. Created the first time
+    /// they're needed and reused afterwards, so those operations don't churn framebuffer objects.
+    scratch: RefCell<Option<(RawFramebufferObject, RawFramebufferObject)>>,
 }
 
 /// The default back framebuffer.
@@ -90,10 +106,15 @@ where
     pub attachments: A,
 }
 
+#[derive(Debug, Clone)]
+pub enum FramebufferCreateError {
+    TooManyColorAttachments { requested: usize, max: usize },
+}
+
 #[doc(hidden)]
 pub struct AttachmentsRefMut<'a, A: 'a + Attachments> {
     attachments: &'a mut A,
-    ahc: &'a mut [Option<Handle>],
+    ahc: &'a mut [Option<AttachedImage>],
 }
 
 impl<A, F> FramebufferObjectAttached<A, F>
@@ -132,7 +153,13 @@ pub trait Framebuffer {
     /// For the default framebuffer, this clears the window associated with said framebuffer. If you
     /// want to clear an individual color attachment, see [`clear_color_attachment`].
     ///
+    /// Only the channels enabled in the [`color_mask`] of the most recently uploaded render state
+    /// get cleared, matching how OpenGL treats color clears. Use [`clear_color_all_forced`] to
+    /// clear every channel regardless of the mask.
+    ///
     /// [`clear_color_attachment`]: ./struct.FramebufferObjectAttached.html#method.clear_color_attachment
+    /// [`color_mask`]: ./render_state/struct.RenderState.html#structfield.color_mask
+    /// [`clear_color_all_forced`]: #method.clear_color_all_forced
     #[inline]
     fn clear_color_all(&mut self, color: Rgba<f32>) {
         let (raw_mut, arm, state) = self.raw_mut();
@@ -145,6 +172,56 @@ pub trait Framebuffer {
         }
     }
 
+    /// Clears every channel of all attached color buffers to `color`, ignoring the render state's
+    /// [`color_mask`].
+    ///
+    /// The color mask is set to all channels for the clear and restored afterwards, so subsequent
+    /// draws aren't affected.
+    ///
+    /// [`color_mask`]: ./render_state/struct.RenderState.html#structfield.color_mask
+    #[inline]
+    fn clear_color_all_forced(&mut self, color: Rgba<f32>) {
+        let (raw_mut, arm, state) = self.raw_mut();
+        unsafe {
+            let mut framebuffer_bind = state.framebuffer_targets.draw.bind(raw_mut, &state.gl);
+            framebuffer_bind.set_attachments(arm.ahc, arm.attachments);
+            render_state::with_full_color_mask(state, || {
+                arm.attachments.color_attachments(|attachment_index| {
+                    framebuffer_bind.clear_color_attachment(color, attachment_index);
+                });
+            });
+        }
+    }
+
+    /// Clears the pixels in `rect` of all attached color buffers to `color`, leaving the rest of
+    /// the framebuffer untouched.
+    ///
+    /// Like [`read_pixels`], `rect`'s end is one past the last cleared pixel. The scissor test
+    /// gets enabled for the clear and restored to its previous state afterwards, so subsequent
+    /// draws aren't affected.
+    ///
+    /// ## Panics
+    /// Panics if `rect`'s start is past its end on either axis.
+    ///
+    /// [`read_pixels`]: ./struct.FramebufferDefault.html#method.read_pixels
+    #[inline]
+    fn clear_color_rect<V>(&mut self, color: Rgba<f32>, rect: RangeInclusive<V>)
+    where
+        V: Into<GLVec2<u32, NonNormalized>>,
+    {
+        let (start, end) = rect.into_inner();
+        let (raw_mut, arm, state) = self.raw_mut();
+        unsafe {
+            let mut framebuffer_bind = state.framebuffer_targets.draw.bind(raw_mut, &state.gl);
+            framebuffer_bind.set_attachments(arm.ahc, arm.attachments);
+            raw::with_scissor(&state.gl, start.into(), end.into(), || {
+                arm.attachments.color_attachments(|attachment_index| {
+                    framebuffer_bind.clear_color_attachment(color, attachment_index);
+                });
+            });
+        }
+    }
+
     /// Clears the depth buffer attached to this framebuffer to the specified value.
     #[inline]
     fn clear_depth(&mut self, depth: f32) {
@@ -157,6 +234,15 @@ pub trait Framebuffer {
     }
 
     /// Clears the stencil buffer attached to this framebuffer to the specified value.
+    ///
+    /// The depth values of a packed depth-stencil attachment are left untouched.
+    ///
+    /// Only the bits set in the [`stencil_mask`] of the most recently uploaded render state get
+    /// cleared, matching how OpenGL treats stencil clears. If a draw call left the mask at
+    /// something other than all ones, upload a render state with a full mask before clearing or
+    /// the masked-off bits will keep their old values.
+    ///
+    /// [`stencil_mask`]: ./render_state/struct.RenderState.html#structfield.stencil_mask
     #[inline]
     fn clear_stencil(&mut self, stencil: u32) {
         let (raw_mut, arm, state) = self.raw_mut();
@@ -167,21 +253,55 @@ pub trait Framebuffer {
         }
     }
 
+    /// Reads the depth buffer attached to this framebuffer into a newly allocated `Vec`, sized to
+    /// fit `rect`.
+    ///
+    /// Like [`read_pixels`], `rect`'s end is one past the last read pixel, and rows are returned
+    /// bottom to top. The values are the post-projection depths in the `[0, 1]` range, which are
+    /// non-linear in view space; pass them to [`linearize_depth`] to recover eye-space distances.
+    ///
+    /// ## Panics
+    /// Panics if the framebuffer has no depth buffer, or if `rect` extends past the framebuffer.
+    ///
+    /// [`read_pixels`]: ./struct.FramebufferDefault.html#method.read_pixels
+    /// [`linearize_depth`]: ./fn.linearize_depth.html
+    #[inline]
+    fn read_depth<V>(&mut self, rect: RangeInclusive<V>) -> Vec<f32>
+    where
+        V: Into<GLVec2<u32, NonNormalized>>,
+    {
+        let (start, end) = rect.into_inner();
+        let (start, end): (GLVec2<_, _>, GLVec2<_, _>) = (start.into(), end.into());
+        let dims = end - start;
+        let mut data = vec![0.0; (dims.x * dims.y) as usize];
+        let (raw, arm, state) = self.raw_mut();
+        unsafe {
+            let mut framebuffer_bind = state.framebuffer_targets.read.bind(raw, &state.gl);
+            framebuffer_bind.set_attachments(arm.ahc, arm.attachments);
+            framebuffer_bind.read_depth(start, dims, &mut data);
+        }
+        data
+    }
+
     /// Performs a single draw call.
     ///
     /// ## Parameters
     /// * `mode`: The rendering primitive that the vertex array gets interpereted as. See the [`DrawMode`]
     ///   documentation for more information.
     /// * `range`: The range of vertices in the VAO that gets drawn. If the VAO has an index buffer, this is
-    ///   a range into that index array; otherwise, it's a range into the vertex buffer.
+    ///   a range into that index array; otherwise, it's a range into the vertex buffer. Panics if the
+    ///   range extends past [`index_count`] or [`vertex_count`], respectively.
     /// * `program`: The compiled program used to render the vertices.
     /// * `uniform`: The uniforms used by the program. If the program has no uniforms, pass `()`.
     /// * `render_state`: The state parameters used to control rendering.
-    fn draw<R, V, I, U>(
+    ///
+    /// [`index_count`]: ../vertex/struct.VertexArrayObject.html#method.index_count
+    /// [`vertex_count`]: ../vertex/struct.VertexArrayObject.html#method.vertex_count
+    fn draw<R, V, I, N, U>(
         &mut self,
         mode: DrawMode,
         range: R,
-        vao: &VertexArrayObject<V, I>,
+        vao: &VertexArrayObject<V, I, N>,
         program: &Program<V, U::Static, Self::AttachmentsStatic>,
         uniforms: &U,
         render_state: &RenderState,
@@ -189,6 +309,7 @@ pub trait Framebuffer {
         R: RangeBounds<usize>,
         V: Vertex,
         I: Index,
+        N: Vertex,
         U: Uniforms,
     {
         self.draw_ext(mode, range, vao, program, uniforms, render_state, None, None)
@@ -196,16 +317,22 @@ pub trait Framebuffer {
 
     /// ## Extra parameters
     /// * `instance_count`: The highest value the instance ID gets incremented to. See
-    ///   https://www.khronos.org/opengl/wiki/Vertex_Rendering#Instancing for more details.
+    ///   https://www.khronos.org/opengl/wiki/Vertex_Rendering#Instancing for more details. The
+    ///   VAO doesn't need any per-instance attributes; every instance reads the same vertices, and
+    ///   the shader can tell them apart through `gl_InstanceID`. VAOs created with
+    ///   [`VertexArrayObject::with_instances`] also read one element of their instance buffer per
+    ///   instance, and panic if `instance_count` exceeds the buffer's length.
     /// * `base_index`: An offset that gets added to every accessed element in the vertex buffer.
     ///   Useful if multiple meshes are being stored in one buffer.
+    ///
+    /// [`VertexArrayObject::with_instances`]: ../vertex/struct.VertexArrayObject.html#method.with_instances
     // TODO: DRAW_EXT IS AN AWFUL NAME
     #[inline]
-    fn draw_ext<R, V, I, U>(
+    fn draw_ext<R, V, I, N, U>(
         &mut self,
         mode: DrawMode,
         range: R,
-        vao: &VertexArrayObject<V, I>,
+        vao: &VertexArrayObject<V, I, N>,
         program: &Program<V, U::Static, Self::AttachmentsStatic>,
         uniforms: &U,
         render_state: &RenderState,
@@ -215,6 +342,123 @@ pub trait Framebuffer {
         R: RangeBounds<usize>,
         V: Vertex,
         I: Index,
+        N: Vertex,
+        U: Uniforms,
+    {
+        let (raw_mut, arm, state) = self.raw_mut();
+        render_state.upload_state(state);
+        unsafe {
+            let vao_bind = state.vao_target.bind(vao);
+
+            let program_bind = state.program_target.bind(program);
+            program_bind.upload_uniforms(uniforms);
+
+            let mut framebuffer_bind = state.framebuffer_targets.draw.bind(raw_mut, &state.gl);
+            framebuffer_bind.set_attachments(arm.ahc, arm.attachments);
+            framebuffer_bind.draw(
+                mode,
+                range,
+                &vao_bind,
+                &program_bind,
+                instance_count,
+                base_index,
+                None,
+            );
+        }
+    }
+
+    /// Performs an instanced draw call, with instance IDs starting at `base_instance`.
+    ///
+    /// Takes the same parameters as [`draw_ext`], plus:
+    /// * `base_instance`: The index of the first instance. Per-instance attributes get read
+    ///   starting from this instance, which lets one large instance buffer hold several batches
+    ///   without rebinding it. Note that `gl_InstanceID` still starts at `0`; the base instance
+    ///   is only exposed to shaders through `gl_BaseInstance`, which needs GLSL 4.60 or
+    ///   `ARB_shader_draw_parameters`.
+    ///
+    /// ## Panics
+    /// Panics if the context doesn't support OpenGL 4.2 or `ARB_base_instance`. Check for support
+    /// with [`ContextState::supports_base_instance`].
+    ///
+    /// [`draw_ext`]: #method.draw_ext
+    /// [`ContextState::supports_base_instance`]: ../struct.ContextState.html#method.supports_base_instance
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn draw_instanced_base_instance<R, V, I, N, U>(
+        &mut self,
+        mode: DrawMode,
+        range: R,
+        vao: &VertexArrayObject<V, I, N>,
+        program: &Program<V, U::Static, Self::AttachmentsStatic>,
+        uniforms: &U,
+        render_state: &RenderState,
+        instance_count: usize,
+        base_instance: u32,
+        base_index: Option<I>,
+    ) where
+        R: RangeBounds<usize>,
+        V: Vertex,
+        I: Index,
+        N: Vertex,
+        U: Uniforms,
+    {
+        let (raw_mut, arm, state) = self.raw_mut();
+        if !state.supports_base_instance() {
+            panic!("base instance draws require OpenGL 4.2 or ARB_base_instance");
+        }
+
+        render_state.upload_state(state);
+        unsafe {
+            let vao_bind = state.vao_target.bind(vao);
+
+            let program_bind = state.program_target.bind(program);
+            program_bind.upload_uniforms(uniforms);
+
+            let mut framebuffer_bind = state.framebuffer_targets.draw.bind(raw_mut, &state.gl);
+            framebuffer_bind.set_attachments(arm.ahc, arm.attachments);
+            framebuffer_bind.draw(
+                mode,
+                range,
+                &vao_bind,
+                &program_bind,
+                Some(instance_count),
+                base_index,
+                Some(base_instance),
+            );
+        }
+    }
+
+    /// Performs a draw call that reads its indices from `index_buffer`, instead of from the VAO's
+    /// own index buffer.
+    ///
+    /// This lets one VAO describe the vertex layout while many index buffers get swapped in, such
+    /// as index buffers written by compute shaders. `count` indices get read, starting
+    /// `byte_offset` bytes into `index_buffer`. The VAO's own index buffer, if it has one, is left
+    /// untouched. Unlike [`draw`], the indices can't be checked against the vertex count, so
+    /// they're assumed to be in range.
+    ///
+    /// ## Panics
+    /// Panics if `byte_offset` isn't a multiple of the index size, or if the read indices extend
+    /// past the end of `index_buffer`.
+    ///
+    /// [`draw`]: #method.draw
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    fn draw_with_index_buffer<V, I, J, N, U>(
+        &mut self,
+        mode: DrawMode,
+        vao: &VertexArrayObject<V, J, N>,
+        index_buffer: &Buffer<I>,
+        byte_offset: usize,
+        count: usize,
+        program: &Program<V, U::Static, Self::AttachmentsStatic>,
+        uniforms: &U,
+        render_state: &RenderState,
+    ) where
+        V: Vertex,
+        I: Index,
+        J: Index,
+        N: Vertex,
         U: Uniforms,
     {
         let (raw_mut, arm, state) = self.raw_mut();
@@ -227,11 +471,84 @@ pub trait Framebuffer {
 
             let mut framebuffer_bind = state.framebuffer_targets.draw.bind(raw_mut, &state.gl);
             framebuffer_bind.set_attachments(arm.ahc, arm.attachments);
-            framebuffer_bind.draw(mode, range, &vao_bind, &program_bind, instance_count, base_index);
+            framebuffer_bind.draw_with_index_buffer(
+                mode,
+                &vao_bind,
+                &program_bind,
+                index_buffer,
+                byte_offset,
+                count,
+            );
+        }
+    }
+
+    /// Copy the pixels in `src_rect` of `src` into `dst_rect` of this framebuffer.
+    ///
+    /// `src` gets bound as the read framebuffer and `self` as the draw framebuffer, so the two can
+    /// be different framebuffers. Color gets read from `src`'s first color attachment (or, for the
+    /// default framebuffer, its [read buffer]) and written to every one of `self`'s color
+    /// attachments. If the rectangles have different sizes, the image gets scaled with `filter`.
+    /// The render state doesn't apply to blits: the scissor test and sRGB conversion get disabled
+    /// for the duration of the blit, even if they were enabled by the last draw's
+    /// [`RenderState`] or through raw GL calls, so all of `dst_rect` gets written with the
+    /// unconverted source values.
+    ///
+    /// ## Panics
+    /// Panics if `src` belongs to a different context, or if `mask` includes depth or stencil
+    /// and `filter` is `Linear`.
+    ///
+    /// [read buffer]: ./struct.FramebufferDefault.html#method.set_read_buffer
+    /// [`RenderState`]: ./render_state/struct.RenderState.html
+    fn blit_from<F, V>(
+        &mut self,
+        src: &mut F,
+        src_rect: RangeInclusive<V>,
+        dst_rect: RangeInclusive<V>,
+        mask: BlitMask,
+        filter: FilterMag,
+    ) where
+        F: Framebuffer,
+        V: Into<GLVec2<u32, NonNormalized>>,
+    {
+        if filter == FilterMag::Linear && mask.intersects(BlitMask::DEPTH | BlitMask::STENCIL) {
+            panic!("depth and stencil blits must use `Nearest` filtering");
+        }
+
+        let (src_raw, src_arm, src_state) = src.raw_mut();
+        let (raw_mut, arm, state) = self.raw_mut();
+        if !ptr::eq(src_state, state) {
+            panic!("framebuffers can't be blitted between contexts");
+        }
+
+        let (src_start, src_end) = src_rect.into_inner();
+        let (dst_start, dst_end) = dst_rect.into_inner();
+        unsafe {
+            let mut read_bind = state.framebuffer_targets.read.bind(src_raw, &state.gl);
+            read_bind.set_attachments(src_arm.ahc, src_arm.attachments);
+            let mut draw_bind = state.framebuffer_targets.draw.bind(raw_mut, &state.gl);
+            draw_bind.set_attachments(arm.ahc, arm.attachments);
+            draw_bind.blit_from(
+                &read_bind,
+                (src_start.into(), src_end.into()),
+                (dst_start.into(), dst_end.into()),
+                mask.bits(),
+                GLenum::from(filter),
+            );
         }
     }
 }
 
+bitflags! {
+    /// The buffers copied by [`Framebuffer::blit_from`].
+    ///
+    /// [`Framebuffer::blit_from`]: ./trait.Framebuffer.html#method.blit_from
+    pub struct BlitMask: u32 {
+        const COLOR = gl::COLOR_BUFFER_BIT;
+        const DEPTH = gl::DEPTH_BUFFER_BIT;
+        const STENCIL = gl::STENCIL_BUFFER_BIT;
+    }
+}
+
 impl FramebufferDefault {
     /// Creates a handle* to the default framebuffer.
     ///
@@ -252,6 +569,57 @@ impl FramebufferDefault {
         }
     }
 
+    /// Whether the window's back buffer stores sRGB-encoded colors.
+    ///
+    /// This is decided by the pixel format the window was created with. If it returns `false`,
+    /// [`RenderState::srgb`] has no effect when rendering to the window, and shaders need to apply
+    /// gamma correction themselves.
+    ///
+    /// [`RenderState::srgb`]: ./render_state/struct.RenderState.html#structfield.srgb
+    #[inline]
+    pub fn is_srgb_capable(&self) -> bool {
+        let (raw, state) = self.raw();
+        let mut encoding = 0;
+        unsafe {
+            let _bind = state.framebuffer_targets.read.bind(raw, &state.gl);
+            state.gl.GetFramebufferAttachmentParameteriv(
+                gl::READ_FRAMEBUFFER,
+                gl::BACK_LEFT,
+                gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+                &mut encoding,
+            );
+        }
+        encoding as GLenum == gl::SRGB
+    }
+
+    /// Select the buffer that pixels get read from, by both `read_pixels` and blits.
+    ///
+    /// Reading from the front buffer is useful for taking screenshots after a buffer swap.
+    #[inline]
+    pub fn set_read_buffer(&mut self, buffer: DefaultBuffer) {
+        let (raw, _, state) = self.raw_mut();
+        unsafe {
+            let framebuffer_bind = state.framebuffer_targets.read.bind(raw, &state.gl);
+            framebuffer_bind.read_buffer(buffer.to_gl_enum());
+        }
+    }
+
+    /// Select the buffer that draws and clears render into.
+    ///
+    /// Rendering to [`DefaultBuffer::Front`] draws directly to the image on screen, which is
+    /// useful for low-latency overlays that can't wait for the next buffer swap. Gullery never
+    /// swaps buffers itself; that's still done through the windowing library.
+    ///
+    /// [`DefaultBuffer::Front`]: ./enum.DefaultBuffer.html#variant.Front
+    #[inline]
+    pub fn set_draw_buffer(&mut self, buffer: DefaultBuffer) {
+        let (raw, _, state) = self.raw_mut();
+        unsafe {
+            let mut framebuffer_bind = state.framebuffer_targets.draw.bind(raw, &state.gl);
+            framebuffer_bind.draw_buffer(buffer.to_gl_enum());
+        }
+    }
+
     /// Reads pixels from the default framebuffer
     #[inline]
     pub fn read_pixels<V, C>(&mut self, read_range: RangeInclusive<V>, data: &mut [C])
@@ -269,24 +637,103 @@ impl FramebufferDefault {
             framebuffer_bind.read_pixels(start, end - start, data);
         }
     }
+
+    /// Reads pixels from the default framebuffer into a newly allocated `Vec`, sized to fit
+    /// `read_range`.
+    ///
+    /// Use [`read_pixels`] to read into an existing buffer without allocating.
+    ///
+    /// [`read_pixels`]: #method.read_pixels
+    #[inline]
+    pub fn read_pixels_vec<V, C>(&mut self, read_range: RangeInclusive<V>) -> Vec<C>
+    where
+        V: Into<GLVec2<u32, NonNormalized>>,
+        C: ImageFormatRenderable + ConcreteImageFormat + Default,
+    {
+        let (start, end) = read_range.into_inner();
+        let (start, end): (GLVec2<_, _>, GLVec2<_, _>) = (start.into(), end.into());
+        let mut data = default_pixels(start, end);
+        self.read_pixels(start..=end, &mut data);
+        data
+    }
+}
+
+/// Convert a depth value read with [`Framebuffer::read_depth`] into the distance from the camera
+/// along the view direction, for a perspective projection with the given near and far planes.
+///
+/// This assumes the standard OpenGL projection, which maps `near` to a depth of `0` and `far` to
+/// `1`. For reversed-Z projections that map `near` to `1` and `far` to `0`, swap `near` and
+/// `far`.
+///
+/// [`Framebuffer::read_depth`]: ./trait.Framebuffer.html#method.read_depth
+#[inline]
+pub fn linearize_depth(depth: f32, near: f32, far: f32) -> f32 {
+    let ndc_depth = depth * 2.0 - 1.0;
+    (2.0 * near * far) / (far + near - ndc_depth * (far - near))
+}
+
+/// Allocate a buffer that fits the pixels between `start` and `end`.
+fn default_pixels<C: ConcreteImageFormat + Default>(
+    start: GLVec2<u32, NonNormalized>,
+    end: GLVec2<u32, NonNormalized>,
+) -> Vec<C> {
+    let dims = end - start;
+    vec![C::default(); (dims.x * dims.y) as usize]
+}
+
+/// The maximum number of color attachments a framebuffer object can have.
+///
+/// Corresponds to `GL_MAX_COLOR_ATTACHMENTS`. OpenGL guarantees this is at least 8.
+pub fn max_color_attachments(state: &ContextState) -> usize {
+    let mut max = 0;
+    unsafe {
+        state.gl.GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut max);
+    }
+    max as usize
+}
+
+/// The maximum number of color outputs a fragment shader can simultaneously write to.
+///
+/// Corresponds to `GL_MAX_DRAW_BUFFERS`. OpenGL guarantees this is at least 8.
+pub fn max_draw_buffers(state: &ContextState) -> usize {
+    let mut max = 0;
+    unsafe {
+        state.gl.GetIntegerv(gl::MAX_DRAW_BUFFERS, &mut max);
+    }
+    max as usize
 }
 
 impl<A: Attachments> FramebufferObject<A> {
-    pub fn new(state: Rc<ContextState>) -> FramebufferObject<A> {
-        let mut raw = RawFramebufferObject::new(&state.gl);
-        let mut draw_buffers = [0; 32];
-        for (i, db) in draw_buffers.iter_mut().enumerate() {
-            *db = gl::COLOR_ATTACHMENT0 + i as GLenum;
+    /// Create a new framebuffer object.
+    ///
+    /// Returns an error if `A` has more color attachments than the OpenGL implementation's
+    /// [`max_color_attachments`] or [`max_draw_buffers`].
+    ///
+    /// [`max_color_attachments`]: ./fn.max_color_attachments.html
+    /// [`max_draw_buffers`]: ./fn.max_draw_buffers.html
+    pub fn new(state: Rc<ContextState>) -> Result<FramebufferObject<A>, FramebufferCreateError> {
+        let num_color = A::num_color_members();
+        let max = max_color_attachments(&state).min(max_draw_buffers(&state));
+        if num_color > max {
+            return Err(FramebufferCreateError::TooManyColorAttachments {
+                requested: num_color,
+                max,
+            });
         }
+
+        let mut raw = RawFramebufferObject::new(&state.gl);
+        let draw_buffers = (0..num_color)
+            .map(|i| gl::COLOR_ATTACHMENT0 + i as GLenum)
+            .collect::<Vec<_>>();
         unsafe {
             let mut framebuffer_bind = state.framebuffer_targets.draw.bind(&mut raw, &state.gl);
-            framebuffer_bind.draw_buffers(&draw_buffers[..A::num_members()]);
+            framebuffer_bind.draw_buffers(&draw_buffers);
         }
-        FramebufferObject {
+        Ok(FramebufferObject {
             raw,
             handles: A::AHC::new_zeroed(),
             state,
-        }
+        })
     }
 }
 
@@ -373,6 +820,29 @@ where
         }
     }
 
+    /// Reads pixels from an attachment into a newly allocated `Vec`, sized to fit `read_range`.
+    ///
+    /// Use [`read_pixels_attachment`] to read into an existing buffer without allocating.
+    ///
+    /// [`read_pixels_attachment`]: #method.read_pixels_attachment
+    #[inline]
+    pub fn read_pixels_attachment_vec<V, C, At>(
+        &mut self,
+        read_range: RangeInclusive<V>,
+        get_attachment: impl FnOnce(&<Self as Framebuffer>::Attachments) -> &At,
+    ) -> Vec<C>
+    where
+        V: Into<GLVec2<u32, NonNormalized>>,
+        C: ImageFormatRenderable + ConcreteImageFormat + Default,
+        At: AttachmentType<Format = C>,
+    {
+        let (start, end) = read_range.into_inner();
+        let (start, end): (GLVec2<_, _>, GLVec2<_, _>) = (start.into(), end.into());
+        let mut data = default_pixels(start, end);
+        self.read_pixels_attachment(start..=end, &mut data, get_attachment);
+        data
+    }
+
     pub fn clear_color_attachment<At: AttachmentType>(
         &mut self,
         color: Rgba<f32>,
@@ -390,6 +860,26 @@ where
     }
 }
 
+impl From<FramebufferCreateError> for io::Error {
+    fn from(err: FramebufferCreateError) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+impl Error for FramebufferCreateError {}
+
+impl fmt::Display for FramebufferCreateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            FramebufferCreateError::TooManyColorAttachments { requested, max } => write!(
+                f,
+                "requested {} color attachments, but OpenGL implementation supports at most {}",
+                requested, max,
+            ),
+        }
+    }
+}
+
 impl<A: Attachments> Drop for FramebufferObject<A> {
     fn drop(&mut self) {
         unsafe {
@@ -410,14 +900,135 @@ impl FramebufferTargets {
         FramebufferTargets {
             read: RawFramebufferTargetRead::new(),
             draw: RawFramebufferTargetDraw::new(),
+            scratch: RefCell::new(None),
+        }
+    }
+
+    /// Run `f` with the scratch read and draw framebuffers, creating them if necessary.
+    ///
+    /// `f` must detach everything it attaches before returning, so the scratch framebuffers don't
+    /// keep deleted textures alive.
+    unsafe fn with_scratch<R>(
+        &self,
+        gl: &Gl,
+        f: impl FnOnce(&mut RawFramebufferObject, &mut RawFramebufferObject) -> R,
+    ) -> R {
+        let mut scratch = self.scratch.borrow_mut();
+        let (read_fbo, draw_fbo) = scratch
+            .get_or_insert_with(|| (RawFramebufferObject::new(gl), RawFramebufferObject::new(gl)));
+        f(read_fbo, draw_fbo)
+    }
+
+    /// Forget which read and draw framebuffers are bound, so the next bind to either target
+    /// always gets issued, even for the default framebuffer.
+    pub(crate) fn invalidate(&self) {
+        self.read.invalidate();
+        self.draw.invalidate();
+    }
+
+    /// Fill each of the texture's mip levels by blitting the next-largest level into it, using
+    /// nearest-neighbor filtering.
+    ///
+    /// This goes through the scratch framebuffers.
+    pub(crate) fn blit_mip_chain<D, T>(
+        &self,
+        texture: &Texture<D, T>,
+        base: u8,
+        state: &ContextState,
+    ) where
+        D: Dimension<u32>,
+        T: TextureType<D, MipSelector = u8>,
+        T::Format: ImageFormatRenderable,
+    {
+        let (attachment, mask) = match <T::Format as ImageFormatRenderable>::FormatType::FORMAT_TYPE
+        {
+            FormatTypeTag::Color => (gl::COLOR_ATTACHMENT0, gl::COLOR_BUFFER_BIT),
+            FormatTypeTag::Depth => (gl::DEPTH_ATTACHMENT, gl::DEPTH_BUFFER_BIT),
+            FormatTypeTag::DepthStencil => (
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT,
+            ),
+        };
+        let origin = GLVec2::new(0, 0);
+        let level_corner = |level| {
+            let dims = T::mip_dims(texture.dims(), level);
+            GLVec2::new(dims.width(), dims.height())
+        };
+
+        unsafe {
+            self.with_scratch(&state.gl, |read_fbo, draw_fbo| {
+                let mut read_bind = self.read.bind(read_fbo, &state.gl);
+                let mut draw_bind = self.draw.bind(draw_fbo, &state.gl);
+                for level in base + 1..texture.num_mips() {
+                    read_bind.attach_texture_level(
+                        attachment,
+                        texture.handle(),
+                        level as GLint - 1,
+                    );
+                    draw_bind.attach_texture_level(attachment, texture.handle(), level as GLint);
+                    draw_bind.blit_from(
+                        &read_bind,
+                        (origin, level_corner(level - 1)),
+                        (origin, level_corner(level)),
+                        mask,
+                        gl::NEAREST,
+                    );
+                }
+                read_bind.detach(attachment);
+                draw_bind.detach(attachment);
+            });
         }
     }
 
+    /// Query the position of one of a multisample texture's samples within a pixel.
+    ///
+    /// GL only reports sample positions for the bound draw framebuffer, so this attaches the
+    /// texture to the scratch draw framebuffer.
+    pub(crate) fn sample_position<C>(
+        &self,
+        texture: &Texture<D2, MultisampleTex<C>>,
+        index: u32,
+        state: &ContextState,
+    ) -> GLVec2<f32, NonNormalized>
+    where
+        C: ?Sized + ImageFormatRenderable,
+    {
+        let attachment = match C::FormatType::FORMAT_TYPE {
+            FormatTypeTag::Color => gl::COLOR_ATTACHMENT0,
+            FormatTypeTag::Depth => gl::DEPTH_ATTACHMENT,
+            FormatTypeTag::DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+        };
+
+        let mut position = [0.0; 2];
+        let mut samples = 0;
+        unsafe {
+            self.with_scratch(&state.gl, |_, draw_fbo| {
+                let mut draw_bind = self.draw.bind(draw_fbo, &state.gl);
+                draw_bind.attach_texture_level(attachment, texture.handle(), 0);
+                state.gl.GetIntegerv(gl::SAMPLES, &mut samples);
+                if index < samples as u32 {
+                    state
+                        .gl
+                        .GetMultisamplefv(gl::SAMPLE_POSITION, index, position.as_mut_ptr());
+                }
+                draw_bind.detach(attachment);
+            });
+        }
+
+        if index >= samples as u32 {
+            panic!(
+                "sample index {} out of range for texture with {} samples",
+                index, samples
+            );
+        }
+        GLVec2::new(position[0], position[1])
+    }
+
     unsafe fn unbind<F: RawFramebuffer>(&self, buffer: &F, gl: &Gl) {
-        if self.read.bound_buffer().get() == buffer.handle() {
+        if self.read.is_bound(buffer.handle()) {
             self.read.reset_bind(gl);
         }
-        if self.draw.bound_buffer().get() == buffer.handle() {
+        if self.draw.is_bound(buffer.handle()) {
             self.draw.reset_bind(gl);
         }
     }
@@ -481,3 +1092,1041 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::{Buffer, BufferUsage, UniformBuffer},
+        framebuffer::render_state::{ColorMask, CullFace, DepthStencilFunc, FrontFace},
+        geometry::{GLMat4r4c, GLVec4, D2},
+        image_format::Depth24Stencil8,
+        program::{error::UniformBlockError, Shader},
+        test_helper::CONTEXT_STATE,
+        uniform::UniformsMemberRegistry,
+        vertex::VertexMemberRegistry,
+    };
+    use std::mem;
+
+    const VERTEX_SHADER: &str = r#"
+        #version 330
+
+        in vec2 pos;
+
+        void main() {
+            gl_Position = vec4(pos, 0.0, 1.0);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        #version 330
+
+        out vec4 color;
+
+        void main() {
+            color = vec4(1.0);
+        }
+    "#;
+
+    const UNIFORM_BLOCK_FRAGMENT_SHADER: &str = r#"
+        #version 330
+
+        layout(std140) uniform Tint {
+            vec4 tint;
+        };
+
+        out vec4 color;
+
+        void main() {
+            color = tint;
+        }
+    "#;
+
+    const DEPTH_VERTEX_SHADER: &str = r#"
+        #version 330
+
+        in vec2 pos;
+
+        void main() {
+            gl_Position = vec4(pos, 0.5, 1.0);
+        }
+    "#;
+
+    const SAMPLE_DEPTH_FRAGMENT_SHADER: &str = r#"
+        #version 330
+
+        uniform sampler2D depth_tex;
+
+        out vec4 color;
+
+        void main() {
+            color = vec4(texelFetch(depth_tex, ivec2(gl_FragCoord.xy), 0).r);
+        }
+    "#;
+
+    const INSTANCED_VERTEX_SHADER: &str = r#"
+        #version 330
+
+        uniform vec2 offsets[4];
+
+        in vec2 pos;
+
+        void main() {
+            gl_Position = vec4(pos * 0.25 + offsets[gl_InstanceID], 0.0, 1.0);
+        }
+    "#;
+
+    const MATRIX_INSTANCED_VERTEX_SHADER: &str = r#"
+        #version 330
+
+        in vec2 pos;
+        layout(location = 1) in mat4 model;
+
+        void main() {
+            gl_Position = model * vec4(pos, 0.0, 1.0);
+        }
+    "#;
+
+    /// Expands each segment of a `lines_adjacency` primitive into a quad, using the adjacent
+    /// vertices to miter the ends so consecutive segments of a strip join without gaps.
+    const THICK_LINE_GEOMETRY_SHADER: &str = r#"
+        #version 330
+
+        layout(lines_adjacency) in;
+        layout(triangle_strip, max_vertices = 4) out;
+
+        uniform float half_width;
+
+        vec2 perp(vec2 v) {
+            return vec2(-v.y, v.x);
+        }
+
+        vec2 miter(vec2 prev_dir, vec2 next_dir) {
+            vec2 normal = perp(next_dir);
+            vec2 miter = perp(normalize(prev_dir + next_dir));
+            return miter * half_width / dot(miter, normal);
+        }
+
+        void main() {
+            vec2 p0 = gl_in[0].gl_Position.xy;
+            vec2 p1 = gl_in[1].gl_Position.xy;
+            vec2 p2 = gl_in[2].gl_Position.xy;
+            vec2 p3 = gl_in[3].gl_Position.xy;
+
+            vec2 dir = normalize(p2 - p1);
+            vec2 miter_start = miter(normalize(p1 - p0), dir);
+            vec2 miter_end = miter(dir, normalize(p3 - p2));
+
+            gl_Position = vec4(p1 - miter_start, 0.0, 1.0);
+            EmitVertex();
+            gl_Position = vec4(p2 - miter_end, 0.0, 1.0);
+            EmitVertex();
+            gl_Position = vec4(p1 + miter_start, 0.0, 1.0);
+            EmitVertex();
+            gl_Position = vec4(p2 + miter_end, 0.0, 1.0);
+            EmitVertex();
+            EndPrimitive();
+        }
+    "#;
+
+    #[derive(Debug, Clone, Copy)]
+    struct ModelInstance {
+        model: GLMat4r4c<f32>,
+    }
+
+    impl Vertex for ModelInstance {
+        fn members<M>(mut reg: M)
+        where
+            M: VertexMemberRegistry<Group = Self>,
+        {
+            reg.add_member("model", |t| unsafe { &(*t).model });
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    struct Tint {
+        tint: GLVec4<f32>,
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct PosVertex {
+        pos: GLVec2<f32>,
+    }
+
+    impl Vertex for PosVertex {
+        fn members<M>(mut reg: M)
+        where
+            M: VertexMemberRegistry<Group = Self>,
+        {
+            reg.add_member("pos", |t| unsafe { &(*t).pos });
+        }
+    }
+
+    struct TestAttachments {
+        color: Texture<D2, Rgba<u8>>,
+    }
+
+    impl Attachments for TestAttachments {
+        type AHC = [Option<AttachedImage>; 1];
+        type Static = Self;
+
+        fn members<R>(mut reg: R)
+        where
+            R: AttachmentsMemberRegistry<Attachments = Self>,
+        {
+            <Texture<D2, Rgba<u8>> as AttachmentType>::add_to_registry(
+                &mut reg,
+                "color",
+                |t| &t.color,
+                Default::default(),
+            );
+        }
+    }
+
+    struct DepthAttachments {
+        color: Texture<D2, Rgba<u8>>,
+        depth: Texture<D2, Depth24Stencil8>,
+    }
+
+    impl Attachments for DepthAttachments {
+        type AHC = [Option<AttachedImage>; 2];
+        type Static = Self;
+
+        fn members<R>(mut reg: R)
+        where
+            R: AttachmentsMemberRegistry<Attachments = Self>,
+        {
+            <Texture<D2, Rgba<u8>> as AttachmentType>::add_to_registry(
+                &mut reg,
+                "color",
+                |t| &t.color,
+                Default::default(),
+            );
+            <Texture<D2, Depth24Stencil8> as AttachmentType>::add_to_registry(
+                &mut reg,
+                "depth",
+                |t| &t.depth,
+                Default::default(),
+            );
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct DepthUniforms<'a> {
+        depth_tex: &'a Texture<D2, Depth24Stencil8>,
+    }
+
+    impl<'a> Uniforms for DepthUniforms<'a> {
+        type ULC = [GLint; 1];
+        type Static = DepthUniforms<'static>;
+
+        fn members<R>(mut reg: R)
+        where
+            R: UniformsMemberRegistry<Uniforms = Self>,
+        {
+            reg.add_member("depth_tex", |t| t.depth_tex);
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct OffsetUniforms {
+        offsets: [GLVec2<f32>; 4],
+    }
+
+    impl Uniforms for OffsetUniforms {
+        type ULC = [GLint; 1];
+        type Static = Self;
+
+        fn members<R>(mut reg: R)
+        where
+            R: UniformsMemberRegistry<Uniforms = Self>,
+        {
+            reg.add_member("offsets", |t| t.offsets);
+        }
+    }
+
+    /// A framebuffer with a single `dims`-sized color texture.
+    fn test_fbo(
+        dims: GLVec2<u32, NonNormalized>,
+        state: &Rc<ContextState>,
+    ) -> FramebufferObjectAttached<TestAttachments> {
+        FramebufferObjectAttached {
+            fbo: FramebufferObject::new(state.clone()).unwrap(),
+            attachments: TestAttachments {
+                color: Texture::with_mip_count(dims, 1, state.clone()).unwrap(),
+            },
+        }
+    }
+
+    /// A quad covering all of clip space, drawn with `DrawMode::TriangleStrip`.
+    fn fullscreen_quad_vao(state: &Rc<ContextState>) -> VertexArrayObject<PosVertex, !> {
+        let vertices = [
+            PosVertex {
+                pos: GLVec2::new(-1.0, -1.0),
+            },
+            PosVertex {
+                pos: GLVec2::new(1.0, -1.0),
+            },
+            PosVertex {
+                pos: GLVec2::new(-1.0, 1.0),
+            },
+            PosVertex {
+                pos: GLVec2::new(1.0, 1.0),
+            },
+        ];
+        VertexArrayObject::new(
+            Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone()),
+            None,
+        )
+    }
+
+    #[test]
+    fn draw_each_mode() {
+        CONTEXT_STATE.with(|state| {
+            let vertices = [
+                PosVertex { pos: GLVec2::new(-0.8, -0.8) },
+                PosVertex { pos: GLVec2::new(0.8, -0.8) },
+                PosVertex { pos: GLVec2::new(-0.8, 0.8) },
+                PosVertex { pos: GLVec2::new(0.8, 0.8) },
+                PosVertex { pos: GLVec2::new(0.8, -0.8) },
+                PosVertex { pos: GLVec2::new(0.0, 0.0) },
+            ];
+            let vao = VertexArrayObject::<_, !>::new(
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone()),
+                None,
+            );
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, _) =
+                Program::<PosVertex, (), TestAttachments>::new(&vertex_shader, None, &fragment_shader)
+                    .unwrap();
+
+            let mut fbo_attached = test_fbo(GLVec2::new(16, 16), state);
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                ..RenderState::default()
+            };
+
+            let modes = [
+                DrawMode::Points,
+                DrawMode::LineStrip,
+                DrawMode::LineLoop,
+                DrawMode::Lines,
+                DrawMode::LineStripAdjacency,
+                DrawMode::LinesAdjacency,
+                DrawMode::TriangleStrip,
+                DrawMode::TriangleFan,
+                DrawMode::Triangles,
+                DrawMode::TriangleStripAdjacency,
+                DrawMode::TrianglesAdjacency,
+            ];
+            let mut pixels = vec![Rgba::new(0, 0, 0, 0); 16 * 16];
+            for &mode in &modes {
+                fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+                fbo_attached.draw(mode, .., &vao, &program, &(), &render_state);
+                fbo_attached.read_pixels_attachment(
+                    GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                    &mut pixels,
+                    |a| &a.color,
+                );
+
+                assert!(
+                    pixels.iter().any(|p| *p != Rgba::new(0, 0, 0, 0)),
+                    "{:?} didn't draw anything",
+                    mode
+                );
+            }
+        })
+    }
+
+    #[test]
+    fn invalidate_cache_reissues_binds() {
+        CONTEXT_STATE.with(|state| {
+            let gl = &state.gl;
+            let vao = fullscreen_quad_vao(state);
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, _) = Program::<PosVertex, (), TestAttachments>::new(
+                &vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+
+            let mut fbo_attached = test_fbo(GLVec2::new(4, 4), state);
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=GLVec2::new(4, 4),
+                ..RenderState::default()
+            };
+            fbo_attached.draw(
+                DrawMode::TriangleStrip,
+                ..,
+                &vao,
+                &program,
+                &(),
+                &render_state,
+            );
+
+            let bindings = || unsafe {
+                let (mut fbo, mut program, mut vao) = (0, 0, 0);
+                gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut fbo);
+                gl.GetIntegerv(gl::CURRENT_PROGRAM, &mut program);
+                gl.GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut vao);
+                (fbo as GLuint, program as GLuint, vao as GLuint)
+            };
+            let expected = (
+                fbo_attached.fbo.raw.handle().unwrap().get(),
+                program.handle().get(),
+                vao.handle().get(),
+            );
+            assert_eq!(expected, bindings());
+
+            // Unbind everything behind Gullery's back. Without invalidating, the cache would still
+            // think everything is bound and skip re-binding for the next draw.
+            unsafe {
+                gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl.UseProgram(0);
+                gl.BindVertexArray(0);
+            }
+            state.invalidate_cache();
+            fbo_attached.draw(
+                DrawMode::TriangleStrip,
+                ..,
+                &vao,
+                &program,
+                &(),
+                &render_state,
+            );
+            assert_eq!(expected, bindings());
+        })
+    }
+
+    #[derive(Clone, Copy)]
+    struct ThickLineUniforms {
+        half_width: f32,
+    }
+
+    impl Uniforms for ThickLineUniforms {
+        type ULC = [GLint; 1];
+        type Static = Self;
+
+        fn members<R>(mut reg: R)
+        where
+            R: UniformsMemberRegistry<Uniforms = Self>,
+        {
+            reg.add_member("half_width", |t| t.half_width);
+        }
+    }
+
+    #[test]
+    fn draw_thick_line_strip_with_geometry_shader() {
+        CONTEXT_STATE.with(|state| {
+            // An L-shaped strip, with the first and last vertices only providing adjacency.
+            let vertices = [
+                PosVertex { pos: GLVec2::new(-1.0, -0.5) },
+                PosVertex { pos: GLVec2::new(-0.5, -0.5) },
+                PosVertex { pos: GLVec2::new(0.5, -0.5) },
+                PosVertex { pos: GLVec2::new(0.5, 0.5) },
+                PosVertex { pos: GLVec2::new(0.5, 1.0) },
+            ];
+            let vao = VertexArrayObject::<_, !>::new(
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone()),
+                None,
+            );
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let geometry_shader = Shader::new(THICK_LINE_GEOMETRY_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, warnings) = Program::<PosVertex, ThickLineUniforms, TestAttachments>::new(
+                &vertex_shader,
+                Some(&geometry_shader),
+                &fragment_shader,
+            )
+            .unwrap();
+            assert_eq!(0, warnings.len(), "{:?}", warnings);
+
+            let mut fbo_attached = test_fbo(GLVec2::new(16, 16), state);
+            // The geometry shader emits counter-clockwise quads, so back-face culling shouldn't
+            // remove any of the line.
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                cull: Some((CullFace::Back, FrontFace::CounterClockwise)),
+                ..RenderState::default()
+            };
+
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw(
+                DrawMode::LineStripAdjacency,
+                ..,
+                &vao,
+                &program,
+                &ThickLineUniforms { half_width: 0.125 },
+                &render_state,
+            );
+
+            let mut pixels = vec![Rgba::new(0, 0, 0, 0); 16 * 16];
+            fbo_attached.read_pixels_attachment(
+                GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                &mut pixels,
+                |a| &a.color,
+            );
+            let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+            let lit = Rgba::new(255, 255, 255, 255);
+            let empty = Rgba::new(0, 0, 0, 0);
+
+            // Both segments are two pixels thick.
+            assert_eq!(lit, pixel(8, 3));
+            assert_eq!(lit, pixel(8, 4));
+            assert_eq!(lit, pixel(11, 8));
+            assert_eq!(lit, pixel(12, 8));
+            // The mitered corner is filled in.
+            assert_eq!(lit, pixel(12, 3));
+            // The adjacency vertices aren't drawn.
+            assert_eq!(empty, pixel(1, 4));
+            assert_eq!(empty, pixel(12, 14));
+            // Nothing is drawn away from the line.
+            assert_eq!(empty, pixel(8, 8));
+            assert_eq!(empty, pixel(8, 1));
+        })
+    }
+
+    #[test]
+    fn draw_with_external_index_buffer() {
+        CONTEXT_STATE.with(|state| {
+            let vertices = [
+                PosVertex { pos: GLVec2::new(-1.0, -1.0) },
+                PosVertex { pos: GLVec2::new(1.0, -1.0) },
+                PosVertex { pos: GLVec2::new(-1.0, 1.0) },
+                PosVertex { pos: GLVec2::new(1.0, 1.0) },
+            ];
+            // The VAO's own indices draw the bottom-left triangle...
+            let vao = VertexArrayObject::new(
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone()),
+                Some(Buffer::with_data(BufferUsage::StaticDraw, &[0u16, 1, 2], state.clone())),
+            );
+            // ...and the second half of the external buffer draws the top-right one.
+            let index_buffer = Buffer::with_data(
+                BufferUsage::StaticDraw,
+                &[0u16, 1, 2, 1, 3, 2],
+                state.clone(),
+            );
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, _) =
+                Program::<PosVertex, (), TestAttachments>::new(&vertex_shader, None, &fragment_shader)
+                    .unwrap();
+
+            let mut fbo_attached = test_fbo(GLVec2::new(16, 16), state);
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                ..RenderState::default()
+            };
+            let lit = Rgba::new(255, 255, 255, 255);
+            let empty = Rgba::new(0, 0, 0, 0);
+            let mut pixels = vec![empty; 16 * 16];
+
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw_with_index_buffer(
+                DrawMode::Triangles,
+                &vao,
+                &index_buffer,
+                3 * mem::size_of::<u16>(),
+                3,
+                &program,
+                &(),
+                &render_state,
+            );
+            fbo_attached.read_pixels_attachment(
+                GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                &mut pixels,
+                |a| &a.color,
+            );
+            assert_eq!((empty, lit), (pixels[16 + 1], pixels[14 * 16 + 14]));
+
+            // Regular draws go back to using the VAO's own index buffer.
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw(DrawMode::Triangles, .., &vao, &program, &(), &render_state);
+            fbo_attached.read_pixels_attachment(
+                GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                &mut pixels,
+                |a| &a.color,
+            );
+            assert_eq!((lit, empty), (pixels[16 + 1], pixels[14 * 16 + 14]));
+        })
+    }
+
+    #[test]
+    fn viewport_f_overrides_viewport() {
+        CONTEXT_STATE.with(|state| {
+            let vao = fullscreen_quad_vao(state);
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, _) =
+                Program::<PosVertex, (), TestAttachments>::new(&vertex_shader, None, &fragment_shader)
+                    .unwrap();
+
+            let mut fbo_attached = test_fbo(GLVec2::new(16, 16), state);
+            let viewport = GLVec2::new(0, 0)..=GLVec2::new(16, 16);
+            let render_state = RenderState {
+                viewport: viewport.clone(),
+                viewport_f: Some([0.0, 0.0, 8.0, 16.0]),
+                ..RenderState::default()
+            };
+            let lit = Rgba::new(255, 255, 255, 255);
+            let empty = Rgba::new(0, 0, 0, 0);
+            let mut pixels = vec![empty; 16 * 16];
+
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw(DrawMode::TriangleStrip, .., &vao, &program, &(), &render_state);
+            fbo_attached.read_pixels_attachment(viewport.clone(), &mut pixels, |a| &a.color);
+            assert_eq!((lit, empty), (pixels[8 * 16 + 4], pixels[8 * 16 + 12]));
+
+            // Setting the integer viewport directly has to replace the fractional one, even though
+            // the integer viewport it replaces matches.
+            state.set_viewport(viewport);
+            let mut gl_viewport = [0; 4];
+            unsafe { state.gl.GetIntegerv(gl::VIEWPORT, gl_viewport.as_mut_ptr()) };
+            assert_eq!([0, 0, 16, 16], gl_viewport);
+        })
+    }
+
+    #[test]
+    fn draw_with_uniform_block() {
+        CONTEXT_STATE.with(|state| {
+            let vao = fullscreen_quad_vao(state);
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader =
+                Shader::new(UNIFORM_BLOCK_FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, _) =
+                Program::<PosVertex, (), TestAttachments>::new(&vertex_shader, None, &fragment_shader)
+                    .unwrap();
+
+            let tint = Tint { tint: GLVec4::new(1.0, 0.0, 1.0, 1.0) };
+            let uniform_buffer = UniformBuffer::new(BufferUsage::StaticDraw, tint, state.clone());
+            assert_eq!(
+                Err(UniformBlockError::NotFound("Missing".to_string())),
+                program.bind_uniform_block("Missing", 1, &uniform_buffer)
+            );
+            let too_small = UniformBuffer::new(BufferUsage::StaticDraw, 1.0f32, state.clone());
+            assert_eq!(
+                Err(UniformBlockError::RangeTooSmall { block_size: 16, range_size: 4 }),
+                program.bind_uniform_block("Tint", 1, &too_small)
+            );
+            program.bind_uniform_block("Tint", 1, &uniform_buffer).unwrap();
+
+            let mut fbo_attached = test_fbo(GLVec2::new(4, 4), state);
+            let viewport = GLVec2::new(0, 0)..=GLVec2::new(4, 4);
+            let render_state = RenderState {
+                viewport: viewport.clone(),
+                ..RenderState::default()
+            };
+            let mut pixels = vec![Rgba::new(0, 0, 0, 0); 4 * 4];
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw(DrawMode::TriangleStrip, .., &vao, &program, &(), &render_state);
+            fbo_attached.read_pixels_attachment(viewport, &mut pixels, |a| &a.color);
+            assert!(pixels.iter().all(|&p| p == Rgba::new(255, 0, 255, 255)));
+        })
+    }
+
+    #[test]
+    fn disallowing_dither_keeps_clears_exact() {
+        CONTEXT_STATE.with(|state| {
+            let dither_enabled = || unsafe { state.gl.IsEnabled(gl::DITHER) == gl::TRUE };
+            let render_state = RenderState::default();
+            render_state.upload_state(state);
+            assert!(dither_enabled());
+
+            // Clears don't upload a render state, so this has to take effect immediately.
+            state.set_dither_allowed(false);
+            assert!(!dither_enabled());
+            render_state.upload_state(state);
+            assert!(!dither_enabled());
+
+            let mut fbo_attached = test_fbo(GLVec2::new(16, 16), state);
+            let mut pixels = vec![Rgba::new(0, 0, 0, 0); 16 * 16];
+            fbo_attached.clear_color_all(Rgba::new(128.0 / 255.0, 64.0 / 255.0, 1.0 / 255.0, 1.0));
+            fbo_attached.read_pixels_attachment(
+                GLVec2::new(0, 0)..=GLVec2::new(16, 16),
+                &mut pixels,
+                |a| &a.color,
+            );
+            assert!(pixels.iter().all(|&p| p == Rgba::new(128, 64, 1, 255)));
+
+            state.set_dither_allowed(true);
+            render_state.upload_state(state);
+            assert!(dither_enabled());
+        })
+    }
+
+    #[test]
+    fn sample_depth_stencil_attachment() {
+        CONTEXT_STATE.with(|state| {
+            let vao = fullscreen_quad_vao(state);
+
+            let depth_vertex_shader = Shader::new(DEPTH_VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (depth_program, _) = Program::<PosVertex, (), DepthAttachments>::new(
+                &depth_vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let sample_fragment_shader =
+                Shader::new(SAMPLE_DEPTH_FRAGMENT_SHADER, state.clone()).unwrap();
+            let (sample_program, _) =
+                Program::<PosVertex, DepthUniforms<'static>, TestAttachments>::new(
+                    &vertex_shader,
+                    None,
+                    &sample_fragment_shader,
+                )
+                .unwrap();
+
+            let dims = GLVec2::new(16, 16);
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=dims,
+                depth_test: Some(DepthStencilFunc::Less),
+                ..RenderState::default()
+            };
+
+            // Render depth into the depth-stencil texture.
+            let mut depth_fbo = FramebufferObjectAttached {
+                fbo: FramebufferObject::new(state.clone()).unwrap(),
+                attachments: DepthAttachments {
+                    color: Texture::with_mip_count(dims, 1, state.clone()).unwrap(),
+                    depth: Texture::with_mip_count(dims, 1, state.clone()).unwrap(),
+                },
+            };
+            depth_fbo.clear_depth(1.0);
+            depth_fbo.draw(
+                DrawMode::TriangleStrip,
+                ..,
+                &vao,
+                &depth_program,
+                &(),
+                &render_state,
+            );
+
+            // Then sample that texture while drawing into a different framebuffer.
+            let mut color_fbo = test_fbo(dims, state);
+            color_fbo.draw(
+                DrawMode::TriangleStrip,
+                ..,
+                &vao,
+                &sample_program,
+                &DepthUniforms {
+                    depth_tex: &depth_fbo.attachments.depth,
+                },
+                &RenderState {
+                    depth_test: None,
+                    ..render_state
+                },
+            );
+
+            let mut pixels = vec![Rgba::new(0, 0, 0, 0); 16 * 16];
+            color_fbo.read_pixels_attachment(GLVec2::new(0, 0)..=dims, &mut pixels, |a| &a.color);
+
+            // A clip-space depth of 0.5 maps to a window-space depth of 0.75.
+            for pixel in &pixels {
+                assert!((pixel.r as i32 - 191).abs() <= 1, "sampled depth was {:?}", pixel);
+            }
+        })
+    }
+
+    #[test]
+    fn draw_instanced_without_instance_attributes() {
+        CONTEXT_STATE.with(|state| {
+            let vao = fullscreen_quad_vao(state);
+
+            let vertex_shader = Shader::new(INSTANCED_VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, _) = Program::<PosVertex, OffsetUniforms, TestAttachments>::new(
+                &vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+
+            let dims = GLVec2::new(16, 16);
+            let mut fbo_attached = test_fbo(dims, state);
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=dims,
+                ..RenderState::default()
+            };
+
+            // Each instance draws a quad in the center of a different quadrant.
+            let uniforms = OffsetUniforms {
+                offsets: [
+                    GLVec2::new(-0.5, -0.5),
+                    GLVec2::new(0.5, -0.5),
+                    GLVec2::new(-0.5, 0.5),
+                    GLVec2::new(0.5, 0.5),
+                ],
+            };
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw_ext(
+                DrawMode::TriangleStrip,
+                ..,
+                &vao,
+                &program,
+                &uniforms,
+                &render_state,
+                Some(4),
+                None,
+            );
+
+            let mut pixels = vec![Rgba::new(0, 0, 0, 0); 16 * 16];
+            fbo_attached.read_pixels_attachment(GLVec2::new(0, 0)..=dims, &mut pixels, |a| {
+                &a.color
+            });
+
+            let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+            for &(x, y) in &[(4, 4), (12, 4), (4, 12), (12, 12)] {
+                assert_eq!(Rgba::new(255, 255, 255, 255), pixel(x, y), "({}, {})", x, y);
+            }
+            for &(x, y) in &[(0, 0), (8, 8), (15, 15)] {
+                assert_eq!(Rgba::new(0, 0, 0, 0), pixel(x, y), "({}, {})", x, y);
+            }
+        })
+    }
+
+    #[test]
+    fn draw_instanced_with_matrix_instance_attribute() {
+        CONTEXT_STATE.with(|state| {
+            let vertices = [
+                PosVertex { pos: GLVec2::new(-1.0, -1.0) },
+                PosVertex { pos: GLVec2::new(1.0, -1.0) },
+                PosVertex { pos: GLVec2::new(-1.0, 1.0) },
+                PosVertex { pos: GLVec2::new(1.0, 1.0) },
+            ];
+            // Each instance scales the quad down and moves it to the center of a quadrant.
+            let instances: Vec<_> = [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)]
+                .iter()
+                .map(|&(x, y)| ModelInstance {
+                    model: GLMat4r4c::from_columns(
+                        GLVec4::new(0.25, 0.0, 0.0, 0.0),
+                        GLVec4::new(0.0, 0.25, 0.0, 0.0),
+                        GLVec4::new(0.0, 0.0, 1.0, 0.0),
+                        GLVec4::new(x, y, 0.0, 1.0),
+                    ),
+                })
+                .collect();
+            let vao = VertexArrayObject::<_, !, _>::with_instances(
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone()),
+                Buffer::with_data(BufferUsage::StaticDraw, &instances, state.clone()),
+                None,
+            );
+
+            let vertex_shader =
+                Shader::new(MATRIX_INSTANCED_VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+            let (program, warnings) = Program::<PosVertex, (), TestAttachments>::new(
+                &vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+            assert!(warnings.is_empty(), "{:?}", warnings);
+
+            let dims = GLVec2::new(16, 16);
+            let mut fbo_attached = test_fbo(dims, state);
+            let render_state = RenderState {
+                viewport: GLVec2::new(0, 0)..=dims,
+                ..RenderState::default()
+            };
+
+            fbo_attached.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo_attached.draw_ext(
+                DrawMode::TriangleStrip,
+                ..,
+                &vao,
+                &program,
+                &(),
+                &render_state,
+                Some(4),
+                None,
+            );
+
+            let pixels =
+                fbo_attached.read_pixels_attachment_vec(GLVec2::new(0, 0)..=dims, |a| &a.color);
+            let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+            for &(x, y) in &[(4, 4), (12, 4), (4, 12), (12, 12)] {
+                assert_eq!(Rgba::new(255, 255, 255, 255), pixel(x, y), "({}, {})", x, y);
+            }
+            for &(x, y) in &[(0, 0), (8, 8), (15, 15)] {
+                assert_eq!(Rgba::new(0, 0, 0, 0), pixel(x, y), "({}, {})", x, y);
+            }
+        })
+    }
+
+    #[test]
+    fn blit_between_framebuffers() {
+        CONTEXT_STATE.with(|state| {
+            let dims = GLVec2::new(16, 16);
+            let mut src = test_fbo(dims, state);
+            let mut dst = test_fbo(dims, state);
+            src.clear_color_all(Rgba::new(1.0, 0.0, 0.0, 1.0));
+            dst.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+
+            // Copy the source's bottom-left quadrant into the destination's top-right quadrant.
+            dst.blit_from(
+                &mut src,
+                GLVec2::new(0, 0)..=GLVec2::new(8, 8),
+                GLVec2::new(8, 8)..=dims,
+                BlitMask::COLOR,
+                FilterMag::Nearest,
+            );
+
+            let pixels = dst.read_pixels_attachment_vec(GLVec2::new(0, 0)..=dims, |a| &a.color);
+            let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+            assert_eq!(Rgba::new(255, 0, 0, 255), pixel(12, 12));
+            assert_eq!(Rgba::new(0, 0, 0, 0), pixel(4, 4));
+        })
+    }
+
+    #[test]
+    fn blit_ignores_scissor_and_srgb() {
+        CONTEXT_STATE.with(|state| {
+            let dims = GLVec2::new(16, 16);
+            let mut src = test_fbo(dims, state);
+            let mut dst = test_fbo(dims, state);
+            src.clear_color_all(Rgba::new(1.0, 0.0, 0.0, 1.0));
+            dst.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+
+            unsafe {
+                state.gl.Enable(gl::SCISSOR_TEST);
+                state.gl.Scissor(0, 0, 4, 4);
+                state.gl.Enable(gl::FRAMEBUFFER_SRGB);
+            }
+            dst.blit_from(
+                &mut src,
+                GLVec2::new(0, 0)..=dims,
+                GLVec2::new(0, 0)..=dims,
+                BlitMask::COLOR,
+                FilterMag::Nearest,
+            );
+            unsafe {
+                assert_eq!(gl::TRUE, state.gl.IsEnabled(gl::SCISSOR_TEST));
+                assert_eq!(gl::TRUE, state.gl.IsEnabled(gl::FRAMEBUFFER_SRGB));
+                state.gl.Disable(gl::SCISSOR_TEST);
+                state.gl.Disable(gl::FRAMEBUFFER_SRGB);
+            }
+
+            let pixels = dst.read_pixels_attachment_vec(GLVec2::new(0, 0)..=dims, |a| &a.color);
+            let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+            assert_eq!(Rgba::new(255, 0, 0, 255), pixel(2, 2));
+            assert_eq!(Rgba::new(255, 0, 0, 255), pixel(12, 12));
+        })
+    }
+
+    #[test]
+    fn clear_color_rect_restores_scissor() {
+        CONTEXT_STATE.with(|state| {
+            let dims = GLVec2::new(16, 16);
+            let mut fbo = test_fbo(dims, state);
+            fbo.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+            fbo.clear_color_rect(
+                Rgba::new(0.0, 1.0, 0.0, 1.0),
+                GLVec2::new(4, 4)..=GLVec2::new(8, 8),
+            );
+            unsafe {
+                assert_eq!(gl::FALSE, state.gl.IsEnabled(gl::SCISSOR_TEST));
+            }
+
+            let pixels = fbo.read_pixels_attachment_vec(GLVec2::new(0, 0)..=dims, |a| &a.color);
+            let pixel = |x: usize, y: usize| pixels[y * 16 + x];
+            assert_eq!(Rgba::new(0, 255, 0, 255), pixel(4, 4));
+            assert_eq!(Rgba::new(0, 255, 0, 255), pixel(7, 7));
+            assert_eq!(Rgba::new(0, 0, 0, 0), pixel(8, 8));
+            assert_eq!(Rgba::new(0, 0, 0, 0), pixel(3, 4));
+        })
+    }
+
+    #[test]
+    fn read_depth_returns_cleared_depth() {
+        CONTEXT_STATE.with(|state| {
+            let dims = GLVec2::new(16, 16);
+            let mut fbo = FramebufferObjectAttached {
+                fbo: FramebufferObject::new(state.clone()).unwrap(),
+                attachments: DepthAttachments {
+                    color: Texture::with_mip_count(dims, 1, state.clone()).unwrap(),
+                    depth: Texture::with_mip_count(dims, 1, state.clone()).unwrap(),
+                },
+            };
+            fbo.clear_depth(0.5);
+
+            let depth = fbo.read_depth(GLVec2::new(0, 0)..=GLVec2::new(4, 2));
+            assert_eq!(8, depth.len());
+            for &d in &depth {
+                assert!((d - 0.5).abs() < 0.0001, "{}", d);
+            }
+        })
+    }
+
+    #[test]
+    fn linearize_depth_maps_planes() {
+        assert!((linearize_depth(0.0, 0.1, 100.0) - 0.1).abs() < 0.0001);
+        assert!((linearize_depth(1.0, 0.1, 100.0) - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn clear_color_all_forced_ignores_color_mask() {
+        CONTEXT_STATE.with(|state| {
+            let dims = GLVec2::new(16, 16);
+            let mut fbo = test_fbo(dims, state);
+            fbo.clear_color_all(Rgba::new(0.0, 0.0, 0.0, 0.0));
+
+            RenderState {
+                color_mask: ColorMask::R,
+                ..RenderState::default()
+            }
+            .upload_state(state);
+            fbo.clear_color_all(Rgba::new(1.0, 1.0, 1.0, 1.0));
+            let pixels = fbo.read_pixels_attachment_vec(GLVec2::new(0, 0)..=dims, |a| &a.color);
+            assert_eq!(Rgba::new(255, 0, 0, 0), pixels[0]);
+
+            fbo.clear_color_all_forced(Rgba::new(0.0, 1.0, 0.0, 1.0));
+            let pixels = fbo.read_pixels_attachment_vec(GLVec2::new(0, 0)..=dims, |a| &a.color);
+            assert_eq!(Rgba::new(0, 255, 0, 255), pixels[0]);
+
+            let mut write_mask = [gl::TRUE; 4];
+            unsafe {
+                state.gl.GetBooleanv(gl::COLOR_WRITEMASK, write_mask.as_mut_ptr());
+            }
+            assert_eq!([gl::TRUE, gl::FALSE, gl::FALSE, gl::FALSE], write_mask);
+            RenderState::default().upload_state(state);
+        })
+    }
+
+    #[test]
+    fn sample_shading_rate_is_clamped() {
+        CONTEXT_STATE.with(|state| {
+            if !state.supports_sample_shading() {
+                return;
+            }
+
+            RenderState {
+                sample_shading: Some(2.0),
+                ..RenderState::default()
+            }
+            .upload_state(state);
+            let mut min_rate = 0.0;
+            let enabled = unsafe {
+                state
+                    .gl
+                    .GetFloatv(gl::MIN_SAMPLE_SHADING_VALUE_ARB, &mut min_rate);
+                state.gl.IsEnabled(gl::SAMPLE_SHADING_ARB)
+            };
+            assert_eq!(gl::TRUE, enabled);
+            assert_eq!(1.0, min_rate);
+            RenderState::default().upload_state(state);
+        })
+    }
+}
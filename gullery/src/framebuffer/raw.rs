@@ -14,6 +14,7 @@
 
 use super::{attachments::*, Renderbuffer};
 use crate::{
+    buffer::Buffer,
     geometry::Dimension,
     gl::{self, types::*, Gl},
     geometry::{GLVec2, NonNormalized},
@@ -53,16 +54,25 @@ unsafe impl RawFramebuffer for RawFramebufferObject {
     }
 }
 
+/// The framebuffer bound to `GL_READ_FRAMEBUFFER`. `bound_fb` is `Some(None)` if the default
+/// framebuffer is bound, and `None` if the binding is unknown.
 pub struct RawFramebufferTargetRead {
-    bound_fb: Cell<Option<Handle>>,
+    bound_fb: Cell<Option<Option<Handle>>>,
 }
 
+/// The framebuffer bound to `GL_DRAW_FRAMEBUFFER`. `bound_fb` is `Some(None)` if the default
+/// framebuffer is bound, and `None` if the binding is unknown.
 pub struct RawFramebufferTargetDraw {
-    bound_fb: Cell<Option<Handle>>,
+    bound_fb: Cell<Option<Option<Handle>>>,
 }
 
 /// The primitive rendering mode for the `draw` call. See [here](https://www.khronos.org/opengl/wiki/Primitive)
 /// for more information.
+///
+/// `GL_PATCHES` isn't included, since patches can only be drawn by programs with tessellation
+/// shaders, which require OpenGL 4.0. Adding it should go along with a tessellation evaluation
+/// stage and a way to set `GL_PATCH_DEFAULT_INNER_LEVEL` and `GL_PATCH_DEFAULT_OUTER_LEVEL` in
+/// `RenderState`, since programs without a tessellation control shader rely on those defaults.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u32)]
 pub enum DrawMode {
@@ -70,14 +80,45 @@ pub enum DrawMode {
     LineStrip = gl::LINE_STRIP,
     LineLoop = gl::LINE_LOOP,
     Lines = gl::LINES,
+    /// A line strip whose first and last vertices are only passed to the geometry shader as
+    /// adjacency information. Without a geometry shader, the adjacency vertices aren't drawn.
+    ///
+    /// This is usually paired with a geometry shader that expands each segment into a triangle
+    /// strip, which can render lines of any thickness with properly joined corners.
     LineStripAdjacency = gl::LINE_STRIP_ADJACENCY,
+    /// Independent lines of four vertices each, where the outer two vertices are only passed to
+    /// the geometry shader as adjacency information.
     LinesAdjacency = gl::LINES_ADJACENCY,
     TriangleStrip = gl::TRIANGLE_STRIP,
     TriangleFan = gl::TRIANGLE_FAN,
     Triangles = gl::TRIANGLES,
     TriangleStripAdjacency = gl::TRIANGLE_STRIP_ADJACENCY,
-    TrianglesAdjacency = gl::TRIANGLES_ADJACENCY, // We don't support patches because that's an OpenGL 4 feature.
-                                                  // Patches
+    TrianglesAdjacency = gl::TRIANGLES_ADJACENCY,
+}
+
+/// A color buffer of the default framebuffer.
+///
+/// Buffers that the context wasn't created with (e.g. the right buffers on a non-stereo context)
+/// can't be selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum DefaultBuffer {
+    /// The front buffer, which is the image currently shown on screen.
+    Front = gl::FRONT,
+    /// **Default value.** The back buffer, which gets shown on screen after a buffer swap.
+    Back = gl::BACK,
+    /// The left front and back buffers of a stereo context.
+    Left = gl::LEFT,
+    /// The right front and back buffers of a stereo context.
+    Right = gl::RIGHT,
+    /// The left front buffer of a stereo context.
+    FrontLeft = gl::FRONT_LEFT,
+    /// The right front buffer of a stereo context.
+    FrontRight = gl::FRONT_RIGHT,
+    /// The left back buffer of a stereo context.
+    BackLeft = gl::BACK_LEFT,
+    /// The right back buffer of a stereo context.
+    BackRight = gl::BACK_RIGHT,
 }
 
 pub struct RawBoundFramebufferRead<'a, F>
@@ -121,7 +162,7 @@ impl RawFramebufferTargetRead {
     #[inline]
     pub fn new() -> RawFramebufferTargetRead {
         RawFramebufferTargetRead {
-            bound_fb: Cell::new(None),
+            bound_fb: Cell::new(Some(None)),
         }
     }
 
@@ -134,8 +175,8 @@ impl RawFramebufferTargetRead {
     where
         F: RawFramebuffer,
     {
-        if self.bound_fb.get() != framebuffer.handle() {
-            self.bound_fb.set(framebuffer.handle());
+        if self.bound_fb.get() != Some(framebuffer.handle()) {
+            self.bound_fb.set(Some(framebuffer.handle()));
             gl.BindFramebuffer(
                 gl::READ_FRAMEBUFFER,
                 framebuffer.handle().map(|h| h.get()).unwrap_or(0),
@@ -150,13 +191,18 @@ impl RawFramebufferTargetRead {
 
     #[inline]
     pub unsafe fn reset_bind(&self, gl: &Gl) {
-        self.bound_fb.set(None);
+        self.bound_fb.set(Some(None));
         gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
     }
 
     #[inline]
-    pub fn bound_buffer(&self) -> &Cell<Option<Handle>> {
-        &self.bound_fb
+    pub fn invalidate(&self) {
+        self.bound_fb.set(None);
+    }
+
+    #[inline]
+    pub fn is_bound(&self, handle: Option<Handle>) -> bool {
+        self.bound_fb.get() == Some(handle)
     }
 }
 
@@ -164,7 +210,7 @@ impl RawFramebufferTargetDraw {
     #[inline]
     pub fn new() -> RawFramebufferTargetDraw {
         RawFramebufferTargetDraw {
-            bound_fb: Cell::new(None),
+            bound_fb: Cell::new(Some(None)),
         }
     }
 
@@ -177,8 +223,8 @@ impl RawFramebufferTargetDraw {
     where
         F: RawFramebuffer,
     {
-        if self.bound_fb.get() != framebuffer.handle() {
-            self.bound_fb.set(framebuffer.handle());
+        if self.bound_fb.get() != Some(framebuffer.handle()) {
+            self.bound_fb.set(Some(framebuffer.handle()));
             gl.BindFramebuffer(
                 gl::DRAW_FRAMEBUFFER,
                 framebuffer.handle().map(|h| h.get()).unwrap_or(0),
@@ -193,13 +239,18 @@ impl RawFramebufferTargetDraw {
 
     #[inline]
     pub unsafe fn reset_bind(&self, gl: &Gl) {
-        self.bound_fb.set(None);
+        self.bound_fb.set(Some(None));
         gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
     }
 
     #[inline]
-    pub fn bound_buffer(&self) -> &Cell<Option<Handle>> {
-        &self.bound_fb
+    pub fn invalidate(&self) {
+        self.bound_fb.set(None);
+    }
+
+    #[inline]
+    pub fn is_bound(&self, handle: Option<Handle>) -> bool {
+        self.bound_fb.get() == Some(handle)
     }
 }
 
@@ -216,6 +267,43 @@ where
         }
     }
     #[inline]
+    pub(crate) fn read_buffer(&self, buffer: GLenum) {
+        unsafe {
+            self.gl.ReadBuffer(buffer);
+            assert_eq!(0, self.gl.GetError());
+        }
+    }
+
+    /// Read the depth buffer as floats in the `[0, 1]` range.
+    #[inline]
+    pub(crate) fn read_depth(
+        &self,
+        read_origin: GLVec2<u32, NonNormalized>,
+        read_dims: GLVec2<u32, NonNormalized>,
+        data: &mut [f32],
+    ) {
+        let read_len = (read_dims.x * read_dims.y) as usize;
+        assert_eq!(
+            read_len,
+            data.len(),
+            "expected buffer of length {}, but got buffer of length {}",
+            read_len,
+            data.len()
+        );
+        unsafe {
+            self.gl.ReadPixels(
+                read_origin.x as GLint,
+                read_origin.y as GLint,
+                read_dims.x as GLsizei,
+                read_dims.y as GLsizei,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                data.as_mut_ptr() as *mut GLvoid,
+            );
+            assert_eq!(0, self.gl.GetError());
+        }
+    }
+    #[inline]
     pub(crate) fn read_pixels<C: ImageFormatRenderable + ConcreteImageFormat>(
         &self,
         read_origin: GLVec2<u32, NonNormalized>,
@@ -280,9 +368,62 @@ where
 
     #[inline]
     pub(crate) fn clear_stencil(&mut self, stencil: u32) {
+        let stencil = stencil as GLint;
+        unsafe { self.gl.ClearBufferiv(gl::STENCIL, 0, &stencil) }
+    }
+
+    /// Copy a region of the bound read framebuffer into a region of this framebuffer.
+    ///
+    /// The scissor test and `GL_FRAMEBUFFER_SRGB` both affect blits, so they get disabled for the
+    /// duration of the blit and restored afterwards. Neither is cached by Gullery in a way that
+    /// covers raw GL calls, so their previous state gets queried from OpenGL.
+    #[inline]
+    pub(crate) fn blit_from<R>(
+        &mut self,
+        _read: &RawBoundFramebufferRead<R>,
+        src: (GLVec2<u32, NonNormalized>, GLVec2<u32, NonNormalized>),
+        dst: (GLVec2<u32, NonNormalized>, GLVec2<u32, NonNormalized>),
+        mask: GLbitfield,
+        filter: GLenum,
+    ) where
+        R: RawFramebuffer,
+    {
+        unsafe {
+            let caps = [gl::SCISSOR_TEST, gl::FRAMEBUFFER_SRGB];
+            let was_enabled = caps.map(|cap| self.gl.IsEnabled(cap) == gl::TRUE);
+            for (&cap, &enabled) in caps.iter().zip(&was_enabled) {
+                if enabled {
+                    self.gl.Disable(cap);
+                }
+            }
+
+            self.gl.BlitFramebuffer(
+                src.0.x as GLint,
+                src.0.y as GLint,
+                src.1.x as GLint,
+                src.1.y as GLint,
+                dst.0.x as GLint,
+                dst.0.y as GLint,
+                dst.1.x as GLint,
+                dst.1.y as GLint,
+                mask,
+                filter,
+            );
+            assert_eq!(0, self.gl.GetError());
+
+            for (&cap, &enabled) in caps.iter().zip(&was_enabled) {
+                if enabled {
+                    self.gl.Enable(cap);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn draw_buffer(&mut self, buffer: GLenum) {
         unsafe {
-            self.gl.ClearStencil(stencil as GLint);
-            self.gl.Clear(gl::STENCIL_BUFFER_BIT);
+            self.gl.DrawBuffer(buffer);
+            assert_eq!(0, self.gl.GetError());
         }
     }
 
@@ -295,67 +436,249 @@ where
     }
 
     #[inline]
-    pub(crate) fn draw<R, V, I, U, A>(
+    pub(crate) fn draw<R, V, I, N, U, A>(
         &mut self,
         mode: DrawMode,
         range: R,
-        bound_vao: &BoundVAO<V, I>,
+        bound_vao: &BoundVAO<V, I, N>,
         _bound_program: &BoundProgram<V, U, A>,
         instance_count: Option<usize>,
         base_index: Option<I>,
+        base_instance: Option<u32>,
     ) where
         R: RangeBounds<usize>,
         V: Vertex,
         I: Index,
+        N: Vertex,
         U: Uniforms,
         A: Attachments,
     {
-        let index_type_option = I::INDEX_GL_ENUM;
-        let read_offset = crate::bound_to_num_start(range.start_bound(), 0);
+        draw_vao(
+            self.gl,
+            mode,
+            range,
+            bound_vao,
+            instance_count,
+            base_index,
+            base_instance,
+        );
+    }
 
-        let instance_count = instance_count.map(|instance_count| {
-            assert!(instance_count <= GLsizei::max_value() as usize);
-            instance_count as GLsizei
-        });
+    /// Draw `count` indices read from `index_buffer`, starting `byte_offset` bytes into it. The
+    /// buffer only gets attached to the VAO for the duration of the draw, after which the VAO's
+    /// own index buffer is restored.
+    #[inline]
+    pub(crate) fn draw_with_index_buffer<V, I, J, N, U, A>(
+        &mut self,
+        mode: DrawMode,
+        bound_vao: &BoundVAO<V, J, N>,
+        _bound_program: &BoundProgram<V, U, A>,
+        index_buffer: &Buffer<I>,
+        byte_offset: usize,
+        count: usize,
+    ) where
+        V: Vertex,
+        I: Index,
+        J: Index,
+        N: Vertex,
+        U: Uniforms,
+        A: Attachments,
+    {
+        let index_type = I::INDEX_GL_ENUM.expect("index buffer must hold `u8`, `u16`, or `u32`");
+        assert_eq!(
+            0,
+            byte_offset % mem::size_of::<I>(),
+            "index buffer offset {} isn't aligned to the {}-byte index type",
+            byte_offset,
+            mem::size_of::<I>()
+        );
+        let first = byte_offset / mem::size_of::<I>();
+        assert!(
+            first + count <= index_buffer.len(),
+            "draw reads up to index {}, but the index buffer only has {} indices",
+            first + count,
+            index_buffer.len()
+        );
+        assert!(count <= GLsizei::MAX as usize);
 
-        if let (Some(index_type), Some(index_buffer)) =
-            (index_type_option, bound_vao.vao().index_buffer())
-        {
-            let read_end = crate::bound_to_num_end(range.end_bound(), index_buffer.len());
-            assert!(read_offset <= read_end);
-            assert!((read_end - read_offset) <= GLsizei::max_value() as usize);
-
-            let mode = mode.to_gl_enum();
-            let first = (read_end - read_offset) as GLsizei;
-            let indices = (read_offset * mem::size_of::<I>()) as *const GLvoid;
-
-            unsafe {
-                match (instance_count, base_index) {
-                    (Some(instance_count), Some(base_index)) =>
-                        self.gl.DrawElementsInstancedBaseVertex(mode, first, index_type, indices, instance_count, base_index.as_glint()),
-                    (None, Some(base_index)) =>
-                        self.gl.DrawElementsBaseVertex(mode, first, index_type, indices, base_index.as_glint()),
-                    (Some(instance_count), None) =>
-                        self.gl.DrawElementsInstanced(mode, first, index_type, indices, instance_count),
-                    (None, None) => self.gl.DrawElements(mode, first, index_type, indices),
+        let vao_index_buffer = bound_vao
+            .vao()
+            .index_buffer()
+            .as_ref()
+            .map(|b| b.handle().get());
+        unsafe {
+            self.gl
+                .BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer.handle().get());
+            self.gl.DrawElements(
+                mode.to_gl_enum(),
+                count as GLsizei,
+                index_type,
+                byte_offset as *const GLvoid,
+            );
+            self.gl
+                .BindBuffer(gl::ELEMENT_ARRAY_BUFFER, vao_index_buffer.unwrap_or(0));
+        }
+    }
+}
+
+/// Run `f` with the scissor test restricting rendering to the rectangle from `start` to `end`,
+/// then restore the scissor test's previous enabled state and box.
+///
+/// Gullery doesn't cache scissor state, so the previous state gets queried from OpenGL.
+pub(crate) unsafe fn with_scissor<R>(
+    gl: &Gl,
+    start: GLVec2<u32, NonNormalized>,
+    end: GLVec2<u32, NonNormalized>,
+    f: impl FnOnce() -> R,
+) -> R {
+    assert!(
+        start.x <= end.x && start.y <= end.y,
+        "scissor rectangle start {:?} is past its end {:?}",
+        start,
+        end
+    );
+    let was_enabled = gl.IsEnabled(gl::SCISSOR_TEST) == gl::TRUE;
+    let mut old_box: [GLint; 4] = [0; 4];
+    gl.GetIntegerv(gl::SCISSOR_BOX, old_box.as_mut_ptr());
+
+    gl.Enable(gl::SCISSOR_TEST);
+    gl.Scissor(
+        start.x as GLint,
+        start.y as GLint,
+        (end.x - start.x) as GLsizei,
+        (end.y - start.y) as GLsizei,
+    );
+    let result = f();
+
+    gl.Scissor(old_box[0], old_box[1], old_box[2], old_box[3]);
+    if !was_enabled {
+        gl.Disable(gl::SCISSOR_TEST);
+    }
+    result
+}
+
+/// Issue a draw call for the currently bound VAO and program.
+///
+/// `base_instance` requires OpenGL 4.2 or `ARB_base_instance`; callers are responsible for
+/// checking support. If it's `Some` and `instance_count` is `None`, a single instance is drawn.
+pub(crate) fn draw_vao<R, V, I, N>(
+    gl: &Gl,
+    mode: DrawMode,
+    range: R,
+    bound_vao: &BoundVAO<V, I, N>,
+    instance_count: Option<usize>,
+    base_index: Option<I>,
+    base_instance: Option<u32>,
+) where
+    R: RangeBounds<usize>,
+    V: Vertex,
+    I: Index,
+    N: Vertex,
+{
+    let index_type_option = I::INDEX_GL_ENUM;
+    let read_offset = crate::bound_to_num_start(range.start_bound(), 0);
+
+    if let Some(buffer_instances) = bound_vao.vao().instance_count() {
+        let instances_end =
+            base_instance.unwrap_or(0) as usize + instance_count.unwrap_or(1);
+        assert!(
+            instances_end <= buffer_instances,
+            "draw reads up to instance {}, but the instance buffer only has {} instances",
+            instances_end,
+            buffer_instances
+        );
+    }
+
+    let instance_count = instance_count.map(|instance_count| {
+        assert!(instance_count <= GLsizei::max_value() as usize);
+        instance_count as GLsizei
+    });
+
+    if let (Some(index_type), Some(index_buffer)) =
+        (index_type_option, bound_vao.vao().index_buffer())
+    {
+        let read_end = crate::bound_to_num_end(range.end_bound(), index_buffer.len());
+        assert!(
+            read_end <= index_buffer.len(),
+            "draw range end {} exceeds index count {}",
+            read_end,
+            index_buffer.len()
+        );
+        assert!(read_offset <= read_end);
+        assert!((read_end - read_offset) <= GLsizei::max_value() as usize);
+
+        let mode = mode.to_gl_enum();
+        let first = (read_end - read_offset) as GLsizei;
+        let indices = (read_offset * mem::size_of::<I>()) as *const GLvoid;
+
+        unsafe {
+            if let Some(base_instance) = base_instance {
+                let instance_count = instance_count.unwrap_or(1);
+                match base_index {
+                    Some(base_index) => gl.DrawElementsInstancedBaseVertexBaseInstance(
+                        mode,
+                        first,
+                        index_type,
+                        indices,
+                        instance_count,
+                        base_index.as_glint(),
+                        base_instance,
+                    ),
+                    None => gl.DrawElementsInstancedBaseInstance(
+                        mode,
+                        first,
+                        index_type,
+                        indices,
+                        instance_count,
+                        base_instance,
+                    ),
                 }
+                return;
             }
-        } else {
-            let read_end =
-                crate::bound_to_num_end(range.end_bound(), bound_vao.vao().vertex_buffer().len());
-            assert!(read_offset <= GLint::max_value() as usize);
-            assert!(read_offset <= read_end);
-            assert!((read_end - read_offset) <= isize::max_value() as usize);
-
-            let mode = mode.to_gl_enum();
-            let count = (read_end - read_offset) as GLsizei;
-            let read_offset = read_offset as GLint;
-
-            unsafe {
-                match instance_count {
-                    Some(instance_count) => self.gl.DrawArraysInstanced(mode, read_offset, count, instance_count),
-                    None => self.gl.DrawArrays(mode, read_offset, count),
-                }
+
+            match (instance_count, base_index) {
+                (Some(instance_count), Some(base_index)) =>
+                    gl.DrawElementsInstancedBaseVertex(mode, first, index_type, indices, instance_count, base_index.as_glint()),
+                (None, Some(base_index)) =>
+                    gl.DrawElementsBaseVertex(mode, first, index_type, indices, base_index.as_glint()),
+                (Some(instance_count), None) =>
+                    gl.DrawElementsInstanced(mode, first, index_type, indices, instance_count),
+                (None, None) => gl.DrawElements(mode, first, index_type, indices),
+            }
+        }
+    } else {
+        let vertex_count = bound_vao.vao().vertex_count();
+        let read_end = crate::bound_to_num_end(range.end_bound(), vertex_count);
+        assert!(
+            read_end <= vertex_count,
+            "draw range end {} exceeds vertex count {}",
+            read_end,
+            vertex_count
+        );
+        assert!(read_offset <= GLint::max_value() as usize);
+        assert!(read_offset <= read_end);
+        assert!((read_end - read_offset) <= isize::max_value() as usize);
+
+        let mode = mode.to_gl_enum();
+        let count = (read_end - read_offset) as GLsizei;
+        let read_offset = read_offset as GLint;
+
+        unsafe {
+            if let Some(base_instance) = base_instance {
+                let instance_count = instance_count.unwrap_or(1);
+                gl.DrawArraysInstancedBaseInstance(
+                    mode,
+                    read_offset,
+                    count,
+                    instance_count,
+                    base_instance,
+                );
+                return;
+            }
+
+            match instance_count {
+                Some(instance_count) => gl.DrawArraysInstanced(mode, read_offset, count, instance_count),
+                None => gl.DrawArrays(mode, read_offset, count),
             }
         }
     }
@@ -385,16 +708,64 @@ pub unsafe trait RawBoundFramebuffer {
     const TARGET: GLenum;
     fn gl(&self) -> &Gl;
 
-    fn set_attachments<A: Attachments>(&mut self, handles: &mut [Option<Handle>], attachments: &A) {
-        struct Attacher<'a, A: 'a + Attachments, I: Iterator<Item = &'a mut Option<Handle>>> {
+    /// Attach a single level of a non-layered texture, bypassing the `Attachments` machinery.
+    ///
+    /// Only use this on framebuffers that don't get attachments set through `set_attachments`,
+    /// since the cached attachment handles won't reflect the new attachment.
+    unsafe fn attach_texture_level(&mut self, attachment: GLenum, texture: Handle, level: GLint) {
+        self.gl()
+            .FramebufferTexture(Self::TARGET, attachment, texture.get(), level);
+        assert_eq!(0, self.gl().GetError());
+    }
+
+    /// Detach whatever's attached to `attachment` by `attach_texture_level`.
+    unsafe fn detach(&mut self, attachment: GLenum) {
+        self.gl().FramebufferTexture(Self::TARGET, attachment, 0, 0);
+    }
+
+    fn set_attachments<A: Attachments>(
+        &mut self,
+        images: &mut [Option<AttachedImage>],
+        attachments: &A,
+    ) {
+        struct Attacher<'a, A: 'a + Attachments, I: Iterator<Item = &'a mut Option<AttachedImage>>> {
             color_index: GLenum,
             depth_attachment_used: bool,
             gl: &'a Gl,
-            handles: I,
+            images: I,
             target: GLenum,
             attachments: &'a A,
         }
-        impl<'a, A: Attachments, I: Iterator<Item = &'a mut Option<Handle>>>
+        impl<'a, A: Attachments, I: Iterator<Item = &'a mut Option<AttachedImage>>> Attacher<'a, A, I> {
+            fn next_attachment(
+                &mut self,
+                format_type: FormatTypeTag,
+            ) -> (GLenum, &'a mut Option<AttachedImage>) {
+                let attachment = match format_type {
+                    FormatTypeTag::Color => {
+                        let attachment = gl::COLOR_ATTACHMENT0 + self.color_index;
+                        self.color_index += 1;
+                        attachment
+                    }
+                    FormatTypeTag::Depth | FormatTypeTag::DepthStencil => {
+                        if self.depth_attachment_used {
+                            panic!("Attempted to attach multiple depth images to a single FBO");
+                        }
+                        self.depth_attachment_used = true;
+                        match format_type {
+                            FormatTypeTag::DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+                            _ => gl::DEPTH_ATTACHMENT,
+                        }
+                    }
+                };
+                let image = self
+                    .images
+                    .next()
+                    .expect("Mismatched attachment handle container length");
+                (attachment, image)
+            }
+        }
+        impl<'a, A: Attachments, I: Iterator<Item = &'a mut Option<AttachedImage>>>
             AttachmentsMemberRegistry for Attacher<'a, A, I>
         {
             type Attachments = A;
@@ -406,34 +777,18 @@ pub unsafe trait RawBoundFramebuffer {
                 Im: ImageFormatRenderable,
             {
                 let member = get_member(self.attachments);
-                let handle = self
-                    .handles
-                    .next()
-                    .expect("Mismatched attachment handle container length");
-                if Some(member.handle()) != *handle {
-                    *handle = Some(member.handle());
-                    let handle = member.handle();
-                    let attachment: GLenum;
-                    match <<Renderbuffer<Im> as AttachmentType>::Format as ImageFormatRenderable>::FormatType::FORMAT_TYPE {
-                        FormatTypeTag::Color => {
-                            attachment = gl::COLOR_ATTACHMENT0 + self.color_index;
-                            self.color_index += 1;
-                        },
-                        FormatTypeTag::Depth => {
-                            if self.depth_attachment_used {
-                                panic!("Attempted to attach multiple depth images to a single FBO");
-                            }
-                            self.depth_attachment_used = true;
-                            attachment = gl::DEPTH_ATTACHMENT;
-                        }
-                    }
-
+                let (attachment, image) = self.next_attachment(
+                    <<Renderbuffer<Im> as AttachmentType>::Format as ImageFormatRenderable>::FormatType::FORMAT_TYPE
+                );
+                let new_image = AttachedImage::new(member.handle(), 0, None);
+                if Some(new_image) != *image {
+                    *image = Some(new_image);
                     unsafe {
                         self.gl.FramebufferRenderbuffer(
                             self.target,
                             attachment,
                             gl::RENDERBUFFER,
-                            handle.get(),
+                            member.handle().get(),
                         );
                         assert_eq!(0, self.gl.GetError());
                     }
@@ -442,44 +797,46 @@ pub unsafe trait RawBoundFramebuffer {
             fn add_texture<D, T>(
                 &mut self,
                 _: &str,
-                get_member: impl FnOnce(&Self::Attachments) -> &Texture<D, T>,
-                texture_level: T::MipSelector,
+                get_member: impl FnOnce(&Self::Attachments) -> (&Texture<D, T>, T::MipSelector, Option<u32>),
             ) where
                 D: Dimension<u32>,
                 T: TextureType<D>,
                 T::Format: ImageFormatRenderable,
             {
-                let texture = get_member(self.attachments);
-                let handle = self
-                    .handles
-                    .next()
-                    .expect("Mismatched attachment handle container length");
-                if Some(texture.handle()) != *handle {
-                    *handle = Some(texture.handle());
-                    let handle = texture.handle();
-                    let attachment: GLenum;
-                    match <<Texture<D, T> as AttachmentType>::Format as ImageFormatRenderable>::FormatType::FORMAT_TYPE {
-                        FormatTypeTag::Color => {
-                            attachment = gl::COLOR_ATTACHMENT0 + self.color_index;
-                            self.color_index += 1;
-                        },
-                        FormatTypeTag::Depth => {
-                            if self.depth_attachment_used {
-                                panic!("Attempted to attach multiple depth images to a single FBO");
-                            }
-                            self.depth_attachment_used = true;
-                            attachment = gl::DEPTH_ATTACHMENT;
-                        }
-                    }
-
+                let (texture, texture_level, layer) = get_member(self.attachments);
+                let (attachment, image) = self.next_attachment(
+                    <<Texture<D, T> as AttachmentType>::Format as ImageFormatRenderable>::FormatType::FORMAT_TYPE
+                );
+                let handle = texture.handle();
+                let level = texture_level.to_glint();
+                let new_image = AttachedImage::new(handle, level, layer);
+                if Some(new_image) != *image {
+                    *image = Some(new_image);
                     unsafe {
-                        // TODO: HANDLE LAYERED TEXTURES
-                        self.gl.FramebufferTexture(
-                            self.target,
-                            attachment,
-                            handle.get(),
-                            texture_level.to_glint(),
-                        );
+                        match (layer, T::BIND_TARGET) {
+                            (None, _) => self.gl.FramebufferTexture(
+                                self.target,
+                                attachment,
+                                handle.get(),
+                                level,
+                            ),
+                            // Cubemap faces can only be attached through `FramebufferTextureLayer`
+                            // in GL 4.5, so attach them as 2D images instead.
+                            (Some(face), gl::TEXTURE_CUBE_MAP) => self.gl.FramebufferTexture2D(
+                                self.target,
+                                attachment,
+                                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                                handle.get(),
+                                level,
+                            ),
+                            (Some(layer), _) => self.gl.FramebufferTextureLayer(
+                                self.target,
+                                attachment,
+                                handle.get(),
+                                level,
+                                layer as GLint,
+                            ),
+                        }
                         assert_eq!(0, self.gl.GetError());
                     }
                 }
@@ -489,7 +846,7 @@ pub unsafe trait RawBoundFramebuffer {
         A::members(Attacher {
             color_index: 0,
             depth_attachment_used: false,
-            handles: handles.iter_mut(),
+            images: images.iter_mut(),
             gl: self.gl(),
             target: Self::TARGET,
             attachments,
@@ -501,8 +858,16 @@ pub unsafe trait RawBoundFramebuffer {
 }
 
 impl DrawMode {
+    /// The OpenGL enum for this mode.
+    #[inline]
+    pub fn to_gl_enum(self) -> GLenum {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl DefaultBuffer {
     #[inline]
-    fn to_gl_enum(self) -> GLenum {
+    pub(crate) fn to_gl_enum(self) -> GLenum {
         unsafe { mem::transmute(self) }
     }
 }
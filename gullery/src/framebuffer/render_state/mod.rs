@@ -31,80 +31,286 @@ pub struct RenderState {
     pub cull: Option<(CullFace, FrontFace)>,
     pub depth_clamp: bool,
     pub depth_test: Option<DepthStencilFunc>,
+    /// Whether colors get dithered when they're written to attachments with fewer bits per
+    /// channel than the color being written. Defaults to `true`, matching OpenGL.
+    ///
+    /// Dithering is allowed to add noise to any write, and `GL_DITHER` also affects clears, which
+    /// don't upload a render state and use whatever was set by the last draw. Pixels read back
+    /// from an 8-bit attachment may not exactly match the color drawn or cleared with, which
+    /// breaks pixel-exact comparisons on some drivers. Use
+    /// [`ContextState::set_dither_allowed`] to keep dithering off for the whole context,
+    /// regardless of this field.
+    ///
+    /// [`ContextState::set_dither_allowed`]: ../../struct.ContextState.html#method.set_dither_allowed
     pub dither: bool,
+    /// Whether linear colors written by shaders get encoded to sRGB when rendering into sRGB
+    /// attachments. Has no effect on linear attachments or on sampling; textures decode sRGB
+    /// data when sampled based only on their storage format.
+    ///
+    /// Corresponds to `GL_FRAMEBUFFER_SRGB`.
     pub srgb: bool,
     pub multisample: bool,
+    /// Run the fragment shader for each sample, rather than once per pixel, when rendering to a
+    /// multisampled framebuffer.
+    ///
+    /// The value is the minimum fraction of samples that get shaded individually, clamped to
+    /// `0.0..=1.0`. `Some(1.0)` shades every sample, which antialiases specular highlights and
+    /// alpha-tested edges that regular MSAA can't.
+    ///
+    /// Requires OpenGL 4.0 or `GL_ARB_sample_shading`; uploading a render state with this set to
+    /// `Some` on a context without either panics. Check for support with
+    /// [`ContextState::supports_sample_shading`].
+    ///
+    /// [`ContextState::supports_sample_shading`]: ../../struct.ContextState.html#method.supports_sample_shading
+    pub sample_shading: Option<f32>,
+    /// Restrict which samples of a multisampled framebuffer fragments can write to. Bit `n` of the
+    /// mask controls sample `n`; samples whose bit is unset are left untouched.
+    ///
+    /// Useful for stochastic transparency and other per-sample effects. Only the first 32 samples
+    /// can be masked, and the mask has no effect when rendering to single-sampled targets.
+    pub sample_mask: Option<u32>,
     pub primitive_restart_index: Option<u32>,
     pub rasterizer_discard: bool,
+    /// Rasterize every pixel that a primitive touches, rather than just the pixels whose centers
+    /// it covers. Useful for voxelization.
+    ///
+    /// Requires `GL_NV_conservative_raster` or `GL_INTEL_conservative_rasterization`; on contexts
+    /// without either extension this does nothing. Check for support with
+    /// [`ContextState::supports_conservative_raster`].
+    ///
+    /// [`ContextState::supports_conservative_raster`]: ../../struct.ContextState.html#method.supports_conservative_raster
+    pub conservative_raster: bool,
     pub stencil_test: Option<StencilTest>,
     pub texture_cubemap_seamless: bool,
     pub program_point_size: bool,
     pub polygon_offset: Option<PolygonOffset>,
+    /// The region of the framebuffer that draw calls render into. Clears ignore the viewport; only
+    /// the scissor test restricts them.
+    ///
+    /// This shares its cache with [`ContextState::set_viewport`], so uploading a render state
+    /// overrides any viewport set there.
+    ///
+    /// [`ContextState::set_viewport`]: ../../struct.ContextState.html#method.set_viewport
     pub viewport: RangeInclusive<GLVec2<u32, NonNormalized>>,
+    /// A viewport with fractional bounds, as `[x, y, width, height]` in pixels. When set, this
+    /// gets used in place of [`viewport`].
+    ///
+    /// Offsetting the viewport by a fraction of a pixel each frame is a way to jitter the
+    /// rendered image for temporal antialiasing, without touching the projection matrix.
+    /// Requires OpenGL 4.1 or `GL_ARB_viewport_array`; on contexts without either, the bounds
+    /// get rounded to whole pixels. Check for support with
+    /// [`ContextState::supports_fractional_viewport`].
+    ///
+    /// [`viewport`]: #structfield.viewport
+    /// [`ContextState::supports_fractional_viewport`]: ../../struct.ContextState.html#method.supports_fractional_viewport
+    pub viewport_f: Option<[f32; 4]>,
+    /// The color channels that can be written to, both by draw calls and by color clears.
+    /// Defaults to every channel.
+    ///
+    /// Clearing after a render state with some channels masked off leaves those channels
+    /// untouched. Use [`clear_color_all_forced`] to clear every channel regardless of the mask.
+    ///
+    /// [`clear_color_all_forced`]: ../trait.Framebuffer.html#method.clear_color_all_forced
     pub color_mask: ColorMask,
     pub depth_mask: bool,
+    /// The bits of the stencil buffer that can be written to, both by the stencil test's
+    /// operations and by [`clear_stencil`]. Defaults to all ones.
+    ///
+    /// [`clear_stencil`]: ../trait.Framebuffer.html#method.clear_stencil
+    pub stencil_mask: u32,
 }
 
 impl RenderState {
+    /// State for opaque geometry: depth testing with `Less`, depth writes, and back-face culling
+    /// of clockwise faces. Blending is off.
+    ///
+    /// Like every preset, this leaves the viewport empty, so it still needs to be set.
+    #[inline]
+    pub fn opaque() -> RenderState {
+        RenderState {
+            cull: Some((CullFace::Back, FrontFace::CounterClockwise)),
+            depth_test: Some(DepthStencilFunc::Less),
+            depth_mask: true,
+            ..RenderState::default()
+        }
+    }
+
+    /// State for translucent geometry drawn after the opaque geometry: depth testing against the
+    /// opaque geometry, but no depth writes, so translucent surfaces don't hide each other. Uses
+    /// [`BlendFuncs::alpha`] blending and doesn't cull, so both sides of thin surfaces show up.
+    ///
+    /// Translucent geometry still needs to be sorted back to front for the blending to be right.
+    ///
+    /// [`BlendFuncs::alpha`]: ./struct.BlendFuncs.html#method.alpha
+    #[inline]
+    pub fn transparent() -> RenderState {
+        RenderState {
+            blend: BlendFuncs::alpha(),
+            depth_test: Some(DepthStencilFunc::Less),
+            depth_mask: false,
+            ..RenderState::default()
+        }
+    }
+
+    /// State for UI drawn over the rest of the scene: no depth testing or depth writes, no
+    /// culling, and [`BlendFuncs::alpha`] blending.
+    ///
+    /// The render state doesn't include a scissor rectangle, so clipping UI elements has to be
+    /// done separately.
+    ///
+    /// [`BlendFuncs::alpha`]: ./struct.BlendFuncs.html#method.alpha
+    #[inline]
+    pub fn ui_overlay() -> RenderState {
+        RenderState {
+            blend: BlendFuncs::alpha(),
+            depth_test: None,
+            depth_mask: false,
+            ..RenderState::default()
+        }
+    }
+
     #[inline]
     pub fn upload_state(&self, state: &ContextState) {
-        let old_state = state.render_state.replace(self.clone());
+        self.upload_changed(state, false);
+    }
+
+    /// Upload every field, regardless of the cached state. Used to bring OpenGL back in sync with
+    /// the cache after it may have been changed behind Gullery's back.
+    #[inline]
+    pub(crate) fn force_upload_state(&self, state: &ContextState) {
+        self.upload_changed(state, true);
+    }
+
+    fn upload_changed(&self, state: &ContextState, force: bool) {
+        assert!(
+            self.sample_shading.is_none() || state.sample_shading,
+            "context doesn't support sample shading"
+        );
+        let dither = self.dither && state.dither_allowed.get();
+        let old_state = state.render_state.replace(RenderState {
+            dither,
+            ..self.clone()
+        });
         let gl = &state.gl;
-        if self.blend != old_state.blend {
+        if force || self.blend != old_state.blend {
             raw::set_gl_cap(gl, Capability::Blend(Some(self.blend)));
         }
-        if self.cull != old_state.cull {
+        if force || self.cull != old_state.cull {
             raw::set_gl_cap(gl, Capability::Cull(self.cull));
         }
-        if self.depth_clamp != old_state.depth_clamp {
+        if force || self.depth_clamp != old_state.depth_clamp {
             raw::set_gl_cap(gl, Capability::DepthClamp(self.depth_clamp));
         }
-        if self.depth_test != old_state.depth_test {
+        if force || self.depth_test != old_state.depth_test {
             raw::set_gl_cap(gl, Capability::DepthTest(self.depth_test));
         }
-        if self.dither != old_state.dither {
-            raw::set_gl_cap(gl, Capability::Dither(self.dither));
+        if force || dither != old_state.dither {
+            raw::set_gl_cap(gl, Capability::Dither(dither));
         }
-        if self.srgb != old_state.srgb {
+        if force || self.srgb != old_state.srgb {
             raw::set_gl_cap(gl, Capability::Srgb(self.srgb));
         }
-        if self.multisample != old_state.multisample {
+        if force || self.multisample != old_state.multisample {
             raw::set_gl_cap(gl, Capability::Multisample(self.multisample));
         }
-        if self.primitive_restart_index != old_state.primitive_restart_index {
+        if (force || self.sample_shading != old_state.sample_shading) && state.sample_shading {
+            raw::set_gl_cap(gl, Capability::SampleShading(self.sample_shading));
+        }
+        if force || self.sample_mask != old_state.sample_mask {
+            raw::set_gl_cap(gl, Capability::SampleMask(self.sample_mask));
+        }
+        if force || self.primitive_restart_index != old_state.primitive_restart_index {
             raw::set_gl_cap(
                 gl,
                 Capability::PrimitiveRestart(self.primitive_restart_index),
             );
         }
-        if self.rasterizer_discard != old_state.rasterizer_discard {
+        if force || self.rasterizer_discard != old_state.rasterizer_discard {
             raw::set_gl_cap(gl, Capability::RasterizerDiscard(self.rasterizer_discard));
         }
-        if self.stencil_test != old_state.stencil_test {
+        if force || self.conservative_raster != old_state.conservative_raster {
+            if let Some(capability) = state.conservative_raster_capability {
+                raw::set_conservative_raster(gl, capability, self.conservative_raster);
+            }
+        }
+        if force || self.stencil_test != old_state.stencil_test {
             raw::set_gl_cap(gl, Capability::StencilTest(self.stencil_test));
         }
-        if self.texture_cubemap_seamless != old_state.texture_cubemap_seamless {
+        if force || self.texture_cubemap_seamless != old_state.texture_cubemap_seamless {
             raw::set_gl_cap(
                 gl,
                 Capability::TextureCubemapSeamless(self.texture_cubemap_seamless),
             );
         }
-        if self.program_point_size != old_state.program_point_size {
+        if force || self.program_point_size != old_state.program_point_size {
             raw::set_gl_cap(gl, Capability::ProgramPointSize(self.program_point_size));
         }
-        if self.polygon_offset != old_state.polygon_offset {
+        if force || self.polygon_offset != old_state.polygon_offset {
             raw::set_gl_cap(gl, Capability::PolygonOffset(self.polygon_offset));
         }
-        if self.viewport != old_state.viewport {
-            raw::set_viewport(gl, *self.viewport.start(), *self.viewport.end());
+        if force || self.viewport != old_state.viewport || self.viewport_f != old_state.viewport_f {
+            match self.viewport_f {
+                Some(viewport_f) => raw::set_viewport_f(gl, viewport_f, state.viewport_array),
+                None => raw::set_viewport(gl, *self.viewport.start(), *self.viewport.end()),
+            }
         }
-        if self.color_mask != old_state.color_mask {
+        if force || self.color_mask != old_state.color_mask {
             raw::set_color_mask(gl, self.color_mask);
         }
-        if self.depth_mask != old_state.depth_mask {
+        if force || self.depth_mask != old_state.depth_mask {
             raw::set_depth_mask(gl, self.depth_mask);
         }
+        if force || self.stencil_mask != old_state.stencil_mask {
+            raw::set_stencil_mask(gl, self.stencil_mask);
+        }
+    }
+}
+
+/// Set the viewport without uploading the rest of the render state, skipping the GL call if the
+/// cached viewport already matches.
+#[inline]
+pub(crate) fn set_viewport(
+    state: &ContextState,
+    viewport: RangeInclusive<GLVec2<u32, NonNormalized>>,
+) {
+    let mut render_state = state.render_state.take();
+    // A fractional viewport overrides the integer one, so it has to be cleared too.
+    let had_viewport_f = render_state.viewport_f.take().is_some();
+    if had_viewport_f || render_state.viewport != viewport {
+        raw::set_viewport(&state.gl, *viewport.start(), *viewport.end());
+        render_state.viewport = viewport;
+    }
+    state.render_state.set(render_state);
+}
+
+/// Disable dithering right away if `allowed` is `false`, rather than waiting for the next render
+/// state upload, so that clears don't get dithered either.
+#[inline]
+pub(crate) fn set_dither_allowed(state: &ContextState, allowed: bool) {
+    state.dither_allowed.set(allowed);
+    let mut render_state = state.render_state.take();
+    if !allowed && render_state.dither {
+        raw::set_gl_cap(&state.gl, Capability::Dither(false));
+        render_state.dither = false;
+    }
+    state.render_state.set(render_state);
+}
+
+/// Run `f` with every color channel writable, restoring the cached color mask afterwards. Used
+/// for clears that shouldn't be affected by `RenderState::color_mask`.
+#[inline]
+pub(crate) fn with_full_color_mask<R>(state: &ContextState, f: impl FnOnce() -> R) -> R {
+    let render_state = state.render_state.take();
+    let color_mask = render_state.color_mask;
+    state.render_state.set(render_state);
+
+    if color_mask == ColorMask::all() {
+        return f();
     }
+    raw::set_color_mask(&state.gl, ColorMask::all());
+    let result = f();
+    raw::set_color_mask(&state.gl, color_mask);
+    result
 }
 
 impl Default for RenderState {
@@ -118,15 +324,20 @@ impl Default for RenderState {
             dither: true,
             srgb: false,
             multisample: true,
+            sample_shading: None,
+            sample_mask: None,
             primitive_restart_index: None,
             rasterizer_discard: false,
+            conservative_raster: false,
             stencil_test: None,
             texture_cubemap_seamless: false,
             program_point_size: false,
             polygon_offset: None,
             viewport: GLVec2::new(0, 0)..=GLVec2::new(0, 0),
+            viewport_f: None,
             color_mask: ColorMask::default(),
             depth_mask: true,
+            stencil_mask: !0,
         }
     }
 }
@@ -28,6 +28,8 @@ pub enum Capability {
     Dither(bool),
     Srgb(bool),
     Multisample(bool),
+    SampleShading(Option<f32>),
+    SampleMask(Option<u32>),
     PrimitiveRestart(Option<u32>),
     RasterizerDiscard(bool),
     StencilTest(Option<StencilTest>),
@@ -55,6 +57,56 @@ impl Default for BlendFuncs {
     }
 }
 
+impl BlendFuncs {
+    /// Standard alpha blending, for colors that aren't premultiplied by their alpha.
+    ///
+    /// The destination alpha gets blended with `src_alpha: One`, rather than with the source alpha.
+    /// Blending alpha with `SrcAlpha` multiplies the source alpha by itself, which makes
+    /// translucent surfaces rendered into a texture more transparent than they should be.
+    #[inline]
+    pub fn alpha() -> BlendFuncs {
+        BlendFuncs {
+            src_rgb: BlendFunc::SrcAlpha,
+            dst_rgb: BlendFunc::OneMinusSrcAlpha,
+            src_alpha: BlendFunc::One,
+            dst_alpha: BlendFunc::OneMinusSrcAlpha,
+        }
+    }
+
+    /// Alpha blending for colors that have already been multiplied by their alpha.
+    #[inline]
+    pub fn premultiplied() -> BlendFuncs {
+        BlendFuncs {
+            src_rgb: BlendFunc::One,
+            dst_rgb: BlendFunc::OneMinusSrcAlpha,
+            src_alpha: BlendFunc::One,
+            dst_alpha: BlendFunc::OneMinusSrcAlpha,
+        }
+    }
+
+    /// Adds the source color to the destination color. Useful for lights and particle effects.
+    #[inline]
+    pub fn additive() -> BlendFuncs {
+        BlendFuncs {
+            src_rgb: BlendFunc::One,
+            dst_rgb: BlendFunc::One,
+            src_alpha: BlendFunc::One,
+            dst_alpha: BlendFunc::One,
+        }
+    }
+
+    /// Multiplies the destination color by the source color. Useful for tinting and shadow decals.
+    #[inline]
+    pub fn multiply() -> BlendFuncs {
+        BlendFuncs {
+            src_rgb: BlendFunc::DstColor,
+            dst_rgb: BlendFunc::Zero,
+            src_alpha: BlendFunc::DstAlpha,
+            dst_alpha: BlendFunc::Zero,
+        }
+    }
+}
+
 bitflags! {
     pub struct ColorMask: u8 {
         const R = 1 << 0;
@@ -191,6 +243,20 @@ pub fn set_gl_cap(gl: &Gl, cap: Capability) {
                 gl_capability = &[gl::MULTISAMPLE];
                 enable = ms;
             }
+            SampleShading(min_rate_opt) => {
+                gl_capability = &[gl::SAMPLE_SHADING_ARB];
+                if let Some(min_rate) = min_rate_opt {
+                    enable = true;
+                    gl.MinSampleShadingARB(min_rate.max(0.0).min(1.0));
+                }
+            }
+            SampleMask(mask_opt) => {
+                gl_capability = &[gl::SAMPLE_MASK];
+                if let Some(mask) = mask_opt {
+                    enable = true;
+                    gl.SampleMaski(0, mask);
+                }
+            }
             PrimitiveRestart(restart_opt) => {
                 gl_capability = &[gl::PRIMITIVE_RESTART];
                 if let Some(restart) = restart_opt {
@@ -259,6 +325,23 @@ pub fn set_viewport(gl: &Gl, min: GLVec2<u32, NonNormalized>, max: GLVec2<u32, N
     }
 }
 
+/// Set the viewport from `[x, y, width, height]`. Without `indexed`, the bounds get rounded to
+/// whole pixels, since `glViewport` only takes integers.
+pub fn set_viewport_f(gl: &Gl, [x, y, width, height]: [f32; 4], indexed: bool) {
+    unsafe {
+        if indexed {
+            gl.ViewportIndexedf(0, x, y, width, height);
+        } else {
+            gl.Viewport(
+                x.round() as GLint,
+                y.round() as GLint,
+                width.round() as GLint,
+                height.round() as GLint,
+            );
+        }
+    }
+}
+
 pub fn set_color_mask(gl: &Gl, mask: ColorMask) {
     unsafe {
         gl.ColorMask(
@@ -276,6 +359,21 @@ pub fn set_depth_mask(gl: &Gl, mask: bool) {
     }
 }
 
+pub fn set_stencil_mask(gl: &Gl, mask: u32) {
+    unsafe {
+        gl.StencilMask(mask);
+    }
+}
+
+pub fn set_conservative_raster(gl: &Gl, capability: GLenum, enable: bool) {
+    unsafe {
+        match enable {
+            true => gl.Enable(capability),
+            false => gl.Disable(capability),
+        }
+    }
+}
+
 impl From<BlendFunc> for GLenum {
     #[inline]
     fn from(func: BlendFunc) -> GLenum {
@@ -42,6 +42,10 @@ impl RenderbufferTarget {
     pub(crate) fn new() -> RenderbufferTarget {
         RenderbufferTarget(RawRenderbufferTarget::new())
     }
+
+    pub(crate) fn invalidate(&self) {
+        self.0.invalidate();
+    }
 }
 
 impl<I: ImageFormatRenderable> Renderbuffer<I> {
@@ -70,10 +74,10 @@ impl<I: ImageFormatRenderable> Renderbuffer<I> {
             ),
         };
 
-        unsafe {
+        let samples = unsafe {
             let mut bind = state.renderbuffer_target.0.bind_mut(&mut raw, &state.gl);
-            bind.alloc_storage(internal_format, dims, samples);
-        }
+            bind.alloc_storage(internal_format, dims, samples)
+        };
 
         Renderbuffer {
             raw,
@@ -91,6 +95,11 @@ impl<I: ImageFormatRenderable> Renderbuffer<I> {
     }
 
     /// The number of multisampling samples.
+    ///
+    /// This is the number the driver actually allocated, which can be higher than the number
+    /// passed to [`new`], since drivers round requests up to a sample count they support.
+    ///
+    /// [`new`]: #method.new
     #[inline(always)]
     pub fn samples(&self) -> u32 {
         self.samples
@@ -71,6 +71,11 @@ impl RawRenderbufferTarget {
         }
     }
 
+    #[inline]
+    pub fn invalidate(&self) {
+        self.bound_buffer.set(None);
+    }
+
     #[inline]
     pub unsafe fn bind_mut<'a>(
         &'a self,
@@ -96,12 +101,14 @@ impl RawRenderbufferTarget {
 }
 
 impl<'a> RawBoundRenderbufferMut<'a> {
+    /// Allocate the renderbuffer's storage, returning the number of samples the driver actually
+    /// allocated.
     pub fn alloc_storage(
         &mut self,
         internal_format: GLenum,
         dims: GLVec2<u32, NonNormalized>,
         samples: u32,
-    ) {
+    ) -> u32 {
         unsafe {
             self.gl.RenderbufferStorageMultisample(
                 gl::RENDERBUFFER,
@@ -111,6 +118,14 @@ impl<'a> RawBoundRenderbufferMut<'a> {
                 dims.y as GLsizei,
             );
             assert_eq!(0, self.gl.GetError());
+
+            let mut allocated_samples = 0;
+            self.gl.GetRenderbufferParameteriv(
+                gl::RENDERBUFFER,
+                gl::RENDERBUFFER_SAMPLES,
+                &mut allocated_samples,
+            );
+            allocated_samples as u32
         }
     }
 }
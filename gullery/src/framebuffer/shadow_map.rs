@@ -0,0 +1,152 @@
+// Copyright 2018 Osspial
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Depth-only render targets for shadow mapping.
+//!
+//! A [`ShadowMap`] bundles a depth texture, a framebuffer that renders into it, and a comparison
+//! sampler. Render the scene's depth from the light's point of view into
+//! [`ShadowMap::bind_as_target`], then pass [`ShadowMap::sampled`] to a `sampler2DShadow` uniform
+//! in the lighting pass.
+//!
+//! [`ShadowMap`]: ./struct.ShadowMap.html
+//! [`ShadowMap::bind_as_target`]: ./struct.ShadowMap.html#method.bind_as_target
+//! [`ShadowMap::sampled`]: ./struct.ShadowMap.html#method.sampled
+
+use super::{
+    attachments::{AttachedImage, AttachmentType, Attachments, AttachmentsMemberRegistry},
+    FramebufferObject, FramebufferObjectAttached,
+};
+use crate::{
+    framebuffer::render_state::DepthStencilFunc,
+    geometry::{GLVec2, D2},
+    image_format::Depth32F,
+    texture::{
+        sample_parameters::{FilterMag, FilterMin, SampleParameters, TextureWrap, TextureWrapAxis},
+        Sampler, ShadowSampledTexture, Texture, TextureCreateError,
+    },
+    ContextState,
+};
+
+use std::rc::Rc;
+
+/// The attachments used when rendering into a [`ShadowMap`].
+///
+/// [`ShadowMap`]: ./struct.ShadowMap.html
+pub struct ShadowMapAttachments<'a> {
+    pub depth: &'a mut Texture<D2, Depth32F>,
+}
+
+/// A depth texture paired with the framebuffer and comparison sampler needed to use it as a
+/// shadow map.
+pub struct ShadowMap {
+    texture: Texture<D2, Depth32F>,
+    sampler: Sampler,
+    fbo: FramebufferObject<ShadowMapAttachments<'static>>,
+}
+
+impl<'a> Attachments for ShadowMapAttachments<'a> {
+    type AHC = [Option<AttachedImage>; 1];
+    type Static = ShadowMapAttachments<'static>;
+
+    #[inline]
+    fn members<M>(mut reg: M)
+    where
+        M: AttachmentsMemberRegistry<Attachments = Self>,
+    {
+        <&'a mut Texture<D2, Depth32F> as AttachmentType>::add_to_registry(
+            &mut reg,
+            "depth",
+            |t| &t.depth,
+            Default::default(),
+        );
+    }
+}
+
+impl ShadowMap {
+    /// Create a shadow map with the given dimensions.
+    ///
+    /// The sampler passes fragments whose reference depth is less than the stored depth, and
+    /// linearly filters the comparison results for hardware-accelerated percentage-closer
+    /// filtering. Its parameters can be changed with [`sampler_mut`].
+    ///
+    /// [`sampler_mut`]: ./struct.ShadowMap.html#method.sampler_mut
+    pub fn new(
+        dims: GLVec2<u32>,
+        state: Rc<ContextState>,
+    ) -> Result<ShadowMap, TextureCreateError<D2, Depth32F>> {
+        let texture = Texture::with_mip_count(dims, 1, state.clone())?;
+        let sampler = Sampler::with_parameters(
+            SampleParameters {
+                filter_min: FilterMin::Linear,
+                filter_mag: FilterMag::Linear,
+                texture_wrap: TextureWrap {
+                    s: TextureWrapAxis::ClampToEdge,
+                    t: TextureWrapAxis::ClampToEdge,
+                    r: TextureWrapAxis::ClampToEdge,
+                },
+                compare: Some(DepthStencilFunc::Less),
+                ..SampleParameters::default()
+            },
+            state.clone(),
+        );
+        let fbo = FramebufferObject::new(state)
+            .expect("depth-only framebuffer can't exceed the color attachment limit");
+
+        Ok(ShadowMap {
+            texture,
+            sampler,
+            fbo,
+        })
+    }
+
+    /// Get a framebuffer that renders depth into the shadow map.
+    #[inline]
+    pub fn bind_as_target(
+        &mut self,
+    ) -> FramebufferObjectAttached<
+        ShadowMapAttachments<'_>,
+        &mut FramebufferObject<ShadowMapAttachments<'static>>,
+    > {
+        FramebufferObjectAttached {
+            fbo: &mut self.fbo,
+            attachments: ShadowMapAttachments {
+                depth: &mut self.texture,
+            },
+        }
+    }
+
+    /// Get the shadow map as a comparison-sampled uniform.
+    #[inline]
+    pub fn sampled(&self) -> ShadowSampledTexture<'_, D2, Depth32F> {
+        ShadowSampledTexture {
+            sampler: &self.sampler,
+            texture: &self.texture,
+        }
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Texture<D2, Depth32F> {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    #[inline]
+    pub fn sampler_mut(&mut self) -> &mut Sampler {
+        &mut self.sampler
+    }
+}
@@ -161,7 +161,7 @@ pub trait ScalarBase: 'static + Copy {
 /// Scalar that OpenGL can read.
 ///
 /// Implemented for `u8`, `u16`, `u32`, `i8`, `i16`, `i32`, `f32`, `bool`, and [`GLSLInt`]-wrapped
-/// integers.
+/// integers. `i64` and `u64` are also implemented, but can only be used as uniforms.
 ///
 /// [`GLSLInt`]: ./struct.GLSLInt.html
 pub unsafe trait Scalar<N: Normalization>: ScalarBase {
@@ -191,6 +191,14 @@ pub enum GLSLIntSigned {}
 ///
 /// Used in conjunction with [`Scalar::ScalarType`](./trait.Scalar.html#associatedtype.ScalarType)
 pub enum GLSLIntUnsigned {}
+/// Marker enum for types GLSL reads as a *64-bit signed int*. Requires `ARB_gpu_shader_int64`.
+///
+/// Used in conjunction with [`Scalar::ScalarType`](./trait.Scalar.html#associatedtype.ScalarType)
+pub enum GLSLInt64Signed {}
+/// Marker enum for types GLSL reads as a *64-bit unsigned int*. Requires `ARB_gpu_shader_int64`.
+///
+/// Used in conjunction with [`Scalar::ScalarType`](./trait.Scalar.html#associatedtype.ScalarType)
+pub enum GLSLInt64Unsigned {}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Normalized {}
@@ -235,6 +243,14 @@ unsafe impl ScalarType for GLSLIntUnsigned {
     const PRIM_TAG: TypeTagSingle = TypeTagSingle::UInt;
     const IS_INTEGER: bool = true;
 }
+unsafe impl ScalarType for GLSLInt64Signed {
+    const PRIM_TAG: TypeTagSingle = TypeTagSingle::Int64;
+    const IS_INTEGER: bool = true;
+}
+unsafe impl ScalarType for GLSLInt64Unsigned {
+    const PRIM_TAG: TypeTagSingle = TypeTagSingle::UInt64;
+    const IS_INTEGER: bool = true;
+}
 
 /// A scalar that is also a number.
 pub unsafe trait ScalarNum<N: Normalization>: Scalar<N> + Num {}
@@ -267,6 +283,14 @@ pub enum TypeTagSingle {
     UVec2 = gl::UNSIGNED_INT_VEC2,
     UVec3 = gl::UNSIGNED_INT_VEC3,
     UVec4 = gl::UNSIGNED_INT_VEC4,
+    Int64 = gl::INT64_ARB,
+    I64Vec2 = gl::INT64_VEC2_ARB,
+    I64Vec3 = gl::INT64_VEC3_ARB,
+    I64Vec4 = gl::INT64_VEC4_ARB,
+    UInt64 = gl::UNSIGNED_INT64_ARB,
+    U64Vec2 = gl::UNSIGNED_INT64_VEC2_ARB,
+    U64Vec3 = gl::UNSIGNED_INT64_VEC3_ARB,
+    U64Vec4 = gl::UNSIGNED_INT64_VEC4_ARB,
     Bool = gl::BOOL,
     BVec2 = gl::BOOL_VEC2,
     BVec3 = gl::BOOL_VEC3,
@@ -293,18 +317,18 @@ pub enum TypeTagSingle {
     Sampler2D = gl::SAMPLER_2D,
     Sampler3D = gl::SAMPLER_3D,
     SamplerCube = gl::SAMPLER_CUBE,
-    // Sampler1DShadow = gl::SAMPLER_1D_SHADOW,
-    // Sampler2DShadow = gl::SAMPLER_2D_SHADOW,
+    Sampler1DShadow = gl::SAMPLER_1D_SHADOW,
+    Sampler2DShadow = gl::SAMPLER_2D_SHADOW,
     Sampler1DArray = gl::SAMPLER_1D_ARRAY,
     Sampler2DArray = gl::SAMPLER_2D_ARRAY,
-    // Sampler1DArrayShadow = gl::SAMPLER_1D_ARRAY_SHADOW,
-    // Sampler2DArrayShadow = gl::SAMPLER_2D_ARRAY_SHADOW,
+    Sampler1DArrayShadow = gl::SAMPLER_1D_ARRAY_SHADOW,
+    Sampler2DArrayShadow = gl::SAMPLER_2D_ARRAY_SHADOW,
     Sampler2DMS = gl::SAMPLER_2D_MULTISAMPLE,
     Sampler2DMSArray = gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
-    // SamplerCubeShadow = gl::SAMPLER_CUBE_SHADOW,
+    SamplerCubeShadow = gl::SAMPLER_CUBE_SHADOW,
     // SamplerBuffer = gl::SAMPLER_BUFFER,
     Sampler2DRect = gl::SAMPLER_2D_RECT,
-    // Sampler2DRectShadow = gl::SAMPLER_2D_RECT_SHADOW,
+    Sampler2DRectShadow = gl::SAMPLER_2D_RECT_SHADOW,
     ISampler1D = gl::INT_SAMPLER_1D,
     ISampler2D = gl::INT_SAMPLER_2D,
     ISampler3D = gl::INT_SAMPLER_3D,
@@ -581,6 +605,37 @@ matrix_struct! {
     struct ColumnMatrix4 -> GLMat4r4c(x, y, z, w): GLVec4, (4, 4);
 }
 
+impl GLMat3r3c<f32> {
+    /// Compute the normal matrix for a model matrix: the inverse transpose of its upper-left 3x3
+    /// section.
+    ///
+    /// Transforming normals by the model matrix skews them whenever the model is scaled
+    /// non-uniformly, which the normal matrix corrects for. Upload the result to a GLSL `mat3`
+    /// uniform. Returns `None` if the model matrix's 3x3 section isn't invertible.
+    pub fn normal_matrix(model: GLMat4r4c<f32>) -> Option<GLMat3r3c<f32>> {
+        let column = |c: GLVec4<f32>| GLVec3::new(c.x, c.y, c.z);
+        let cross = |a: GLVec3<f32>, b: GLVec3<f32>| {
+            GLVec3::new(
+                a.y * b.z - a.z * b.y,
+                a.z * b.x - a.x * b.z,
+                a.x * b.y - a.y * b.x,
+            )
+        };
+        let (a, b, c) = (column(model.x), column(model.y), column(model.z));
+
+        // The inverse transpose is the cofactor matrix divided by the determinant, and the
+        // cofactor matrix's columns are the cross products of the other two columns.
+        let (bc, ca, ab) = (cross(b, c), cross(c, a), cross(a, b));
+        let det = a.x * bc.x + a.y * bc.y + a.z * bc.z;
+        if det == 0.0 {
+            return None;
+        }
+
+        let scale = |v: GLVec3<f32>| GLVec3::new(v.x / det, v.y / det, v.z / det);
+        Some(GLMat3r3c::from_columns(scale(bc), scale(ca), scale(ab)))
+    }
+}
+
 // I'm not implementing arrays right now because that's kinda complicated and I'm not convinced
 // it's worth the effort rn.
 // macro_rules! impl_glsl_array {
@@ -657,6 +712,21 @@ impl_gl_scalar_int! {
     impl i8 = GLSLIntSigned;
     impl i16 = GLSLIntSigned;
     impl i32 = GLSLIntSigned;
+    impl i64 = GLSLInt64Signed;
+    impl u64 = GLSLInt64Unsigned;
+}
+
+// 64-bit integers only exist as GLSL types, so unlike the other integers they don't get
+// normalized `Scalar` impls for use in images and vertex attributes.
+impl ScalarBase for i64 {
+    type ImageNormalization = NonNormalized;
+    const GL_ENUM: GLenum = gl::INT64_ARB;
+    const SIGNED: bool = true;
+}
+impl ScalarBase for u64 {
+    type ImageNormalization = NonNormalized;
+    const GL_ENUM: GLenum = gl::UNSIGNED_INT64_ARB;
+    const SIGNED: bool = false;
 }
 
 unsafe impl Scalar<NonNormalized> for bool {
@@ -704,6 +774,14 @@ impl Display for TypeTagSingle {
             UVec2 => "uvec2",
             UVec3 => "uvec3",
             UVec4 => "uvec4",
+            Int64 => "int64_t",
+            I64Vec2 => "i64vec2",
+            I64Vec3 => "i64vec3",
+            I64Vec4 => "i64vec4",
+            UInt64 => "uint64_t",
+            U64Vec2 => "u64vec2",
+            U64Vec3 => "u64vec3",
+            U64Vec4 => "u64vec4",
             Bool => "bool",
             BVec2 => "bvec2",
             BVec3 => "bvec3",
@@ -730,18 +808,18 @@ impl Display for TypeTagSingle {
             Sampler2D => "sampler2D",
             Sampler3D => "sampler3D",
             SamplerCube => "samplerCube",
-            // Sampler1DShadow => "sampler1DShadow",
-            // Sampler2DShadow => "sampler2DShadow",
+            Sampler1DShadow => "sampler1DShadow",
+            Sampler2DShadow => "sampler2DShadow",
             Sampler1DArray => "sampler1DArray",
             Sampler2DArray => "sampler2DArray",
-            // Sampler1DArrayShadow => "sampler1DArrayShadow",
-            // Sampler2DArrayShadow => "sampler2DArrayShadow",
+            Sampler1DArrayShadow => "sampler1DArrayShadow",
+            Sampler2DArrayShadow => "sampler2DArrayShadow",
             Sampler2DMS => "sampler2DMS",
             Sampler2DMSArray => "sampler2DMSArray",
-            // SamplerCubeShadow => "samplerCubeShadow",
+            SamplerCubeShadow => "samplerCubeShadow",
             // SamplerBuffer => "samplerBuffer",
             Sampler2DRect => "sampler2DRect",
-            // Sampler2DRectShadow => "sampler2DRectShadow",
+            Sampler2DRectShadow => "sampler2DRectShadow",
             ISampler1D => "isampler1D",
             ISampler2D => "isampler2D",
             ISampler3D => "isampler3D",
@@ -777,24 +855,32 @@ impl TypeTagSingle {
             Int   |
             Float |
             UInt  |
+            Int64 |
+            UInt64 |
             Bool => 1,
 
             // Dvec2 |
             Vec2  |
             IVec2 |
             UVec2 |
+            I64Vec2 |
+            U64Vec2 |
             BVec2 => 2,
 
             // Dvec3 |
             Vec3  |
             IVec3 |
             UVec3 |
+            I64Vec3 |
+            U64Vec3 |
             BVec3 => 3,
 
             // Dvec4 |
             Vec4  |
             IVec4 |
             UVec4 |
+            I64Vec4 |
+            U64Vec4 |
             BVec4 => 4,
 
             // DMat2 |
@@ -819,18 +905,18 @@ impl TypeTagSingle {
             Sampler2D |
             Sampler3D |
             SamplerCube |
-            // Sampler1DShadow |
-            // Sampler2DShadow |
+            Sampler1DShadow |
+            Sampler2DShadow |
             Sampler1DArray |
             Sampler2DArray |
-            // Sampler1DArrayShadow |
-            // Sampler2DArrayShadow |
+            Sampler1DArrayShadow |
+            Sampler2DArrayShadow |
             Sampler2DMS |
             Sampler2DMSArray |
-            // SamplerCubeShadow |
+            SamplerCubeShadow |
             // SamplerBuffer |
             Sampler2DRect |
-            // Sampler2DRectShadow |
+            Sampler2DRectShadow |
             ISampler1D |
             ISampler2D |
             ISampler3D |
@@ -881,18 +967,18 @@ impl TypeTagSingle {
             Sampler2D |
             Sampler3D |
             SamplerCube |
-            // Sampler1DShadow |
-            // Sampler2DShadow |
+            Sampler1DShadow |
+            Sampler2DShadow |
             Sampler1DArray |
             Sampler2DArray |
-            // Sampler1DArrayShadow |
-            // Sampler2DArrayShadow |
+            Sampler1DArrayShadow |
+            Sampler2DArrayShadow |
             Sampler2DMS |
             Sampler2DMSArray |
-            // SamplerCubeShadow |
+            SamplerCubeShadow |
             // SamplerBuffer |
             Sampler2DRect |
-            // Sampler2DRectShadow |
+            Sampler2DRectShadow |
             ISampler1D |
             ISampler2D |
             ISampler3D |
@@ -932,7 +1018,15 @@ impl TypeTagSingle {
             Vec4   |
             IVec4  |
             UVec4  |
-            BVec4 => 1,
+            BVec4  |
+            Int64  |
+            I64Vec2 |
+            I64Vec3 |
+            I64Vec4 |
+            UInt64 |
+            U64Vec2 |
+            U64Vec3 |
+            U64Vec4 => 1,
         }
     }
 
@@ -957,6 +1051,16 @@ impl TypeTagSingle {
             (UInt, 3) => Some(UVec3),
             (UInt, 4) => Some(UVec4),
 
+            (Int64, 1) => Some(Int64),
+            (Int64, 2) => Some(I64Vec2),
+            (Int64, 3) => Some(I64Vec3),
+            (Int64, 4) => Some(I64Vec4),
+
+            (UInt64, 1) => Some(UInt64),
+            (UInt64, 2) => Some(U64Vec2),
+            (UInt64, 3) => Some(U64Vec3),
+            (UInt64, 4) => Some(U64Vec4),
+
             (Bool, 1) => Some(Bool),
             (Bool, 2) => Some(BVec2),
             (Bool, 3) => Some(BVec3),
@@ -1018,6 +1122,14 @@ impl TypeTagSingle {
             gl::UNSIGNED_INT_VEC2 => Some(UVec2),
             gl::UNSIGNED_INT_VEC3 => Some(UVec3),
             gl::UNSIGNED_INT_VEC4 => Some(UVec4),
+            gl::INT64_ARB => Some(Int64),
+            gl::INT64_VEC2_ARB => Some(I64Vec2),
+            gl::INT64_VEC3_ARB => Some(I64Vec3),
+            gl::INT64_VEC4_ARB => Some(I64Vec4),
+            gl::UNSIGNED_INT64_ARB => Some(UInt64),
+            gl::UNSIGNED_INT64_VEC2_ARB => Some(U64Vec2),
+            gl::UNSIGNED_INT64_VEC3_ARB => Some(U64Vec3),
+            gl::UNSIGNED_INT64_VEC4_ARB => Some(U64Vec4),
             gl::BOOL => Some(Bool),
             gl::BOOL_VEC2 => Some(BVec2),
             gl::BOOL_VEC3 => Some(BVec3),
@@ -1044,18 +1156,18 @@ impl TypeTagSingle {
             gl::SAMPLER_2D => Some(Sampler2D),
             gl::SAMPLER_3D => Some(Sampler3D),
             gl::SAMPLER_CUBE => Some(SamplerCube),
-            // gl::SAMPLER_1D_SHADOW => Some(Sampler1DShadow),
-            // gl::SAMPLER_2D_SHADOW => Some(Sampler2DShadow),
+            gl::SAMPLER_1D_SHADOW => Some(Sampler1DShadow),
+            gl::SAMPLER_2D_SHADOW => Some(Sampler2DShadow),
             gl::SAMPLER_1D_ARRAY => Some(Sampler1DArray),
             gl::SAMPLER_2D_ARRAY => Some(Sampler2DArray),
-            // gl::SAMPLER_1D_ARRAY_SHADOW => Some(Sampler1DArrayShadow),
-            // gl::SAMPLER_2D_ARRAY_SHADOW => Some(Sampler2DArrayShadow),
+            gl::SAMPLER_1D_ARRAY_SHADOW => Some(Sampler1DArrayShadow),
+            gl::SAMPLER_2D_ARRAY_SHADOW => Some(Sampler2DArrayShadow),
             gl::SAMPLER_2D_MULTISAMPLE => Some(Sampler2DMS),
             gl::SAMPLER_2D_MULTISAMPLE_ARRAY => Some(Sampler2DMSArray),
-            // gl::SAMPLER_CUBE_SHADOW => Some(SamplerCubeShadow),
+            gl::SAMPLER_CUBE_SHADOW => Some(SamplerCubeShadow),
             // gl::SAMPLER_BUFFER => Some(SamplerBuffer),
             gl::SAMPLER_2D_RECT => Some(Sampler2DRect),
-            // gl::SAMPLER_2D_RECT_SHADOW => Some(Sampler2DRectShadow),
+            gl::SAMPLER_2D_RECT_SHADOW => Some(Sampler2DRectShadow),
             gl::INT_SAMPLER_1D => Some(ISampler1D),
             gl::INT_SAMPLER_2D => Some(ISampler2D),
             gl::INT_SAMPLER_3D => Some(ISampler3D),
@@ -1101,3 +1213,46 @@ impl<S: Copy + Add<Output = S> + Sub<Output = S> + Scalar<NonNormalized>> Dimens
 impl<S: Copy + Add<Output = S> + Sub<Output = S> + Scalar<NonNormalized>> Dimension<S> for D3 {
     type Vector = GLVec3<S, NonNormalized>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_matrix() {
+        let model = GLMat4r4c::from_columns(
+            GLVec4::new(2.0, 0.0, 0.0, 0.0),
+            GLVec4::new(0.0, 4.0, 0.0, 0.0),
+            GLVec4::new(0.0, 0.0, 1.0, 0.0),
+            GLVec4::new(5.0, 6.0, 7.0, 1.0),
+        );
+        assert_eq!(
+            Some(GLMat3r3c::from_columns(
+                GLVec3::new(0.5, 0.0, 0.0),
+                GLVec3::new(0.0, 0.25, 0.0),
+                GLVec3::new(0.0, 0.0, 1.0),
+            )),
+            GLMat3r3c::normal_matrix(model)
+        );
+
+        let flat = GLMat4r4c::from_columns(
+            GLVec4::new(1.0, 0.0, 0.0, 0.0),
+            GLVec4::new(0.0, 1.0, 0.0, 0.0),
+            GLVec4::new(0.0, 0.0, 0.0, 0.0),
+            GLVec4::new(0.0, 0.0, 0.0, 1.0),
+        );
+        assert_eq!(None, GLMat3r3c::normal_matrix(flat));
+    }
+
+    #[test]
+    fn int64_type_tags() {
+        assert_eq!(TypeTagSingle::Int64, i64::prim_tag());
+        assert_eq!(TypeTagSingle::U64Vec3, GLVec3::<u64>::prim_tag());
+        assert_eq!(3, TypeTagSingle::U64Vec3.len());
+        assert_eq!(
+            Some(TypeTagSingle::I64Vec4),
+            TypeTagSingle::from_gl_enum(GLenum::from(TypeTagSingle::I64Vec4))
+        );
+        assert_eq!("i64vec2", TypeTagSingle::I64Vec2.to_string());
+    }
+}
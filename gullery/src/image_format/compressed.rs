@@ -58,7 +58,7 @@ pub type BC5<S> = RGTC<Rg<S>>;
 ///
 /// [rgtc-spec]: https://www.khronos.org/registry/DataFormat/specs/1.1/dataformat.1.1.html#RGTC
 #[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RGTC<S: ColorComponents> {
     pub block: [S; 8],
 }
@@ -74,7 +74,7 @@ pub struct RGTC<S: ColorComponents> {
 /// [RGB]: https://www.khronos.org/registry/DataFormat/specs/1.1/dataformat.1.1.html#_bc1_with_no_alpha
 /// [RGBA]: https://www.khronos.org/registry/DataFormat/specs/1.1/dataformat.1.1.html#_bc1_with_alpha
 #[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct DXT1<S: ColorComponents> {
     pub block: [S::Scalar; 8],
 }
@@ -86,7 +86,7 @@ pub struct DXT1<S: ColorComponents> {
 ///
 /// [bc2-spec]: https://www.khronos.org/registry/DataFormat/specs/1.1/dataformat.1.1.html#_bc2
 #[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct DXT3<S: ColorComponents> {
     pub block: [S::Scalar; 16],
 }
@@ -98,7 +98,7 @@ pub struct DXT3<S: ColorComponents> {
 ///
 /// [bc3-spec]: https://www.khronos.org/registry/DataFormat/specs/1.1/dataformat.1.1.html#_bc3
 #[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct DXT5<S: ColorComponents> {
     pub block: [S::Scalar; 16],
 }
@@ -43,7 +43,7 @@ pub enum FormatTypeTag {
     Color,
     Depth,
     // Stencil,
-    // DepthStencil
+    DepthStencil,
 }
 
 /// Attributes used by OpenGL to process and display images.
@@ -80,6 +80,82 @@ pub enum FormatAttributes {
     },
 }
 
+/// `GL_RGB565`. Core since OpenGL 4.1, but accepted as an internal format by most 3.3 drivers.
+const RGB565: GLenum = 0x8D62;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InternalFormatClass {
+    Color,
+    Integer,
+    Depth,
+    DepthStencil,
+}
+
+impl InternalFormatClass {
+    fn of_internal_format(internal_format: GLenum) -> Option<InternalFormatClass> {
+        use self::InternalFormatClass::*;
+        match internal_format {
+            gl::RED | gl::RG | gl::RGB | gl::RGBA | gl::R8 | gl::R8_SNORM | gl::R16
+            | gl::R16_SNORM | gl::RG8 | gl::RG8_SNORM | gl::RG16 | gl::RG16_SNORM
+            | gl::R3_G3_B2 | gl::RGB4 | gl::RGB5 | RGB565 | gl::RGB8 | gl::RGB8_SNORM
+            | gl::RGB10 | gl::RGB12 | gl::RGB16 | gl::RGB16_SNORM | gl::RGBA2 | gl::RGBA4
+            | gl::RGB5_A1 | gl::RGBA8 | gl::RGBA8_SNORM | gl::RGB10_A2 | gl::RGBA12
+            | gl::RGBA16 | gl::RGBA16_SNORM | gl::SRGB | gl::SRGB8 | gl::SRGB_ALPHA
+            | gl::SRGB8_ALPHA8 | gl::R16F | gl::RG16F | gl::RGB16F | gl::RGBA16F | gl::R32F
+            | gl::RG32F | gl::RGB32F | gl::RGBA32F | gl::R11F_G11F_B10F | gl::RGB9_E5
+            | gl::COMPRESSED_RED | gl::COMPRESSED_RG | gl::COMPRESSED_RGB
+            | gl::COMPRESSED_RGBA | gl::COMPRESSED_SRGB | gl::COMPRESSED_SRGB_ALPHA
+            | gl::COMPRESSED_RED_RGTC1 | gl::COMPRESSED_SIGNED_RED_RGTC1
+            | gl::COMPRESSED_RG_RGTC2 | gl::COMPRESSED_SIGNED_RG_RGTC2
+            | gl::COMPRESSED_RGB_S3TC_DXT1_EXT | gl::COMPRESSED_RGBA_S3TC_DXT1_EXT
+            | gl::COMPRESSED_RGBA_S3TC_DXT3_EXT | gl::COMPRESSED_RGBA_S3TC_DXT5_EXT => Some(Color),
+            gl::R8I | gl::R8UI | gl::R16I | gl::R16UI | gl::R32I | gl::R32UI | gl::RG8I
+            | gl::RG8UI | gl::RG16I | gl::RG16UI | gl::RG32I | gl::RG32UI | gl::RGB8I
+            | gl::RGB8UI | gl::RGB16I | gl::RGB16UI | gl::RGB32I | gl::RGB32UI | gl::RGBA8I
+            | gl::RGBA8UI | gl::RGBA16I | gl::RGBA16UI | gl::RGBA32I | gl::RGBA32UI
+            | gl::RGB10_A2UI => Some(Integer),
+            gl::DEPTH_COMPONENT | gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24
+            | gl::DEPTH_COMPONENT32 | gl::DEPTH_COMPONENT32F => Some(Depth),
+            gl::DEPTH_STENCIL | gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8 => Some(DepthStencil),
+            _ => None,
+        }
+    }
+
+    fn of_pixel_format(pixel_format: GLenum) -> Option<InternalFormatClass> {
+        use self::InternalFormatClass::*;
+        match pixel_format {
+            gl::RED | gl::RG | gl::RGB | gl::BGR | gl::RGBA | gl::BGRA => Some(Color),
+            gl::RED_INTEGER | gl::RG_INTEGER | gl::RGB_INTEGER | gl::BGR_INTEGER
+            | gl::RGBA_INTEGER | gl::BGRA_INTEGER => Some(Integer),
+            gl::DEPTH_COMPONENT => Some(Depth),
+            gl::DEPTH_STENCIL => Some(DepthStencil),
+            _ => None,
+        }
+    }
+}
+
+impl FormatAttributes {
+    /// Whether OpenGL can convert pixel data in this format to the given internal format when
+    /// uploading it.
+    ///
+    /// Uncompressed color data can be stored in any color internal format, and likewise for
+    /// integer and depth data. Crossing those boundaries is an error in OpenGL, as is overriding
+    /// the internal format of a compressed format.
+    pub fn accepts_internal_format(self, internal_format: GLenum) -> bool {
+        match self {
+            FormatAttributes::Uncompressed { pixel_format, .. } => {
+                let pixel_class = InternalFormatClass::of_pixel_format(pixel_format);
+                let internal_class = InternalFormatClass::of_internal_format(internal_format);
+                pixel_class.is_some() && pixel_class == internal_class
+            }
+            FormatAttributes::Compressed {
+                internal_format: format_internal,
+                ..
+            } => format_internal == internal_format,
+        }
+    }
+}
+
 /// An image format the GPU can use to look up pixel data.
 pub unsafe trait ImageFormat: 'static {
     type ScalarType: ScalarType;
@@ -121,12 +197,17 @@ pub trait FormatType {
 pub enum ColorFormat {}
 /// Marker type that indicates a depth image format.
 pub enum DepthFormat {}
+/// Marker type that indicates a packed depth-stencil image format.
+pub enum DepthStencilFormat {}
 impl FormatType for ColorFormat {
     const FORMAT_TYPE: FormatTypeTag = FormatTypeTag::Color;
 }
 impl FormatType for DepthFormat {
     const FORMAT_TYPE: FormatTypeTag = FormatTypeTag::Depth;
 }
+impl FormatType for DepthStencilFormat {
+    const FORMAT_TYPE: FormatTypeTag = FormatTypeTag::DepthStencil;
+}
 
 pub trait ColorComponents {
     type Normalization: Normalization;
@@ -135,18 +216,27 @@ pub trait ColorComponents {
 
 /// 16-bit unsigned depth format.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Depth16(pub u16);
-// #[repr(C)]
-// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-// pub struct Depth24(pub u32);
+/// 24-bit unsigned depth format.
+///
+/// The most widely supported depth format, and cheaper to store than `Depth32F`. Pixel data is
+/// transferred as a normalized `u32`, where `0` is the near plane and `u32::MAX` is the far plane;
+/// OpenGL only keeps the 24 most significant bits.
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Depth24(pub u32);
 /// 32-bit floating-point depth format.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct Depth32F(pub f32);
-// #[repr(C)]
-// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-// pub struct Depth24Stencil8(pub u32);
+/// Packed 24-bit depth and 8-bit stencil format.
+///
+/// The depth value is stored in the 24 most significant bits, and the stencil value in the 8 least
+/// significant bits. Sampling the texture in a shader returns the depth value.
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Depth24Stencil8(pub u32);
 
 unsafe impl ImageFormat for Depth16 {
     type ScalarType = GLSLFloat;
@@ -162,6 +252,20 @@ unsafe impl ConcreteImageFormat for Depth16 {
     };
 }
 
+unsafe impl ImageFormat for Depth24 {
+    type ScalarType = GLSLFloat;
+}
+unsafe impl ImageFormatRenderable for Depth24 {
+    type FormatType = DepthFormat;
+}
+unsafe impl ConcreteImageFormat for Depth24 {
+    const FORMAT: FormatAttributes = FormatAttributes::Uncompressed {
+        internal_format: gl::DEPTH_COMPONENT24,
+        pixel_format: gl::DEPTH_COMPONENT,
+        pixel_type: <u32 as ScalarBase>::GL_ENUM,
+    };
+}
+
 unsafe impl ImageFormat for Depth32F {
     type ScalarType = GLSLFloat;
 }
@@ -176,6 +280,20 @@ unsafe impl ConcreteImageFormat for Depth32F {
     };
 }
 
+unsafe impl ImageFormat for Depth24Stencil8 {
+    type ScalarType = GLSLFloat;
+}
+unsafe impl ImageFormatRenderable for Depth24Stencil8 {
+    type FormatType = DepthStencilFormat;
+}
+unsafe impl ConcreteImageFormat for Depth24Stencil8 {
+    const FORMAT: FormatAttributes = FormatAttributes::Uncompressed {
+        internal_format: gl::DEPTH24_STENCIL8,
+        pixel_format: gl::DEPTH_STENCIL,
+        pixel_type: gl::UNSIGNED_INT_24_8,
+    };
+}
+
 /// Linear four-channel RGBA color format.
 ///
 /// If you want GLSL to take normalized integer or floating point data, `S` can be `u8`,
@@ -193,6 +311,11 @@ pub struct Rgba<S: Scalar<N> = u8, N: Normalization = <S as ScalarBase>::ImageNo
     pub _normalization: PhantomData<N>,
 }
 
+/// Linear four-channel color format, with the channels stored in BGRA order.
+///
+/// Gets read by GLSL the same way as [`Rgba`]. Only `u8` channels are supported.
+///
+/// [`Rgba`]: ./struct.Rgba.html
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Bgra<S: Scalar<N> = u8, N: Normalization = <S as ScalarBase>::ImageNormalization> {
@@ -203,6 +326,22 @@ pub struct Bgra<S: Scalar<N> = u8, N: Normalization = <S as ScalarBase>::ImageNo
     pub _normalization: PhantomData<N>,
 }
 
+/// Linear three-channel color format, with the channels stored in BGR order.
+///
+/// Gets read by GLSL the same way as [`Rgb`]. This matches the layout of 24-bit Windows bitmaps,
+/// so their data can be uploaded without swapping channels on the CPU. Only `u8` channels are
+/// supported.
+///
+/// [`Rgb`]: ./struct.Rgb.html
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bgr<S: Scalar<N> = u8, N: Normalization = <S as ScalarBase>::ImageNormalization> {
+    pub b: S,
+    pub g: S,
+    pub r: S,
+    pub _normalization: PhantomData<N>,
+}
+
 /// Linear three-channel RGB color format.
 ///
 /// If you want GLSL to take normalized integer or floating point data, `S` can be `u8`,
@@ -253,7 +392,7 @@ pub struct Red<S: Scalar<N> = u8, N: Normalization = <S as ScalarBase>::ImageNor
 /// Unlike linear RGBA data, this applies a gamma correction curve to the color data upon access. See
 /// [here](https://en.wikipedia.org/wiki/SRGB) for more details.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SRgba {
     pub r: u8,
     pub g: u8,
@@ -266,7 +405,7 @@ pub struct SRgba {
 /// Unlike linear RGB data, this applies a gamma correction curve to the color data upon access. See
 /// [here](https://en.wikipedia.org/wiki/SRGB) for more details.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SRgb {
     pub r: u8,
     pub g: u8,
@@ -278,6 +417,13 @@ macro_rules! impl_color {
         impl<S: Scalar<N>, N: Normalization> $name<S, N> {
             impl_color!{impl body $name<S>($len, color: $($channel),+)}
         }
+
+        impl<S: Scalar<N> + Default, N: Normalization> Default for $name<S, N> {
+            #[inline]
+            fn default() -> Self {
+                $name{ $($channel: S::default(),)* _normalization: PhantomData, }
+            }
+        }
     )*};
     (impl body $name:ident<$ty:ty>($len:expr, color: $($channel:ident),+)) => {
         #[inline]
@@ -295,6 +441,7 @@ impl_color! {
     impl Rg<S>(2, color: r, g);
     impl Red<S>(1, color: r);
     impl Bgra<S>(4, color: r, g, b, a);
+    impl Bgr<S>(3, color: r, g, b);
 }
 
 impl SRgba {
@@ -334,6 +481,87 @@ impl<S: ScalarNum<N>, N: Normalization> From<Red<S, N>> for Rgba<S, N> {
     }
 }
 
+macro_rules! impl_array_conversions {
+    ($(impl $name:ident<S>($len:expr, ($($tuple_ty:ident),+), color: $($channel:ident),+);)*) => {$(
+        impl<S: Scalar<N>, N: Normalization> $name<S, N> {
+            /// Create a color from an array of its channels, in the order they're stored.
+            #[inline]
+            pub fn from_array([$($channel),+]: [S; $len]) -> Self {
+                $name::new($($channel),+)
+            }
+
+            /// Get the color's channels as an array, in the order they're stored.
+            #[inline]
+            pub fn to_array(self) -> [S; $len] {
+                [$(self.$channel),+]
+            }
+
+            /// Reinterpret a slice of channel arrays as a slice of colors, without copying.
+            ///
+            /// This lets pixel data loaded as, say, a `Vec<[u8; 4]>` be uploaded directly.
+            #[inline]
+            pub fn from_array_slice(arrays: &[[S; $len]]) -> &[Self] {
+                Self::from_raw_slice(unsafe {
+                    ::std::slice::from_raw_parts(arrays.as_ptr() as *const S, arrays.len() * $len)
+                })
+            }
+
+            /// Reinterpret a slice of colors as a slice of channel arrays, without copying.
+            #[inline]
+            pub fn to_array_slice(colors: &[Self]) -> &[[S; $len]] {
+                let raw = Self::to_raw_slice(colors);
+                unsafe {
+                    ::std::slice::from_raw_parts(raw.as_ptr() as *const [S; $len], colors.len())
+                }
+            }
+        }
+
+        impl<S: Scalar<N>, N: Normalization> From<[S; $len]> for $name<S, N> {
+            #[inline]
+            fn from(array: [S; $len]) -> $name<S, N> {
+                $name::from_array(array)
+            }
+        }
+        impl<S: Scalar<N>, N: Normalization> From<($($tuple_ty,)+)> for $name<S, N> {
+            #[inline]
+            fn from(($($channel,)+): ($($tuple_ty,)+)) -> $name<S, N> {
+                $name::new($($channel),+)
+            }
+        }
+    )*};
+}
+
+impl_array_conversions! {
+    impl Rgba<S>(4, (S, S, S, S), color: r, g, b, a);
+    impl Rgb<S>(3, (S, S, S), color: r, g, b);
+    impl Rg<S>(2, (S, S), color: r, g);
+    impl Red<S>(1, (S), color: r);
+}
+
+impl Rgba<u8> {
+    /// Create a color from a hex literal in `0xRRGGBBAA` form.
+    ///
+    /// Hex colors from design tools are almost always sRGB-encoded. If that's the case, use
+    /// [`SRgba::from_hex`] instead, or the color will look washed out when rendered into an sRGB
+    /// framebuffer.
+    ///
+    /// [`SRgba::from_hex`]: ./struct.SRgba.html#method.from_hex
+    #[inline]
+    pub fn from_hex(hex: u32) -> Self {
+        let [r, g, b, a] = hex.to_be_bytes();
+        Rgba::new(r, g, b, a)
+    }
+}
+
+impl SRgba {
+    /// Create a color from a hex literal in `0xRRGGBBAA` form.
+    #[inline]
+    pub fn from_hex(hex: u32) -> Self {
+        let [r, g, b, a] = hex.to_be_bytes();
+        SRgba::new(r, g, b, a)
+    }
+}
+
 unsafe impl<S: ScalarNum<N>, N: Normalization> TransparentType for Rgba<S, N> {
     type Normalization = N;
     type Scalar = S;
@@ -374,6 +602,14 @@ unsafe impl<S: ScalarNum<N>, N: Normalization> TransparentType for Bgra<S, N> {
         <S as Scalar<N>>::ScalarType::PRIM_TAG.vectorize(4).unwrap()
     }
 }
+unsafe impl<S: ScalarNum<N>, N: Normalization> TransparentType for Bgr<S, N> {
+    type Normalization = N;
+    type Scalar = S;
+    #[inline]
+    fn prim_tag() -> TypeTagSingle {
+        <S as Scalar<N>>::ScalarType::PRIM_TAG.vectorize(3).unwrap()
+    }
+}
 impl<S: ScalarNum<N>, N: Normalization> Into<GLVec4<S, N>> for Rgba<S, N> {
     #[inline]
     fn into(self: Rgba<S, N>) -> GLVec4<S, N> {
@@ -404,6 +640,12 @@ impl<S: ScalarNum<N>, N: Normalization> Into<GLVec4<S, N>> for Bgra<S, N> {
         GLVec4::new(self.r, self.g, self.b, self.a)
     }
 }
+impl<S: ScalarNum<N>, N: Normalization> Into<GLVec3<S, N>> for Bgr<S, N> {
+    #[inline]
+    fn into(self: Bgr<S, N>) -> GLVec3<S, N> {
+        GLVec3::new(self.r, self.g, self.b)
+    }
+}
 
 macro_rules! if_integer {
     (if $prim:ty, $normalized:ty => ($t:expr) else ($f:expr)) => {{
@@ -556,3 +798,42 @@ unsafe impl ConcreteImageFormat for Bgra<u8, Normalized> {
         pixel_type: <u8 as ScalarBase>::GL_ENUM,
     };
 }
+
+impl ColorComponents for Bgr<u8, Normalized> {
+    type Normalization = Normalized;
+    type Scalar = u8;
+}
+unsafe impl ImageFormat for Bgr<u8, Normalized> {
+    type ScalarType = GLSLFloat;
+}
+unsafe impl ImageFormatRenderable for Bgr<u8, Normalized> {
+    type FormatType = ColorFormat;
+}
+unsafe impl ConcreteImageFormat for Bgr<u8, Normalized> {
+    const FORMAT: FormatAttributes = FormatAttributes::Uncompressed {
+        internal_format: gl::RGB8,
+        pixel_format: gl::BGR,
+        pixel_type: <u8 as ScalarBase>::GL_ENUM,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn array_and_hex_conversions() {
+        let color: Rgba<u8> = Rgba::new(0x12, 0x34, 0x56, 0x78);
+        assert_eq!(color, Rgba::from_hex(0x12345678));
+        assert_eq!(color, Rgba::from([0x12, 0x34, 0x56, 0x78]));
+        assert_eq!(color, Rgba::from((0x12, 0x34, 0x56, 0x78)));
+        assert_eq!([0x12, 0x34, 0x56, 0x78], color.to_array());
+        assert_eq!(SRgba::new(0x12, 0x34, 0x56, 0x78), SRgba::from_hex(0x12345678));
+
+        let arrays = vec![[1u8, 2, 3, 4], [5, 6, 7, 8]];
+        let colors = Rgba::<u8>::from_array_slice(&arrays);
+        assert_eq!(&[Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)], colors);
+        assert_eq!(&arrays[..], Rgba::to_array_slice(colors));
+        assert_eq!([0.5], Red::<f32>::new(0.5).to_array());
+    }
+}
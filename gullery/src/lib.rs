@@ -34,13 +34,23 @@ pub mod framebuffer;
 pub mod geometry;
 pub mod image_format;
 pub mod program;
+pub mod query;
 pub mod texture;
 pub mod uniform;
 pub mod vertex;
 
-use crate::gl::Gl;
+use crate::{
+    geometry::{GLVec2, NonNormalized},
+    gl::{types::*, Gl},
+};
 
-use std::{cell::Cell, ops::Bound, num::NonZeroU32, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CStr,
+    num::NonZeroU32,
+    ops::{Bound, RangeInclusive},
+    rc::Rc,
+};
 
 pub type Handle = NonZeroU32;
 pub trait GLObject {
@@ -50,6 +60,56 @@ pub trait GLObject {
     fn state(&self) -> &Rc<ContextState>;
 }
 
+/// Guard returned by [`ContextState::debug_group`], which ends the debug group when dropped.
+///
+/// [`ContextState::debug_group`]: ./struct.ContextState.html#method.debug_group
+#[must_use = "the debug group ends as soon as the guard is dropped"]
+pub struct DebugGroup<'a> {
+    state: &'a ContextState,
+}
+
+impl<'a> Drop for DebugGroup<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        self.state.pop_debug_group();
+    }
+}
+
+/// Debugging information about how Gullery drives OpenGL, including likely misuse that isn't an
+/// error. Reported in debug builds to the callback set with
+/// [`ContextState::set_diagnostic_callback`].
+///
+/// [`ContextState::set_diagnostic_callback`]: ./struct.ContextState.html#method.set_diagnostic_callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// A buffer created with [`BufferUsage::StaticDraw`] was written twice within a frame's time
+    /// (1/60th of a second), which usually means `DynamicDraw` or `StreamDraw` is a better fit.
+    /// Only reported once per buffer.
+    ///
+    /// [`BufferUsage::StaticDraw`]: ./buffer/enum.BufferUsage.html#variant.StaticDraw
+    StaticBufferRewritten { buffer: Handle },
+    /// A texture with non-power-of-two `(width, height, depth)` dimensions was given more than one
+    /// mip level. OpenGL 3.3 supports these, but each level's size gets rounded down rather than
+    /// halved exactly, so texels don't line up between levels. OpenGL ES 2 and some older
+    /// hardware treat such textures as incomplete.
+    NpotMipmaps {
+        texture: Handle,
+        dims: (u32, u32, u32),
+    },
+    /// A vertex array object pointed the attribute at `location` to the vertex or instance
+    /// member named `member`. Reported for every member when a buffer gets attached to the VAO.
+    ///
+    /// Compare against [`Program::attribute_location`] to find members whose names don't match
+    /// the shader's inputs.
+    ///
+    /// [`Program::attribute_location`]: ./program/struct.Program.html#method.attribute_location
+    AttributeLocation {
+        vao: Handle,
+        member: String,
+        location: u32,
+    },
+}
+
 impl<'a, O: GLObject> GLObject for &'a O {
     #[inline(always)]
     fn handle(&self) -> Handle {
@@ -79,13 +139,383 @@ pub struct ContextState {
     framebuffer_targets: framebuffer::FramebufferTargets,
     default_framebuffer_exists: Cell<bool>,
     render_state: Cell<framebuffer::render_state::RenderState>,
+    /// If `false`, dithering stays disabled regardless of `RenderState::dither`.
+    dither_allowed: Cell<bool>,
+    diagnostic_callback: RefCell<Option<Box<dyn Fn(&Diagnostic)>>>,
     image_units: texture::ImageUnits,
     renderbuffer_target: framebuffer::renderbuffer::RenderbufferTarget,
+    /// Shared between every `ContextState` whose contexts share object names.
+    share_group: Rc<()>,
+    /// Whether textures can be modified without binding them to an image unit.
+    direct_state_access: bool,
+    glsl_version: u32,
+    conservative_raster_capability: Option<GLenum>,
+    /// `None` if the context doesn't support anisotropic filtering.
+    max_anisotropy: Option<f32>,
+    compressed_texture_formats: Box<[GLenum]>,
+    /// Whether the context supports the S3TC (DXT) compressed formats.
+    s3tc: bool,
+    /// Whether the context supports the sRGB variants of the S3TC formats.
+    s3tc_srgb: bool,
+    /// Whether buffers can be created with immutable storage.
+    buffer_storage: bool,
+    /// Whether draw calls can specify a base instance.
+    base_instance: bool,
+    /// Whether shaders can use 64-bit integers.
+    shader_int64: bool,
+    /// Whether debug groups can be pushed and popped.
+    debug_groups: bool,
+    /// Whether viewports can have fractional bounds.
+    viewport_array: bool,
+    /// Whether fragment shaders can run once per sample.
+    sample_shading: bool,
+    /// Whether buffers can be filled without uploading the fill data from the CPU.
+    clear_buffer: bool,
+    /// Whether buffers can be bound as atomic counter buffers.
+    atomic_counters: bool,
+    /// Whether transform feedback can write interleaved varyings to several buffers.
+    transform_feedback3: bool,
     gl: Gl,
 }
 
 impl ContextState {
     pub unsafe fn new<F: Fn(&str) -> *const ()>(load_fn: F) -> Rc<ContextState> {
+        ContextState::with_share_group(load_fn, Rc::new(()))
+    }
+
+    /// Create the state for a context that shares object names with `existing`'s context.
+    ///
+    /// Buffers, textures, samplers, renderbuffers, and programs created with either state can be
+    /// used with the other. This is useful for applications with several windows that all render
+    /// the same resources.
+    ///
+    /// ## Safety
+    /// On top of the requirements for [`new`], the context behind `load_fn` must have been created
+    /// by the windowing library as a shared context of `existing`'s context. Additionally:
+    ///
+    /// * Both states must be used from the same thread.
+    /// * Each state caches which objects its context has bound, so the context an object was
+    ///   created with must be current whenever that object is modified or deleted.
+    /// * Vertex array objects and framebuffer objects are containers, which OpenGL never shares
+    ///   between contexts. They may only be used with the context they were created with.
+    ///
+    /// [`new`]: #method.new
+    pub unsafe fn new_shared<F: Fn(&str) -> *const ()>(
+        load_fn: F,
+        existing: &ContextState,
+    ) -> Rc<ContextState> {
+        ContextState::with_share_group(load_fn, existing.share_group.clone())
+    }
+
+    /// Whether objects created with this state can be used with `other`, either because they're
+    /// the same state or because one was created with [`new_shared`].
+    ///
+    /// [`new_shared`]: #method.new_shared
+    #[inline]
+    pub fn shares_objects_with(&self, other: &ContextState) -> bool {
+        Rc::ptr_eq(&self.share_group, &other.share_group)
+    }
+
+    /// The newest GLSL version the context supports, in the format used by `#version` directives
+    /// (e.g. `330` for GLSL 3.30).
+    ///
+    /// If the driver reports a version string Gullery can't parse, this is `330`, the lowest
+    /// version Gullery supports.
+    #[inline]
+    pub fn glsl_version(&self) -> u32 {
+        self.glsl_version
+    }
+
+    /// Whether the context supports [`RenderState::conservative_raster`].
+    ///
+    /// [`RenderState::conservative_raster`]: ./framebuffer/render_state/struct.RenderState.html#structfield.conservative_raster
+    #[inline]
+    pub fn supports_conservative_raster(&self) -> bool {
+        self.conservative_raster_capability.is_some()
+    }
+
+    /// The number of texture units shaders can use, which is the number of units a single set of
+    /// uniforms can bind textures to.
+    ///
+    /// With the default [`TextureEditStrategy::ReserveLastUnit`], this is one less than
+    /// `GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS`, since Gullery binds textures to the last unit when
+    /// modifying them.
+    ///
+    /// [`TextureEditStrategy::ReserveLastUnit`]: ./texture/enum.TextureEditStrategy.html#variant.ReserveLastUnit
+    #[inline]
+    pub fn usable_image_units(&self) -> u32 {
+        self.image_units.usable_unit_count()
+    }
+
+    #[inline]
+    pub fn texture_edit_strategy(&self) -> texture::TextureEditStrategy {
+        self.image_units.edit_strategy()
+    }
+
+    /// Choose how textures get bound when modified, which determines whether shaders can use the
+    /// last texture unit. See [`TextureEditStrategy`] for details.
+    ///
+    /// ## Panics
+    /// Panics if `edit_strategy` is `DirectStateAccess` and the context doesn't support direct
+    /// state access.
+    ///
+    /// [`TextureEditStrategy`]: ./texture/enum.TextureEditStrategy.html
+    pub fn set_texture_edit_strategy(&self, edit_strategy: texture::TextureEditStrategy) {
+        if edit_strategy == texture::TextureEditStrategy::DirectStateAccess {
+            assert!(
+                self.direct_state_access,
+                "context doesn't support direct state access"
+            );
+        }
+        self.image_units.set_edit_strategy(edit_strategy);
+    }
+
+    /// Whether the context can issue draw calls with a base instance, through
+    /// [`Framebuffer::draw_instanced_base_instance`]. Requires OpenGL 4.2 or the
+    /// `ARB_base_instance` extension.
+    ///
+    /// [`Framebuffer::draw_instanced_base_instance`]: ./framebuffer/trait.Framebuffer.html#method.draw_instanced_base_instance
+    #[inline]
+    pub fn supports_base_instance(&self) -> bool {
+        self.base_instance
+    }
+
+    /// Whether buffers can be bound as atomic counters, through [`Buffer::bind_atomic_counter`].
+    /// Requires OpenGL 4.2 or the `ARB_shader_atomic_counters` extension.
+    ///
+    /// [`Buffer::bind_atomic_counter`]: ./buffer/struct.Buffer.html#method.bind_atomic_counter
+    #[inline]
+    pub fn supports_atomic_counters(&self) -> bool {
+        self.atomic_counters
+    }
+
+    /// Whether [`RenderState::viewport_f`] can place the viewport at fractional pixel positions.
+    /// Requires OpenGL 4.1 or the `ARB_viewport_array` extension.
+    ///
+    /// [`RenderState::viewport_f`]: ./framebuffer/render_state/struct.RenderState.html#structfield.viewport_f
+    #[inline]
+    pub fn supports_fractional_viewport(&self) -> bool {
+        self.viewport_array
+    }
+
+    /// Whether [`RenderState::sample_shading`] can be set. Requires OpenGL 4.0 or the
+    /// `ARB_sample_shading` extension.
+    ///
+    /// [`RenderState::sample_shading`]: ./framebuffer/render_state/struct.RenderState.html#structfield.sample_shading
+    #[inline]
+    pub fn supports_sample_shading(&self) -> bool {
+        self.sample_shading
+    }
+
+    /// Whether shaders can use 64-bit integer types, and whether `i64` and `u64` uniforms can be
+    /// uploaded. Requires the `ARB_gpu_shader_int64` extension.
+    ///
+    /// Creating a [`Program`] whose uniforms contain `i64` or `u64` members on a context without
+    /// the extension panics.
+    ///
+    /// [`Program`]: ./program/struct.Program.html
+    #[inline]
+    pub fn supports_shader_int64(&self) -> bool {
+        self.shader_int64
+    }
+
+    /// The highest anisotropic filtering level the context supports, for use as the upper bound
+    /// of [`SampleParameters::with_anisotropy`].
+    ///
+    /// This is `1.0`, which disables anisotropic filtering, if the context doesn't support
+    /// OpenGL 4.6 or either of the `ARB` and `EXT_texture_filter_anisotropic` extensions.
+    ///
+    /// [`SampleParameters::with_anisotropy`]: ./texture/sample_parameters/struct.SampleParameters.html#method.with_anisotropy
+    #[inline]
+    pub fn max_anisotropy(&self) -> f32 {
+        self.max_anisotropy.unwrap_or(1.0)
+    }
+
+    /// Whether the context can create textures with the compressed `internal_format`.
+    ///
+    /// RGTC formats are core in OpenGL 3.0, so they're always supported. S3TC formats require the
+    /// `EXT_texture_compression_s3tc` extension, and their sRGB variants additionally require
+    /// `EXT_texture_sRGB`. Neither RGTC nor the sRGB S3TC formats are required to be listed in
+    /// `GL_COMPRESSED_TEXTURE_FORMATS`, so that list only gets checked for other formats.
+    pub fn supports_compressed_format(&self, internal_format: GLenum) -> bool {
+        match internal_format {
+            gl::COMPRESSED_RED_RGTC1
+            | gl::COMPRESSED_SIGNED_RED_RGTC1
+            | gl::COMPRESSED_RG_RGTC2
+            | gl::COMPRESSED_SIGNED_RG_RGTC2 => true,
+            gl::COMPRESSED_RGB_S3TC_DXT1_EXT
+            | gl::COMPRESSED_RGBA_S3TC_DXT1_EXT
+            | gl::COMPRESSED_RGBA_S3TC_DXT3_EXT
+            | gl::COMPRESSED_RGBA_S3TC_DXT5_EXT => self.s3tc,
+            gl::COMPRESSED_SRGB_S3TC_DXT1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT => self.s3tc_srgb,
+            _ => self.compressed_texture_formats.contains(&internal_format),
+        }
+    }
+
+    /// Set whether pixels read back from framebuffers get clamped to the `[0, 1]` range.
+    ///
+    /// OpenGL defaults to only clamping reads from fixed-point attachments, but some drivers clamp
+    /// float attachments as well. Pass `false` before reading back HDR data with `read_pixels` to
+    /// guarantee values outside that range survive.
+    #[inline]
+    pub fn set_read_color_clamp(&self, clamp: bool) {
+        let clamp = match clamp {
+            true => gl::TRUE,
+            false => gl::FALSE,
+        };
+        unsafe {
+            self.gl.ClampColor(gl::CLAMP_READ_COLOR, clamp as GLenum);
+        }
+    }
+
+    /// Set the region of the framebuffer that draw calls render into, without uploading a whole
+    /// [`RenderState`]. Does nothing if the viewport is already set to `viewport`.
+    ///
+    /// This and [`RenderState::viewport`] share the same cache, so the next render state upload
+    /// replaces the viewport set here. `glClear` ignores the viewport, so clears always affect the
+    /// whole framebuffer (minus any scissored-out region) regardless of this setting.
+    ///
+    /// [`RenderState`]: ./framebuffer/render_state/struct.RenderState.html
+    /// [`RenderState::viewport`]: ./framebuffer/render_state/struct.RenderState.html#structfield.viewport
+    #[inline]
+    pub fn set_viewport(&self, viewport: RangeInclusive<GLVec2<u32, NonNormalized>>) {
+        framebuffer::render_state::set_viewport(self, viewport);
+    }
+
+    /// Set whether [`RenderState::dither`] is allowed to enable dithering. Defaults to `true`.
+    ///
+    /// Passing `false` disables `GL_DITHER` immediately and keeps it disabled for every draw and
+    /// clear until this is called with `true`, which is useful for tests and other code that
+    /// compares read back pixels against exact colors. Once dithering is allowed again, it gets
+    /// re-enabled by the next render state upload with `dither` set.
+    ///
+    /// [`RenderState::dither`]: ./framebuffer/render_state/struct.RenderState.html#structfield.dither
+    #[inline]
+    pub fn set_dither_allowed(&self, allowed: bool) {
+        framebuffer::render_state::set_dither_allowed(self, allowed);
+    }
+
+    #[inline]
+    pub fn dither_allowed(&self) -> bool {
+        self.dither_allowed.get()
+    }
+
+    /// Discard Gullery's cached bindings and render state, so the next operation on each target
+    /// re-binds and the next draw call re-uploads every render state field.
+    ///
+    /// Useful for debugging suspected caching bugs, or for resyncing after calls made through
+    /// [`raw_gl`](#method.raw_gl) changed state without restoring it. Per-object caches, such as
+    /// sampler parameters, VAO buffer bindings, and uniform change tracking, aren't reset.
+    ///
+    /// Bindings are forgotten rather than re-queried from OpenGL: every target, including the
+    /// framebuffer targets and the active texture unit, is marked as unknown until Gullery next
+    /// binds something to it.
+    pub fn invalidate_cache(&self) {
+        self.buffer_binds.invalidate();
+        self.program_target.invalidate();
+        self.vao_target.invalidate();
+        self.renderbuffer_target.invalidate();
+        self.framebuffer_targets.invalidate();
+        self.image_units.invalidate();
+        self.render_state.take().force_upload_state(self);
+    }
+
+    /// Start a named group of OpenGL commands, which debugging tools like RenderDoc show as a
+    /// collapsible node in captured frames. Groups can be nested, and each push must be matched
+    /// with a [`pop_debug_group`]. [`debug_group`] pops automatically.
+    ///
+    /// Does nothing if the context doesn't support OpenGL 4.3 or `KHR_debug`.
+    ///
+    /// [`pop_debug_group`]: #method.pop_debug_group
+    /// [`debug_group`]: #method.debug_group
+    pub fn push_debug_group(&self, name: &str) {
+        if !self.debug_groups {
+            return;
+        }
+        unsafe {
+            self.gl.PushDebugGroup(
+                gl::DEBUG_SOURCE_APPLICATION,
+                0,
+                name.len() as GLsizei,
+                name.as_ptr() as *const GLchar,
+            );
+        }
+    }
+
+    /// End the group started by the most recent [`push_debug_group`].
+    ///
+    /// Does nothing if the context doesn't support OpenGL 4.3 or `KHR_debug`.
+    ///
+    /// [`push_debug_group`]: #method.push_debug_group
+    pub fn pop_debug_group(&self) {
+        if !self.debug_groups {
+            return;
+        }
+        unsafe {
+            self.gl.PopDebugGroup();
+        }
+    }
+
+    /// Start a named debug group that ends when the returned guard gets dropped.
+    ///
+    /// ```rust,no_run
+    /// # use std::rc::Rc;
+    /// # use gullery::ContextState;
+    /// # let context_state: Rc<ContextState> = panic!();
+    /// {
+    ///     let _group = context_state.debug_group("shadow pass");
+    ///     // draw calls...
+    /// }
+    /// ```
+    #[inline]
+    pub fn debug_group(&self, name: &str) -> DebugGroup<'_> {
+        self.push_debug_group(name);
+        DebugGroup { state: self }
+    }
+
+    /// Set the function that receives [`Diagnostic`]s. Pass `None` to stop receiving them, which
+    /// is the default.
+    ///
+    /// Diagnostics are only checked for in debug builds, so the callback never gets called in
+    /// release builds.
+    ///
+    /// ## Panics
+    /// Panics if called from inside the callback.
+    ///
+    /// [`Diagnostic`]: ./enum.Diagnostic.html
+    #[inline]
+    pub fn set_diagnostic_callback(&self, callback: Option<Box<dyn Fn(&Diagnostic)>>) {
+        *self.diagnostic_callback.borrow_mut() = callback;
+    }
+
+    /// Pass the diagnostic built by `diagnostic` to the diagnostic callback, if there is one.
+    #[cfg(debug_assertions)]
+    pub(crate) fn report(&self, diagnostic: impl FnOnce() -> Diagnostic) {
+        if let Some(ref callback) = *self.diagnostic_callback.borrow() {
+            callback(&diagnostic());
+        }
+    }
+
+    /// Get the raw OpenGL function pointers, for calls that Gullery doesn't wrap.
+    ///
+    /// ## Safety
+    /// Gullery caches which objects are bound and which render state is set, and skips calls that
+    /// wouldn't change anything. Raw calls bypass that tracking, so any call that changes bindings
+    /// or state (e.g. `BindTexture`, `UseProgram`, `Viewport`, `Enable`) desyncs the cache and can
+    /// make later Gullery calls operate on the wrong objects. Restore anything you change before
+    /// handing control back to Gullery.
+    #[inline]
+    pub unsafe fn raw_gl(&self) -> &Gl {
+        &self.gl
+    }
+
+    unsafe fn with_share_group<F: Fn(&str) -> *const ()>(
+        load_fn: F,
+        share_group: Rc<()>,
+    ) -> Rc<ContextState> {
         let gl = Gl::load_with(|s| load_fn(s) as *const _);
 
         // use std::os::raw::c_void;
@@ -108,6 +538,10 @@ impl ContextState {
         //     gl.DebugMessageCallback(debug_callback, 0 as *mut _);
         // }
 
+        let gl_version = query_gl_version(&gl);
+        let supports =
+            |version, extension| supports_version_or_extension(&gl, gl_version, version, extension);
+
         Rc::new(ContextState {
             buffer_binds: buffer::BufferBinds::new(),
             program_target: program::ProgramTarget::new(),
@@ -115,13 +549,111 @@ impl ContextState {
             framebuffer_targets: framebuffer::FramebufferTargets::new(),
             default_framebuffer_exists: Cell::new(false),
             render_state: Cell::new(framebuffer::render_state::RenderState::default()),
+            dither_allowed: Cell::new(true),
+            diagnostic_callback: RefCell::new(None),
             image_units: texture::ImageUnits::new(&gl),
             renderbuffer_target: framebuffer::renderbuffer::RenderbufferTarget::new(),
+            share_group,
+            direct_state_access: supports((4, 5), "GL_ARB_direct_state_access"),
+            glsl_version: query_glsl_version(&gl),
+            conservative_raster_capability: conservative_raster_capability(&gl),
+            max_anisotropy: query_max_anisotropy(&gl, gl_version),
+            compressed_texture_formats: query_compressed_texture_formats(&gl),
+            s3tc: supports_extension(&gl, "GL_EXT_texture_compression_s3tc"),
+            s3tc_srgb: supports_extension(&gl, "GL_EXT_texture_compression_s3tc")
+                && supports_extension(&gl, "GL_EXT_texture_sRGB"),
+            buffer_storage: supports((4, 4), "GL_ARB_buffer_storage"),
+            base_instance: supports((4, 2), "GL_ARB_base_instance"),
+            shader_int64: supports_extension(&gl, "GL_ARB_gpu_shader_int64"),
+            debug_groups: supports((4, 3), "GL_KHR_debug"),
+            viewport_array: supports((4, 1), "GL_ARB_viewport_array"),
+            sample_shading: supports((4, 0), "GL_ARB_sample_shading"),
+            clear_buffer: supports((4, 3), "GL_ARB_clear_buffer_object"),
+            atomic_counters: supports((4, 2), "GL_ARB_shader_atomic_counters"),
+            transform_feedback3: supports((4, 0), "GL_ARB_transform_feedback3"),
             gl,
         })
     }
 }
 
+unsafe fn query_glsl_version(gl: &Gl) -> u32 {
+    let ptr = gl.GetString(gl::SHADING_LANGUAGE_VERSION);
+    let version = match ptr.is_null() {
+        true => None,
+        false => {
+            program::parse_glsl_version_string(&CStr::from_ptr(ptr as *const _).to_string_lossy())
+        }
+    };
+    version.unwrap_or(330)
+}
+
+/// The capability that enables conservative rasterization, if the context supports either
+/// `NV_conservative_raster` or `INTEL_conservative_rasterization`.
+unsafe fn conservative_raster_capability(gl: &Gl) -> Option<GLenum> {
+    if supports_extension(gl, "GL_NV_conservative_raster") {
+        Some(gl::CONSERVATIVE_RASTERIZATION_NV)
+    } else if supports_extension(gl, "GL_INTEL_conservative_rasterization") {
+        Some(gl::CONSERVATIVE_RASTERIZATION_INTEL)
+    } else {
+        None
+    }
+}
+
+/// Anisotropic filtering is core in OpenGL 4.6, and is otherwise provided by either
+/// `ARB_texture_filter_anisotropic` or `EXT_texture_filter_anisotropic`. The core and extension
+/// enums share the same values, so the core names get used for all three.
+unsafe fn query_max_anisotropy(gl: &Gl, gl_version: (GLint, GLint)) -> Option<f32> {
+    let supported =
+        supports_version_or_extension(gl, gl_version, (4, 6), "GL_ARB_texture_filter_anisotropic")
+            || supports_extension(gl, "GL_EXT_texture_filter_anisotropic");
+    match supported {
+        true => {
+            let mut max_anisotropy = 1.0;
+            gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+            Some(max_anisotropy)
+        }
+        false => None,
+    }
+}
+
+unsafe fn query_compressed_texture_formats(gl: &Gl) -> Box<[GLenum]> {
+    let mut num_formats = 0;
+    gl.GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut num_formats);
+    let mut formats = vec![0; num_formats as usize];
+    if num_formats > 0 {
+        gl.GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr());
+    }
+    formats.into_iter().map(|f| f as GLenum).collect()
+}
+
+/// The context's OpenGL version, as `(major, minor)`.
+unsafe fn query_gl_version(gl: &Gl) -> (GLint, GLint) {
+    let (mut major, mut minor) = (0, 0);
+    gl.GetIntegerv(gl::MAJOR_VERSION, &mut major);
+    gl.GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    (major, minor)
+}
+
+/// Whether a feature is available, either because it's core in `version` or because the context
+/// supports `extension`.
+unsafe fn supports_version_or_extension(
+    gl: &Gl,
+    gl_version: (GLint, GLint),
+    version: (GLint, GLint),
+    extension: &str,
+) -> bool {
+    gl_version >= version || supports_extension(gl, extension)
+}
+
+unsafe fn supports_extension(gl: &Gl, extension: &str) -> bool {
+    let mut num_extensions = 0;
+    gl.GetIntegerv(gl::NUM_EXTENSIONS, &mut num_extensions);
+    (0..num_extensions as GLuint).any(|i| {
+        let name = gl.GetStringi(gl::EXTENSIONS, i);
+        !name.is_null() && CStr::from_ptr(name as *const _).to_bytes() == extension.as_bytes()
+    })
+}
+
 #[cfg(test)]
 mod test_helper {
     use super::*;
@@ -134,8 +666,8 @@ mod test_helper {
 
     #[derive(Debug, Clone, Copy)]
     pub struct TestVertex {
-        pos: GLVec2<f32>,
-        color: GLVec3<f32>,
+        pub pos: GLVec2<f32>,
+        pub color: GLVec3<f32>,
     }
 
     impl Vertex for TestVertex {
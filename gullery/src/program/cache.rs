@@ -0,0 +1,212 @@
+// Copyright 2018 Osspial
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! On-disk storage for program binaries.
+//!
+//! Each cache file is laid out as follows, with all integers stored little-endian:
+//!
+//! ```text
+//! MAGIC | driver string length: u32 | driver string | binary format: u32 | binary data
+//! ```
+//!
+//! The driver string is built from `GL_VENDOR`, `GL_RENDERER`, and `GL_VERSION`. Drivers refuse
+//! binaries built by other drivers, so storing it lets us treat stale files as cache misses instead
+//! of handing them to the driver.
+
+use crate::{
+    framebuffer::attachments::{
+        AMRNSImpl, AttachmentType, Attachments, AttachmentsMemberRegistryNoSpecifics,
+    },
+    gl::{self, types::*, Gl},
+    vertex::{Vertex, VertexAttribute, VertexMemberRegistry},
+};
+
+use std::{
+    ffi::CStr,
+    fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+const MAGIC: &[u8; 8] = b"GLRYPRG\0";
+
+/// Gets hashed into every cache file name. Bump this whenever the way programs get built changes
+/// in a way that would make old binaries wrong for the same sources.
+const FORMAT_VERSION: u32 = 1;
+
+/// The path of the cache file for the given program sources and types.
+///
+/// The names of the vertex attributes and fragment outputs get hashed alongside the sources,
+/// because their locations are bound before linking and baked into the binary. The hash is
+/// FNV-1a, so file names stay the same across Rust versions and runs of the program.
+pub fn cache_path<V: Vertex, A: Attachments>(
+    cache_dir: &Path,
+    vert: &str,
+    geom: Option<&str>,
+    frag: &str,
+) -> PathBuf {
+    let mut hasher = Fnv1a::new();
+    hasher.write(&FORMAT_VERSION.to_le_bytes());
+    hasher.write_str(vert);
+    match geom {
+        Some(geom) => {
+            hasher.write(&[1]);
+            hasher.write_str(geom);
+        }
+        None => hasher.write(&[0]),
+    }
+    hasher.write_str(frag);
+
+    struct VertexNames<'a, V: Vertex>(&'a mut Fnv1a, PhantomData<V>);
+    impl<'a, V: Vertex> VertexMemberRegistry for VertexNames<'a, V> {
+        type Group = V;
+        fn add_member<T>(&mut self, name: &str, _: fn(*const V) -> *const T)
+        where
+            T: VertexAttribute,
+        {
+            self.0.write_str(name);
+            self.0
+                .write(&(T::prim_tag().num_attrib_slots() as u32).to_le_bytes());
+        }
+    }
+    V::members(VertexNames(&mut hasher, PhantomData));
+
+    struct AttachmentNames<'a, A: Attachments>(&'a mut Fnv1a, PhantomData<A>);
+    impl<'a, A: Attachments> AttachmentsMemberRegistryNoSpecifics for AttachmentNames<'a, A> {
+        type Attachments = A;
+        fn add_member<T>(&mut self, name: &str, _: impl FnOnce(&A) -> &T)
+        where
+            T: AttachmentType,
+        {
+            self.0.write_str(name);
+        }
+    }
+    A::members(AMRNSImpl(AttachmentNames(&mut hasher, PhantomData)));
+
+    cache_dir.join(format!("{:016x}.bin", hasher.finish()))
+}
+
+/// 64-bit FNV-1a hasher.
+///
+/// `DefaultHasher` doesn't guarantee its output stays the same between Rust releases, which would
+/// silently invalidate every cache file whenever the application gets rebuilt.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// Write a length-prefixed string, so adjacent strings can't run into each other.
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A string identifying the driver that produced a program binary.
+pub fn driver_string(gl: &Gl) -> String {
+    let get_string = |name| unsafe {
+        let ptr = gl.GetString(name);
+        match ptr.is_null() {
+            true => String::new(),
+            false => CStr::from_ptr(ptr as *const _)
+                .to_string_lossy()
+                .into_owned(),
+        }
+    };
+
+    format!(
+        "{}\n{}\n{}",
+        get_string(gl::VENDOR),
+        get_string(gl::RENDERER),
+        get_string(gl::VERSION)
+    )
+}
+
+/// Load a cached binary, returning `None` if the file doesn't exist, is malformed, or was built by
+/// a different driver.
+pub fn read(path: &Path, driver_string: &str) -> Option<(GLenum, Vec<u8>)> {
+    let file = fs::read(path).ok()?;
+
+    let rest = strip_prefix(&file, MAGIC)?;
+    let (driver_len, rest) = split_u32(rest)?;
+    if rest.len() < driver_len as usize {
+        return None;
+    }
+    let (file_driver_string, rest) = rest.split_at(driver_len as usize);
+    if file_driver_string != driver_string.as_bytes() {
+        return None;
+    }
+    let (format, binary) = split_u32(rest)?;
+
+    Some((format, binary.to_vec()))
+}
+
+/// Write a binary to the cache, creating the cache directory if it doesn't exist.
+pub fn write(path: &Path, driver_string: &str, format: GLenum, binary: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = Vec::with_capacity(MAGIC.len() + 8 + driver_string.len() + binary.len());
+    file.extend_from_slice(MAGIC);
+    file.extend_from_slice(&(driver_string.len() as u32).to_le_bytes());
+    file.extend_from_slice(driver_string.as_bytes());
+    file.extend_from_slice(&format.to_le_bytes());
+    file.extend_from_slice(binary);
+
+    fs::write(path, file)
+}
+
+fn strip_prefix<'a>(bytes: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    match bytes.starts_with(prefix) {
+        true => Some(&bytes[prefix.len()..]),
+        false => None,
+    }
+}
+
+fn split_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    if bytes.len() < 4 {
+        return None;
+    }
+    let (int_bytes, rest) = bytes.split_at(4);
+    let mut int = [0; 4];
+    int.copy_from_slice(int_bytes);
+    Some((u32::from_le_bytes(int), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference() {
+        let mut hasher = Fnv1a::new();
+        assert_eq!(0xcbf2_9ce4_8422_2325, hasher.finish());
+        hasher.write(b"a");
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hasher.finish());
+    }
+}
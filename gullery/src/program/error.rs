@@ -42,6 +42,11 @@ pub struct MismatchedTypeError {
 /// Error that occurred during program compilation.
 #[derive(Debug, Clone)]
 pub enum ProgramError {
+    /// Error reported by driver that occurred during shader compilation.
+    ///
+    /// Only returned by functions that compile shaders as part of program creation, such as
+    /// [`Program::new_cached`](../struct.Program.html#method.new_cached).
+    ShaderError(ShaderError),
     /// Error reported by driver that occurred during program linking.
     LinkError(LinkError),
     /// A mismatch exists between a Rust type an a GLSL type.
@@ -51,6 +56,22 @@ pub enum ProgramError {
     MismatchedTypeError(Vec<MismatchedTypeError>),
 }
 
+/// Error returned when binding a uniform buffer to one of a program's uniform blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniformBlockError {
+    /// The program has no active uniform block with the given name, which happens if the shader
+    /// doesn't declare it or the shader compiler optimized it out.
+    ///
+    /// Includes the block's name.
+    NotFound(String),
+    /// The bound range of the buffer is smaller than the block, which usually means the buffer's
+    /// type doesn't match the block's `std140` layout. Both sizes are in bytes.
+    RangeTooSmall {
+        block_size: usize,
+        range_size: usize,
+    },
+}
+
 /// Error detected by Gullery that could indicate a misbehaved program.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ProgramWarning {
@@ -96,6 +117,7 @@ impl Display for ProgramError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         use self::ProgramError::*;
         match *self {
+            ShaderError(ref e) => write!(f, "{}", e),
             LinkError(ref e) => write!(f, "{}", e),
             MismatchedTypeError(ref errs) => {
                 let mut errs = errs.iter();
@@ -111,6 +133,25 @@ impl Display for ProgramError {
     }
 }
 
+impl Error for UniformBlockError {}
+
+impl Display for UniformBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        use self::UniformBlockError::*;
+        match *self {
+            NotFound(ref name) => write!(f, "No active uniform block named `{}`", name),
+            RangeTooSmall {
+                block_size,
+                range_size,
+            } => write!(
+                f,
+                "Uniform block needs {} bytes, but the bound buffer range has {}",
+                block_size, range_size
+            ),
+        }
+    }
+}
+
 impl Display for ProgramWarning {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         use self::ProgramWarning::*;
@@ -151,3 +192,9 @@ impl From<ProgramError> for io::Error {
         io::Error::new(io::ErrorKind::InvalidData, e)
     }
 }
+
+impl From<UniformBlockError> for io::Error {
+    fn from(e: UniformBlockError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
@@ -28,26 +28,36 @@
 //!     Said color data can be displayed to the user or saved for later use.
 //!     [(OpenGL Wiki)](https://www.khronos.org/opengl/wiki/Fragment_Shader)
 //!
+//! A program used purely for [transform feedback](./struct.Program.html#method.new_transform_feedback)
+//! can omit the fragment stage.
+//!
 //! ## Programs
 //! Once the desired shaders have been compiled, they must be linked together to create a `Program`
 //! object. These objects then get used by the `Framebuffer::draw` function to render the provided
 //! vertex data to a render target.
 //!
 //! [`Program`]: ./struct.Program.html
+mod cache;
 pub mod error;
 mod raw;
 
 use self::{
-    error::{ProgramError, ProgramWarning, ShaderError},
+    error::{ProgramError, ProgramWarning, ShaderError, UniformBlockError},
     raw::{RawBoundProgram, RawProgram, RawProgramTarget, RawShader},
 };
 
 use crate::{
-    framebuffer::attachments::Attachments, uniform::Uniforms, vertex::Vertex, ContextState,
-    GLObject, Handle,
+    buffer::{Buffer, UniformBuffer},
+    framebuffer::{attachments::Attachments, draw_vao, render_state::RenderState, DrawMode},
+    gl::{self, types::*},
+    uniform::{self, Uniforms},
+    vertex::{Index, Vertex, VertexArrayObject},
+    ContextState, GLObject, Handle,
 };
 
-use std::{marker::PhantomData, rc::Rc};
+use std::{
+    any::Any, cell::RefCell, marker::PhantomData, mem, ops::RangeBounds, path::Path, rc::Rc,
+};
 
 pub use self::raw::{FragmentStage, GeometryStage, ShaderStage, VertexStage};
 
@@ -89,10 +99,27 @@ where
 {
     raw: RawProgram,
     uniform_locs: U::ULC,
+    /// The last uploaded value of each uniform member, if change tracking is enabled.
+    uniform_cache: Option<RefCell<Vec<Option<Box<dyn Any>>>>>,
     state: Rc<ContextState>,
     _marker: PhantomData<(*const V, *const A)>,
 }
 
+/// The primitive type written to the output buffer during transform feedback.
+///
+/// This must match the primitives reaching the end of the program's vertex processing: the output
+/// primitive of the geometry shader if the program has one, or the base primitive of the
+/// [`DrawMode`] otherwise.
+///
+/// [`DrawMode`]: ../framebuffer/enum.DrawMode.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum FeedbackPrimitive {
+    Points = gl::POINTS,
+    Lines = gl::LINES,
+    Triangles = gl::TRIANGLES,
+}
+
 pub(crate) struct ProgramTarget(RawProgramTarget);
 pub(crate) struct BoundProgram<'a, V: 'a + Vertex, U: 'static + Uniforms, A: 'static + Attachments>
 {
@@ -111,6 +138,71 @@ impl<S: ShaderStage> Shader<S> {
             state,
         })
     }
+
+    /// Create a new shader, first checking that the context supports the GLSL version declared by
+    /// the source's `#version` directive.
+    ///
+    /// Drivers often report unsupported versions with confusing compilation errors. If the declared
+    /// version is newer than [`ContextState::glsl_version`], this returns an error naming both
+    /// versions without passing the source to OpenGL. Per the GLSL spec, sources without a
+    /// `#version` directive are treated as GLSL 1.10.
+    ///
+    /// [`ContextState::glsl_version`]: ../struct.ContextState.html#method.glsl_version
+    pub fn new_with_version_check(
+        source: &str,
+        state: Rc<ContextState>,
+    ) -> Result<Shader<S>, ShaderError> {
+        let version = declared_glsl_version(source)?;
+        let supported = state.glsl_version();
+        if version > supported {
+            return Err(ShaderError(format!(
+                "shader declares `#version {}`, but the context only supports GLSL versions up to {}",
+                version, supported
+            )));
+        }
+
+        Shader::new(source, state)
+    }
+}
+
+/// Parse the version number out of a shader's `#version` directive.
+fn declared_glsl_version(source: &str) -> Result<u32, ShaderError> {
+    let directive = source
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| line.starts_with("#"))
+        .and_then(|line| {
+            let mut tokens = line[1..].split_whitespace();
+            match tokens.next() {
+                Some("version") => Some(tokens.next()),
+                _ => None,
+            }
+        });
+
+    match directive {
+        None => Ok(110),
+        Some(number) => number.and_then(|n| n.parse().ok()).ok_or_else(|| {
+            ShaderError(format!(
+                "malformed `#version` directive; expected a version number, found {:?}",
+                number.unwrap_or("")
+            ))
+        }),
+    }
+}
+
+/// Parse a `GL_SHADING_LANGUAGE_VERSION` string, such as `"4.60 NVIDIA"`, into the format used by
+/// `#version` directives.
+pub(crate) fn parse_glsl_version_string(version: &str) -> Option<u32> {
+    let number = version.split_whitespace().next()?;
+    let mut parts = number.split('.');
+    let major: u32 = parts.next()?.parse().ok()?;
+    let minor_str = parts.next()?;
+    let minor: u32 = minor_str.parse().ok()?;
+    match minor_str.len() {
+        1 => Some(major * 100 + minor * 10),
+        2 => Some(major * 100 + minor),
+        _ => None,
+    }
 }
 
 impl<V: Vertex, U: Uniforms, A: Attachments> Program<V, U, A> {
@@ -132,7 +224,7 @@ impl<V: Vertex, U: Uniforms, A: Attachments> Program<V, U, A> {
             panic!("Shaders passed to Program creation are parts of different contexts!");
         }
 
-        let (raw, mut warnings) = RawProgram::new::<_, U>(
+        let (raw, warnings) = RawProgram::new::<_, U>(
             |mut rpsa| {
                 rpsa.attach_shader(&vert.raw);
                 if let Some(ref geom) = geom {
@@ -143,16 +235,395 @@ impl<V: Vertex, U: Uniforms, A: Attachments> Program<V, U, A> {
             &vert.state.gl,
         )?;
 
-        let uniform_locs = raw.get_uniform_locations::<U>(&vert.state.gl, &mut warnings);
-        Ok((
+        Ok(Program::from_raw(raw, warnings, vert.state.clone()))
+    }
+
+    /// Create a new program from shader source code, caching the linked program binary in
+    /// `cache_dir`.
+    ///
+    /// The cache is keyed by a hash of the shader sources and the vertex attribute and fragment
+    /// output names. If `cache_dir` contains a binary for the provided sources that was built by
+    /// the current driver, the program gets loaded from that binary and shader compilation is
+    /// skipped entirely. Otherwise, the shaders are compiled and linked normally, and the resulting
+    /// binary gets written to `cache_dir` for future calls.
+    ///
+    /// The cache is purely an optimization: if the cache can't be read or written, or the driver
+    /// doesn't support program binaries, this behaves exactly like compiling the shaders with
+    /// [`Shader::new`] and passing them to [`Program::new`].
+    ///
+    /// [`Shader::new`]: ./struct.Shader.html#method.new
+    /// [`Program::new`]: ./struct.Program.html#method.new
+    pub fn new_cached(
+        cache_dir: impl AsRef<Path>,
+        vert_source: &str,
+        geom_source: Option<&str>,
+        frag_source: &str,
+        state: Rc<ContextState>,
+    ) -> Result<(Program<V, U, A>, Vec<ProgramWarning>), ProgramError> {
+        let cache_path =
+            cache::cache_path::<V, A>(cache_dir.as_ref(), vert_source, geom_source, frag_source);
+        let driver_string = cache::driver_string(&state.gl);
+
+        if let Some((format, binary)) = cache::read(&cache_path, &driver_string) {
+            if let Some(result) = RawProgram::from_binary::<V, U, A>(format, &binary, &state.gl) {
+                let (raw, warnings) = result?;
+                return Ok(Program::from_raw(raw, warnings, state));
+            }
+        }
+
+        let vert = Shader::<VertexStage<V>>::new(vert_source, state.clone())
+            .map_err(ProgramError::ShaderError)?;
+        let geom = match geom_source {
+            Some(geom_source) => Some(
+                Shader::<GeometryStage>::new(geom_source, state.clone())
+                    .map_err(ProgramError::ShaderError)?,
+            ),
+            None => None,
+        };
+        let frag = Shader::<FragmentStage<A>>::new(frag_source, state.clone())
+            .map_err(ProgramError::ShaderError)?;
+
+        let (raw, warnings) = RawProgram::new::<_, U>(
+            |mut rpsa| {
+                rpsa.binary_retrievable();
+                rpsa.attach_shader(&vert.raw);
+                if let Some(ref geom) = geom {
+                    rpsa.attach_shader(&geom.raw);
+                }
+                rpsa.attach_shader(&frag.raw);
+            },
+            &state.gl,
+        )?;
+
+        if let Some((format, binary)) = raw.binary(&state.gl) {
+            // Failing to write the cache just means we'll compile the shaders again next time.
+            let _ = cache::write(&cache_path, &driver_string, format, &binary);
+        }
+
+        Ok(Program::from_raw(raw, warnings, state))
+    }
+
+    /// Set whether the program only uploads the uniforms that changed since the previous draw.
+    ///
+    /// OpenGL stores uniform values in the program object, so unchanged values don't need to be
+    /// uploaded again. When tracking is enabled, the program keeps a copy of each uniform's last
+    /// uploaded value and skips the `glUniform*` call for members that compare equal to it.
+    /// Texture members are always uploaded, since they also bind the texture to an image unit.
+    ///
+    /// The cache lives in the program rather than alongside the uniforms, since that's where
+    /// OpenGL keeps the values it's mirroring: every set of uniforms drawn with the program goes
+    /// through the same cache, so values uploaded by one set can never be mistaken for another's.
+    /// Values set on the program through [`ContextState::raw_gl`] aren't tracked, so toggle
+    /// tracking off and back on to clear the cache after making such calls.
+    ///
+    /// Comparing values costs CPU time, so this is disabled by default. It pays off for programs
+    /// used by many draw calls that each only change a few uniforms.
+    ///
+    /// [`ContextState::raw_gl`]: ../struct.ContextState.html#method.raw_gl
+    pub fn set_uniform_change_tracking(&mut self, track_changes: bool) {
+        self.uniform_cache = match track_changes {
+            true => Some(RefCell::new(Vec::new())),
+            false => None,
+        };
+    }
+
+    /// Look up the location of the vertex attribute named `name`.
+    ///
+    /// Returns `None` if the program has no active attribute with that name, which happens if
+    /// the shader doesn't declare it or the shader compiler optimized it out. Useful for checking
+    /// that the members of `V` match up with the shader's inputs.
+    pub fn attribute_location(&self, name: &str) -> Option<GLint> {
+        self.raw.attribute_location(name, &self.state.gl)
+    }
+
+    /// Bind all of `buffer` to the binding point `binding`, and have the program's uniform block
+    /// named `block_name` read from that binding point.
+    ///
+    /// This lets large uniform sets be uploaded once and shared between programs, rather than
+    /// being uploaded member-by-member through [`Uniforms`]. The block's binding is stored in the
+    /// program, but the buffer binding is context state: the buffer stays bound to `binding` until
+    /// another buffer gets bound there or the buffer is dropped. See [`UniformBuffer`] for the
+    /// layout `T` needs to have.
+    ///
+    /// ## Errors
+    /// Returns an error if the program has no active block named `block_name`, or if the buffer is
+    /// smaller than the block.
+    ///
+    /// ## Panics
+    /// Panics if `binding` is greater than or equal to `GL_MAX_UNIFORM_BUFFER_BINDINGS`.
+    ///
+    /// [`Uniforms`]: ../uniform/trait.Uniforms.html
+    /// [`UniformBuffer`]: ../buffer/struct.UniformBuffer.html
+    #[inline]
+    pub fn bind_uniform_block<T: 'static + Copy>(
+        &self,
+        block_name: &str,
+        binding: u32,
+        buffer: &UniformBuffer<T>,
+    ) -> Result<(), UniformBlockError> {
+        self.bind_uniform_block_range(block_name, binding, buffer, ..)
+    }
+
+    /// Like [`bind_uniform_block`], but only binds the elements of `buffer` in `range`. Useful for
+    /// keeping the uniforms of many draws in one buffer and selecting each draw's with its index.
+    ///
+    /// ## Errors
+    /// Returns an error if the program has no active block named `block_name`, or if the range is
+    /// smaller than the block.
+    ///
+    /// ## Panics
+    /// Panics if `binding` is greater than or equal to `GL_MAX_UNIFORM_BUFFER_BINDINGS`, if the
+    /// range ends past `buffer.len()` or starts after it ends, or if the range's byte offset isn't
+    /// a multiple of `GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+    ///
+    /// [`bind_uniform_block`]: #method.bind_uniform_block
+    pub fn bind_uniform_block_range<T, R>(
+        &self,
+        block_name: &str,
+        binding: u32,
+        buffer: &UniformBuffer<T>,
+        range: R,
+    ) -> Result<(), UniformBlockError>
+    where
+        T: 'static + Copy,
+        R: RangeBounds<usize>,
+    {
+        let gl = &self.state.gl;
+        let start = crate::bound_to_num_start(range.start_bound(), 0);
+        let end = crate::bound_to_num_end(range.end_bound(), buffer.len());
+        if end > buffer.len() {
+            panic!(
+                "Uniform block range end {} out of range for buffer of length {}",
+                end,
+                buffer.len()
+            );
+        }
+        let range_size = end.checked_sub(start).unwrap_or_else(|| {
+            panic!(
+                "Uniform block range starts at {} but ends at {}",
+                start, end
+            )
+        }) * mem::size_of::<T>();
+        let offset = start * mem::size_of::<T>();
+
+        let (mut max_bindings, mut offset_alignment) = (0, 0);
+        unsafe {
+            gl.GetIntegerv(gl::MAX_UNIFORM_BUFFER_BINDINGS, &mut max_bindings);
+            gl.GetIntegerv(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT, &mut offset_alignment);
+        }
+        if binding >= max_bindings as u32 {
+            panic!(
+                "uniform buffer binding {} exceeds maximum of {}",
+                binding,
+                max_bindings - 1
+            );
+        }
+        if !offset.is_multiple_of(offset_alignment as usize) {
+            panic!(
+                "uniform buffer offset {} isn't a multiple of the required alignment {}",
+                offset, offset_alignment
+            );
+        }
+
+        let index = self
+            .raw
+            .uniform_block_index(block_name, gl)
+            .ok_or_else(|| UniformBlockError::NotFound(block_name.to_string()))?;
+        let block_size = self.raw.uniform_block_data_size(index, gl);
+        if range_size < block_size {
+            return Err(UniformBlockError::RangeTooSmall {
+                block_size,
+                range_size,
+            });
+        }
+
+        self.raw.set_uniform_block_binding(index, binding, gl);
+        let handle = buffer.handle().get();
+        unsafe {
+            if start == 0 && end == buffer.len() {
+                gl.BindBufferBase(gl::UNIFORM_BUFFER, binding, handle);
+            } else {
+                gl.BindBufferRange(
+                    gl::UNIFORM_BUFFER,
+                    binding,
+                    handle,
+                    offset as GLintptr,
+                    range_size as GLsizeiptr,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn from_raw(
+        raw: RawProgram,
+        mut warnings: Vec<ProgramWarning>,
+        state: Rc<ContextState>,
+    ) -> (Program<V, U, A>, Vec<ProgramWarning>) {
+        assert!(
+            state.supports_shader_int64() || !uniform::has_int64_members::<U>(),
+            "uniforms contain 64-bit integers, which require `ARB_gpu_shader_int64`"
+        );
+        let uniform_locs = raw.get_uniform_locations::<U>(&state.gl, &mut warnings);
+        (
             Program {
                 uniform_locs,
+                uniform_cache: None,
                 raw,
-                state: vert.state.clone(),
+                state,
                 _marker: PhantomData,
             },
             warnings,
-        ))
+        )
+    }
+}
+
+impl<V: Vertex, U: Uniforms> Program<V, U, ()> {
+    /// Create a program without a fragment stage, for capturing vertex processing outputs with
+    /// transform feedback.
+    ///
+    /// `varyings` names the vertex shader outputs (or geometry shader outputs, if `geom` is
+    /// provided) that get written to the output buffer by [`draw_feedback`], interleaved in the
+    /// order they're listed. This lets GPU-side computation like particle simulation run on
+    /// OpenGL 3.3 hardware without compute shaders.
+    ///
+    /// On OpenGL 4.0 or with `ARB_transform_feedback3`, `varyings` may also contain the special
+    /// names `gl_NextBuffer`, which sends the following varyings to the next output buffer, and
+    /// `gl_SkipComponents1` through `gl_SkipComponents4`, which leave gaps in the output. Outputs
+    /// routed to several buffers get captured with [`draw_feedback_buffers`].
+    ///
+    /// [`draw_feedback`]: ./struct.Program.html#method.draw_feedback
+    /// [`draw_feedback_buffers`]: ./struct.Program.html#method.draw_feedback_buffers
+    pub fn new_transform_feedback(
+        vert: &Shader<VertexStage<V>>,
+        geom: Option<&Shader<GeometryStage>>,
+        varyings: &[&str],
+    ) -> Result<(Program<V, U, ()>, Vec<ProgramWarning>), ProgramError> {
+        let vsp = vert.state.as_ref() as *const _;
+        let gsp = geom.map(|g| g.state.as_ref() as *const _).unwrap_or(vsp);
+
+        if vsp != gsp {
+            panic!("Shaders passed to Program creation are parts of different contexts!");
+        }
+
+        let (raw, warnings) = RawProgram::new::<_, U>(
+            |mut rpsa| {
+                rpsa.transform_feedback_varyings(varyings);
+                rpsa.attach_shader(&vert.raw);
+                if let Some(ref geom) = geom {
+                    rpsa.attach_shader(&geom.raw);
+                }
+            },
+            &vert.state.gl,
+        )?;
+
+        Ok(Program::from_raw(raw, warnings, vert.state.clone()))
+    }
+
+    /// Run the program over the vertices in `vao`, writing the captured varyings into `output`.
+    ///
+    /// Set `render_state.rasterizer_discard` to skip rasterization entirely, since there's no
+    /// fragment stage to run. Outputs that don't fit in `output` get silently discarded; wrap the
+    /// call in a [`TransformFeedbackPrimitivesWritten`] query to find out how many were captured.
+    ///
+    /// ## Parameters
+    /// * `primitive`: The primitive type captured into `output`. See [`FeedbackPrimitive`].
+    /// * `mode`, `range`, `uniforms`, `render_state`: The same as in [`Framebuffer::draw`].
+    ///
+    /// [`FeedbackPrimitive`]: ./enum.FeedbackPrimitive.html
+    /// [`Framebuffer::draw`]: ../framebuffer/trait.Framebuffer.html#method.draw
+    /// [`TransformFeedbackPrimitivesWritten`]: ../query/enum.QueryType.html#variant.TransformFeedbackPrimitivesWritten
+    pub fn draw_feedback<R, I, N, O>(
+        &self,
+        primitive: FeedbackPrimitive,
+        mode: DrawMode,
+        range: R,
+        vao: &VertexArrayObject<V, I>,
+        uniforms: &N,
+        render_state: &RenderState,
+        output: &mut Buffer<O>,
+    ) where
+        R: RangeBounds<usize>,
+        I: Index,
+        N: Uniforms<ULC = U::ULC, Static = U>,
+        O: 'static + Copy,
+    {
+        self.draw_feedback_buffers(
+            primitive,
+            mode,
+            range,
+            vao,
+            uniforms,
+            render_state,
+            &mut [output],
+        );
+    }
+
+    /// Run the program over the vertices in `vao`, writing the captured varyings into several
+    /// output buffers.
+    ///
+    /// `outputs[0]` receives the varyings listed before the first `gl_NextBuffer` marker in the
+    /// program's varyings, and `outputs[i]` receives the varyings listed after the `i`th marker, so
+    /// a geometry shader can e.g. write live particles to one buffer and dead particles to another
+    /// in a single pass. Otherwise, this behaves like [`draw_feedback`].
+    ///
+    /// ## Panics
+    /// Panics if `outputs` has more than one buffer and the context doesn't support OpenGL 4.0 or
+    /// `ARB_transform_feedback3`, or has more buffers than `GL_MAX_TRANSFORM_FEEDBACK_BUFFERS`.
+    ///
+    /// [`draw_feedback`]: #method.draw_feedback
+    pub fn draw_feedback_buffers<R, I, N, O>(
+        &self,
+        primitive: FeedbackPrimitive,
+        mode: DrawMode,
+        range: R,
+        vao: &VertexArrayObject<V, I>,
+        uniforms: &N,
+        render_state: &RenderState,
+        outputs: &mut [&mut Buffer<O>],
+    ) where
+        R: RangeBounds<usize>,
+        I: Index,
+        N: Uniforms<ULC = U::ULC, Static = U>,
+        O: 'static + Copy,
+    {
+        let state = &self.state;
+        let gl = &state.gl;
+        if outputs.len() > 1 {
+            assert!(
+                state.transform_feedback3,
+                "multiple transform feedback buffers are unsupported; requires OpenGL 4.0 or \
+                 ARB_transform_feedback3"
+            );
+            let mut max_buffers = 1;
+            unsafe { gl.GetIntegerv(gl::MAX_TRANSFORM_FEEDBACK_BUFFERS, &mut max_buffers) };
+            if outputs.len() > max_buffers as usize {
+                panic!(
+                    "{} transform feedback buffers requested, but OpenGL implementation supports \
+                     at most {}",
+                    outputs.len(),
+                    max_buffers
+                );
+            }
+        }
+
+        render_state.upload_state(state);
+        unsafe {
+            let vao_bind = state.vao_target.bind(vao);
+
+            let program_bind = state.program_target.bind(self);
+            program_bind.upload_uniforms(uniforms);
+
+            for (index, output) in outputs.iter().enumerate() {
+                output.bind_base(gl::TRANSFORM_FEEDBACK_BUFFER, index as GLuint);
+            }
+            gl.BeginTransformFeedback(primitive as GLenum);
+            draw_vao(gl, mode, range, &vao_bind, None, None, None);
+            gl.EndTransformFeedback();
+            for index in 0..outputs.len() {
+                gl.BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index as GLuint, 0);
+            }
+        }
     }
 }
 
@@ -162,6 +633,11 @@ impl ProgramTarget {
         ProgramTarget(RawProgramTarget::new())
     }
 
+    #[inline]
+    pub(crate) fn invalidate(&self) {
+        self.0.invalidate();
+    }
+
     #[inline]
     pub unsafe fn bind<'a, V, U, A>(
         &'a self,
@@ -190,9 +666,11 @@ where
     where
         N: Uniforms<ULC = U::ULC, Static = U>,
     {
+        let mut cache = self.program.uniform_cache.as_ref().map(|c| c.borrow_mut());
         self.raw.upload_uniforms(
             uniforms,
             self.program.uniform_locs.as_ref(),
+            cache.as_deref_mut(),
             &self.program.state.image_units,
             &self.program.state.gl,
         )
@@ -251,11 +729,12 @@ where
 mod tests {
     use super::*;
     use crate::{
-        gl::types::*,
-        geometry::GLVec3,
+        buffer::BufferUsage,
+        geometry::{GLVec2, GLVec3, GLVec4},
         test_helper::{TestVertex, CONTEXT_STATE},
         uniform::{Uniforms, UniformsMemberRegistry},
     };
+    use std::ffi::CString;
 
     const VERTEX_SHADER: &str = r#"
         #version 330
@@ -327,4 +806,280 @@ mod tests {
             })
         })
     }
+
+    #[test]
+    fn track_uniform_changes() {
+        CONTEXT_STATE.with(|state| {
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+
+            let (mut program, _) = Program::<TestVertex, TestUniforms, ()>::new(
+                &vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+            program.set_uniform_change_tracking(true);
+
+            let mut uniforms = TestUniforms {
+                color_tint: GLVec3::new(1.0, 1.0, 1.0),
+                offset: GLVec3::new(0.0, 1.0, 0.0),
+            };
+            let (tint_loc, offset_loc) = (program.uniform_locs[0], program.uniform_locs[1]);
+            let get_uniform = |loc| unsafe {
+                let mut value = [0.0; 3];
+                state
+                    .gl
+                    .GetUniformfv(program.handle().get(), loc, value.as_mut_ptr());
+                value
+            };
+
+            let program_bind = unsafe { state.program_target.bind(&program) };
+            program_bind.upload_uniforms(&uniforms);
+
+            // Overwrite both uniforms behind the cache's back. Re-uploading the same values should
+            // skip both `glUniform*` calls, leaving the overwritten values in place.
+            unsafe {
+                state.gl.Uniform3f(tint_loc, 0.5, 0.5, 0.5);
+                state.gl.Uniform3f(offset_loc, 0.5, 0.5, 0.5);
+            }
+            program_bind.upload_uniforms(&uniforms);
+            assert_eq!([0.5, 0.5, 0.5], get_uniform(tint_loc));
+            assert_eq!([0.5, 0.5, 0.5], get_uniform(offset_loc));
+
+            // Only the changed member gets uploaded.
+            uniforms.offset = GLVec3::new(0.0, 0.0, 1.0);
+            program_bind.upload_uniforms(&uniforms);
+            assert_eq!([0.5, 0.5, 0.5], get_uniform(tint_loc));
+            assert_eq!([0.0, 0.0, 1.0], get_uniform(offset_loc));
+        })
+    }
+
+    #[test]
+    fn capture_transform_feedback() {
+        CONTEXT_STATE.with(|state| {
+            let vertices = [
+                TestVertex {
+                    pos: GLVec2::new(0.0, 0.0),
+                    color: GLVec3::new(0.5, 0.25, 1.0),
+                },
+                TestVertex {
+                    pos: GLVec2::new(1.0, 1.0),
+                    color: GLVec3::new(1.0, 1.0, 0.0),
+                },
+            ];
+            let vertex_buffer =
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone());
+            let vao: VertexArrayObject<TestVertex, !> = VertexArrayObject::new(vertex_buffer, None);
+
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let (program, _) = Program::<TestVertex, TestUniforms, ()>::new_transform_feedback(
+                &vertex_shader,
+                None,
+                &["vertex_color"],
+            )
+            .unwrap();
+
+            let mut output: Buffer<[f32; 4]> =
+                Buffer::with_size(BufferUsage::StreamRead, vertices.len(), state.clone());
+            let render_state = RenderState {
+                rasterizer_discard: true,
+                ..RenderState::default()
+            };
+            program.draw_feedback(
+                FeedbackPrimitive::Points,
+                DrawMode::Points,
+                ..,
+                &vao,
+                &TestUniforms {
+                    color_tint: GLVec3::new(2.0, 4.0, 1.0),
+                    offset: GLVec3::new(0.0, 0.0, 0.0),
+                },
+                &render_state,
+                &mut output,
+            );
+
+            let mut captured = [[0.0; 4]; 2];
+            unsafe { output.get_data(0, &mut captured) };
+            assert_eq!([[1.0, 1.0, 1.0, 1.0], [2.0, 4.0, 0.0, 1.0]], captured);
+        })
+    }
+
+    #[test]
+    fn attribute_locations() {
+        CONTEXT_STATE.with(|state| {
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(FRAGMENT_SHADER, state.clone()).unwrap();
+
+            let (program, _) = Program::<TestVertex, TestUniforms, ()>::new(
+                &vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+            assert_eq!(Some(0), program.attribute_location("pos"));
+            assert_eq!(Some(1), program.attribute_location("color"));
+            assert_eq!(None, program.attribute_location("colour"));
+        })
+    }
+
+    #[test]
+    fn build_cached_program() {
+        CONTEXT_STATE.with(|state| {
+            let cache_dir = std::env::temp_dir()
+                .join(format!("gullery_program_cache_test_{}", std::process::id()));
+
+            // The first build populates the cache, and the second build loads from it.
+            for _ in 0..2 {
+                let (program, _) = Program::<TestVertex, TestUniforms, ()>::new_cached(
+                    &cache_dir,
+                    VERTEX_SHADER,
+                    None,
+                    FRAGMENT_SHADER,
+                    state.clone(),
+                )
+                .unwrap();
+                for loc in &program.uniform_locs {
+                    assert_ne!(-1, *loc);
+                }
+            }
+
+            std::fs::remove_dir_all(&cache_dir).unwrap();
+        })
+    }
+
+    const ARRAY_FRAGMENT_SHADER: &str = r#"
+        #version 330
+
+        uniform vec3 colors[2];
+        uniform ivec4 masks[2];
+
+        out vec4 frag_color;
+
+        void main() {
+            frag_color = vec4(colors[0] + colors[1], 1.0) * vec4(masks[0] & masks[1]);
+        }
+    "#;
+
+    #[derive(Clone, Copy)]
+    struct ArrayUniforms {
+        colors: [GLVec3<f32>; 2],
+        masks: [GLVec4<i32>; 2],
+    }
+
+    impl Uniforms for ArrayUniforms {
+        type ULC = [GLint; 2];
+        type Static = Self;
+        fn members<R>(mut reg: R)
+        where
+            R: UniformsMemberRegistry<Uniforms = ArrayUniforms>,
+        {
+            reg.add_member("colors", |t| t.colors);
+            reg.add_member("masks", |t| t.masks);
+        }
+    }
+
+    #[test]
+    fn upload_vector_array_uniforms() {
+        CONTEXT_STATE.with(|state| {
+            let vertex_shader = Shader::new(VERTEX_SHADER, state.clone()).unwrap();
+            let fragment_shader = Shader::new(ARRAY_FRAGMENT_SHADER, state.clone()).unwrap();
+
+            let (program, _) = Program::<TestVertex, ArrayUniforms, ()>::new(
+                &vertex_shader,
+                None,
+                &fragment_shader,
+            )
+            .unwrap();
+
+            let element_loc = |name: &str| unsafe {
+                let name = CString::new(name).unwrap();
+                state
+                    .gl
+                    .GetUniformLocation(program.handle().get(), name.as_ptr())
+            };
+            let get_color = |name| unsafe {
+                let mut value = [0.0; 3];
+                state.gl.GetUniformfv(
+                    program.handle().get(),
+                    element_loc(name),
+                    value.as_mut_ptr(),
+                );
+                value
+            };
+            let get_mask = |name| unsafe {
+                let mut value = [0; 4];
+                state.gl.GetUniformiv(
+                    program.handle().get(),
+                    element_loc(name),
+                    value.as_mut_ptr(),
+                );
+                value
+            };
+
+            let program_bind = unsafe { state.program_target.bind(&program) };
+            program_bind.upload_uniforms(&ArrayUniforms {
+                colors: [GLVec3::new(1.0, 0.5, 0.0), GLVec3::new(0.0, 0.5, 1.0)],
+                masks: [GLVec4::new(1, 2, 3, 4), GLVec4::new(5, 6, 7, 8)],
+            });
+            assert_eq!(0, unsafe { state.gl.GetError() });
+
+            // Each array member goes up in a single `glUniform*v` call, which has to write every
+            // component of every element.
+            assert_eq!([1.0, 0.5, 0.0], get_color("colors[0]"));
+            assert_eq!([0.0, 0.5, 1.0], get_color("colors[1]"));
+            assert_eq!([1, 2, 3, 4], get_mask("masks[0]"));
+            assert_eq!([5, 6, 7, 8], get_mask("masks[1]"));
+        })
+    }
+
+    #[derive(Clone, Copy)]
+    struct Int64Uniforms {
+        offset: GLVec3<f32>,
+        id: i64,
+    }
+
+    impl Uniforms for Int64Uniforms {
+        type ULC = [GLint; 2];
+        type Static = Self;
+        fn members<R>(mut reg: R)
+        where
+            R: UniformsMemberRegistry<Uniforms = Int64Uniforms>,
+        {
+            reg.add_member("offset", |t| t.offset);
+            reg.add_member("id", |t| t.id);
+        }
+    }
+
+    #[test]
+    fn find_int64_uniforms() {
+        assert!(uniform::has_int64_members::<Int64Uniforms>());
+        assert!(!uniform::has_int64_members::<TestUniforms>());
+        assert!(!uniform::has_int64_members::<ArrayUniforms>());
+        assert!(!uniform::has_int64_members::<()>());
+    }
+
+    #[test]
+    fn parse_declared_version() {
+        assert_eq!(330, declared_glsl_version(VERTEX_SHADER).unwrap());
+        assert_eq!(
+            450,
+            declared_glsl_version("// comment\n  #version 450 core\nvoid main() {}").unwrap()
+        );
+        assert_eq!(110, declared_glsl_version("void main() {}").unwrap());
+        assert_eq!(
+            110,
+            declared_glsl_version("#define FOO\n#version 330\nvoid main() {}").unwrap()
+        );
+        assert!(declared_glsl_version("#version core").is_err());
+    }
+
+    #[test]
+    fn parse_context_version() {
+        assert_eq!(Some(460), parse_glsl_version_string("4.60 NVIDIA"));
+        assert_eq!(Some(330), parse_glsl_version_string("3.30"));
+        assert_eq!(Some(150), parse_glsl_version_string("1.50 - Build 26.20"));
+        assert_eq!(Some(410), parse_glsl_version_string("4.1"));
+        assert_eq!(None, parse_glsl_version_string(""));
+    }
 }
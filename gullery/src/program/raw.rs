@@ -22,16 +22,16 @@ use crate::{
 
 use super::error::{LinkError, MismatchedTypeError, ProgramError, ProgramWarning};
 use crate::{
-    geometry::{TransparentType, TypeTag, TypeTagSingle},
+    geometry::{TypeTag, TypeTagSingle},
     texture::ImageUnits,
     uniform::{
         TextureUniformBinder, UniformLocContainer, UniformType, Uniforms, UniformsMemberRegistry,
     },
-    vertex::{Vertex, VertexMemberRegistry},
+    vertex::{Vertex, VertexAttribute, VertexMemberRegistry},
     ContextState, Handle,
 };
 
-use std::{cell::Cell, ffi::CString, marker::PhantomData, mem, ptr};
+use std::{any::Any, cell::Cell, ffi::CString, marker::PhantomData, mem, ptr};
 
 pub struct RawShader<S: ShaderStage> {
     handle: Handle,
@@ -94,6 +94,12 @@ pub enum VertexStage<V: Vertex> {
 pub enum GeometryStage {}
 /// Fragment processing shader stage.
 ///
+/// Before the program gets linked, each color attachment in `A` is bound to the fragment shader
+/// `out` variable with the same name, through `glBindFragDataLocation`. Outputs are numbered in the
+/// order the attachments are declared, which is also the order they're attached to the
+/// framebuffer, so the mapping doesn't depend on the driver and shaders don't need explicit
+/// `layout(location = N)` qualifiers.
+///
 /// See module-level documentation for more information.
 pub enum FragmentStage<A: Attachments> {
     #[doc(hidden)]
@@ -199,15 +205,7 @@ impl RawProgram {
                     post_link_hook(&program, gl, &mut warnings, &mut errors);
                 }
 
-                program.type_check_uniforms::<U>(gl, &mut errors);
-
-                match errors.len() {
-                    0 => Ok((program, warnings)),
-                    _ => {
-                        gl.DeleteProgram(program.handle.get());
-                        Err(ProgramError::MismatchedTypeError(errors))
-                    }
-                }
+                program.finish_link::<U>(gl, warnings, errors)
             } else {
                 let mut info_log_length = 0;
                 gl.GetProgramiv(
@@ -232,6 +230,112 @@ impl RawProgram {
         }
     }
 
+    /// Create a program from a binary previously retrieved with `binary`.
+    ///
+    /// Returns `None` if the driver rejected the binary, which can happen if the binary was built
+    /// by a different driver or driver version.
+    pub fn from_binary<V, U, A>(
+        format: GLenum,
+        binary: &[u8],
+        gl: &Gl,
+    ) -> Option<Result<(RawProgram, Vec<ProgramWarning>), ProgramError>>
+    where
+        V: Vertex,
+        U: Uniforms,
+        A: Attachments,
+    {
+        unsafe {
+            let program = RawProgram {
+                handle: Handle::new(gl.CreateProgram())
+                    .expect("Invalid handle returned from OpenGL"),
+                _sendsync_optout: PhantomData,
+            };
+
+            gl.ProgramBinary(
+                program.handle.get(),
+                format,
+                binary.as_ptr() as *const GLvoid,
+                binary.len() as GLsizei,
+            );
+
+            let mut is_linked = 0;
+            gl.GetProgramiv(program.handle.get(), gl::LINK_STATUS, &mut is_linked);
+            // Clear any error raised by an unrecognized binary format.
+            gl.GetError();
+
+            if is_linked == gl::TRUE as GLint {
+                let (mut warnings, mut errors) = (Vec::new(), Vec::new());
+                // Attribute and fragment data locations are stored in the binary, but we still want
+                // the same diagnostics we'd get from linking the shaders directly.
+                VertexStage::<V>::program_post_link_hook(&program, gl, &mut warnings, &mut errors);
+                FragmentStage::<A>::program_post_link_hook(
+                    &program,
+                    gl,
+                    &mut warnings,
+                    &mut errors,
+                );
+
+                Some(program.finish_link::<U>(gl, warnings, errors))
+            } else {
+                gl.DeleteProgram(program.handle.get());
+                None
+            }
+        }
+    }
+
+    /// Retrieve the program's binary representation and the format it's stored in.
+    ///
+    /// Returns `None` if the driver doesn't support retrieving program binaries. The program must
+    /// have been linked with `RawProgramShaderAttacher::binary_retrievable`.
+    pub fn binary(&self, gl: &Gl) -> Option<(GLenum, Vec<u8>)> {
+        unsafe {
+            let mut num_formats = 0;
+            gl.GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut num_formats);
+            if num_formats <= 0 {
+                return None;
+            }
+
+            let mut binary_len = 0;
+            gl.GetProgramiv(self.handle.get(), gl::PROGRAM_BINARY_LENGTH, &mut binary_len);
+            if binary_len <= 0 {
+                return None;
+            }
+
+            let mut binary = vec![0u8; binary_len as usize];
+            let (mut written_len, mut format) = (0, 0);
+            gl.GetProgramBinary(
+                self.handle.get(),
+                binary_len,
+                &mut written_len,
+                &mut format,
+                binary.as_mut_ptr() as *mut GLvoid,
+            );
+            if gl.GetError() != 0 {
+                return None;
+            }
+            binary.truncate(written_len as usize);
+
+            Some((format, binary))
+        }
+    }
+
+    unsafe fn finish_link<U: Uniforms>(
+        self,
+        gl: &Gl,
+        warnings: Vec<ProgramWarning>,
+        mut errors: Vec<MismatchedTypeError>,
+    ) -> Result<(RawProgram, Vec<ProgramWarning>), ProgramError> {
+        self.type_check_uniforms::<U>(gl, &mut errors);
+
+        match errors.len() {
+            0 => Ok((self, warnings)),
+            _ => {
+                gl.DeleteProgram(self.handle.get());
+                Err(ProgramError::MismatchedTypeError(errors))
+            }
+        }
+    }
+
     pub fn get_uniform_locations<U: Uniforms>(
         &self,
         gl: &Gl,
@@ -326,6 +430,44 @@ impl RawProgram {
         self.handle
     }
 
+    /// Returns `None` if the program has no active attribute named `name`.
+    pub fn attribute_location(&self, name: &str, gl: &Gl) -> Option<GLint> {
+        let name = CString::new(name).ok()?;
+        let location = unsafe { gl.GetAttribLocation(self.handle.get(), name.as_ptr()) };
+        match location {
+            -1 => None,
+            location => Some(location),
+        }
+    }
+
+    /// Returns `None` if the program has no active uniform block named `name`.
+    pub fn uniform_block_index(&self, name: &str, gl: &Gl) -> Option<GLuint> {
+        let name = CString::new(name).ok()?;
+        let index = unsafe { gl.GetUniformBlockIndex(self.handle.get(), name.as_ptr()) };
+        match index {
+            gl::INVALID_INDEX => None,
+            index => Some(index),
+        }
+    }
+
+    /// The minimum size, in bytes, of a buffer range bound to the block at `index`.
+    pub fn uniform_block_data_size(&self, index: GLuint, gl: &Gl) -> usize {
+        let mut size = 0;
+        unsafe {
+            gl.GetActiveUniformBlockiv(
+                self.handle.get(),
+                index,
+                gl::UNIFORM_BLOCK_DATA_SIZE,
+                &mut size,
+            );
+        }
+        size as usize
+    }
+
+    pub fn set_uniform_block_binding(&self, index: GLuint, binding: u32, gl: &Gl) {
+        unsafe { gl.UniformBlockBinding(self.handle.get(), index, binding) };
+    }
+
     pub unsafe fn delete(&mut self, state: &ContextState) {
         state.gl.DeleteProgram(self.handle.get());
         if state.program_target.0.bound_program.get() == Some(self.handle) {
@@ -343,6 +485,11 @@ impl RawProgramTarget {
         }
     }
 
+    #[inline]
+    pub fn invalidate(&self) {
+        self.bound_program.set(None);
+    }
+
     #[inline]
     pub unsafe fn bind<'a>(&'a self, program: &'a RawProgram, gl: &Gl) -> RawBoundProgram<'a> {
         if self.bound_program.get() != Some(program.handle) {
@@ -361,6 +508,36 @@ impl RawProgramTarget {
 }
 
 impl<'a, 'b> RawProgramShaderAttacher<'a, 'b> {
+    /// Hint to the driver that we're going to retrieve the program's binary after linking.
+    #[inline]
+    pub fn binary_retrievable(&mut self) {
+        unsafe {
+            self.gl.ProgramParameteri(
+                self.program.handle.get(),
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as GLint,
+            );
+        }
+    }
+
+    /// Capture the named vertex or geometry shader outputs into a single interleaved buffer when
+    /// transform feedback is active.
+    pub fn transform_feedback_varyings(&mut self, varyings: &[&str]) {
+        let varyings = varyings
+            .iter()
+            .map(|v| CString::new(*v).expect("varying name contains null byte"))
+            .collect::<Vec<_>>();
+        let varying_ptrs = varyings.iter().map(|v| v.as_ptr()).collect::<Vec<_>>();
+        unsafe {
+            self.gl.TransformFeedbackVaryings(
+                self.program.handle.get(),
+                varying_ptrs.len() as GLsizei,
+                varying_ptrs.as_ptr(),
+                gl::INTERLEAVED_ATTRIBS,
+            );
+        }
+    }
+
     #[inline]
     pub fn attach_shader<S: 'a + ShaderStage>(&mut self, shader: &'b RawShader<S>) {
         unsafe {
@@ -380,28 +557,46 @@ impl<'a> RawBoundProgram<'a> {
         &self,
         uniforms: &U,
         locs: &[GLint],
+        cache: Option<&mut Vec<Option<Box<dyn Any>>>>,
         image_units: &ImageUnits,
         gl: &Gl,
     ) {
         struct UniformsUploader<'a, U: Uniforms> {
             locs: &'a [GLint],
             loc_index: usize,
+            cache: Option<&'a mut Vec<Option<Box<dyn Any>>>>,
             unit: u32,
             image_units: &'a ImageUnits,
             gl: &'a Gl,
             uniforms: &'a U,
         }
+        impl<'a, U: Uniforms> UniformsUploader<'a, U> {
+            /// Store `value` in the member's cache slot, returning whether it differs from the
+            /// cached value.
+            fn update_cache<T: UniformType>(&mut self, value: &T) -> bool {
+                match self.cache {
+                    Some(ref mut cache) => {
+                        if cache.len() <= self.loc_index {
+                            cache.resize_with(self.loc_index + 1, || None);
+                        }
+                        value.update_cache(&mut cache[self.loc_index])
+                    }
+                    None => true,
+                }
+            }
+        }
         impl<'a, U: Uniforms> UniformsMemberRegistry for UniformsUploader<'a, U> {
             type Uniforms = U;
             fn add_member<T: UniformType>(&mut self, _: &str, get_member: fn(&U) -> T) {
                 let loc = self.locs[self.loc_index];
-                if loc != -1 {
+                let value = get_member(self.uniforms);
+                if self.update_cache(&value) && loc != -1 {
                     let mut binder = TextureUniformBinder {
                         image_units: &self.image_units,
                         unit: &mut self.unit,
                     };
                     unsafe {
-                        get_member(self.uniforms).upload(loc, &mut binder, self.gl);
+                        value.upload(loc, &mut binder, self.gl);
                     }
                 }
 
@@ -413,6 +608,7 @@ impl<'a> RawBoundProgram<'a> {
         U::members(UniformsUploader {
             locs,
             loc_index: 0,
+            cache,
             unit: 0,
             image_units,
             gl,
@@ -513,7 +709,7 @@ impl<'a, V: Vertex> VertexMemberRegistry for AttribTypeChecker<'a, V> {
     type Group = V;
     fn add_member<T>(&mut self, name: &str, _: fn(*const V) -> *const T)
     where
-        T: TransparentType,
+        T: VertexAttribute,
     {
         self.check_type(name, TypeTag::Single(T::prim_tag()));
     }
@@ -540,7 +736,7 @@ unsafe impl<V: Vertex> ShaderStage for VertexStage<V> {
             type Group = V;
             fn add_member<T>(&mut self, name: &str, _: fn(*const V) -> *const T)
             where
-                T: TransparentType,
+                T: VertexAttribute,
             {
                 // We can't just take ownership of the Vec<u8> to make it a CString, so we have to
                 // create a dummy buffer and swap it to self.cstr_bytes. At the end we swap it back.
@@ -602,8 +798,16 @@ unsafe impl<V: Vertex> ShaderStage for VertexStage<V> {
         //     _marker: PhantomData,
         // });
 
+        // Attributes placed after `V`'s locations are read from a VAO's instance buffer.
+        let vertex_slots = V::num_attrib_slots() as GLint;
         for (name, _) in vertex_attrib_types {
-            warnings.push(ProgramWarning::UnusedVertexAttribute(name));
+            let location = match CString::new(name.as_bytes()) {
+                Ok(cstr) => gl.GetAttribLocation(program.handle.get(), cstr.as_ptr()),
+                Err(_) => -1,
+            };
+            if location < vertex_slots {
+                warnings.push(ProgramWarning::UnusedVertexAttribute(name));
+            }
         }
     }
 }
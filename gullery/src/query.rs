@@ -0,0 +1,208 @@
+// Copyright 2018 Osspial
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Asynchronous queries for information about GPU work.
+//!
+//! A [`Query`] counts something about the draw calls made between [`begin`] and [`end`]. The
+//! result is computed by the GPU alongside the draw calls, so reading it immediately stalls until
+//! the GPU catches up. Use [`try_result`] to poll for the result without blocking.
+//!
+//! [`Query`]: ./struct.Query.html
+//! [`begin`]: ./struct.Query.html#method.begin
+//! [`end`]: ./struct.Query.html#method.end
+//! [`try_result`]: ./struct.Query.html#method.try_result
+
+use crate::{
+    gl::{self, types::*},
+    ContextState, GLObject, Handle,
+};
+
+use std::{cell::Cell, marker::PhantomData, rc::Rc};
+
+/// The value counted by a [`Query`].
+///
+/// [`Query`]: ./struct.Query.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum QueryType {
+    /// The number of samples that pass the depth and stencil tests.
+    SamplesPassed = gl::SAMPLES_PASSED,
+    /// Whether any samples pass the depth and stencil tests. The result is `0` or `1`.
+    AnySamplesPassed = gl::ANY_SAMPLES_PASSED,
+    /// The number of primitives emitted by the end of vertex processing, i.e. by the geometry
+    /// shader if the program has one. This counts primitives even if rasterization is discarded
+    /// or transform feedback isn't active.
+    PrimitivesGenerated = gl::PRIMITIVES_GENERATED,
+    /// The number of primitives written to the output buffer during transform feedback. If this is
+    /// less than [`PrimitivesGenerated`], the output buffer was too small to hold every primitive.
+    ///
+    /// [`PrimitivesGenerated`]: #variant.PrimitivesGenerated
+    TransformFeedbackPrimitivesWritten = gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN,
+    /// The time, in nanoseconds, the GPU took to execute the commands.
+    TimeElapsed = gl::TIME_ELAPSED,
+}
+
+/// A query object that counts a [`QueryType`] over a series of draw calls.
+///
+/// Only one query of each type may be active at a time.
+///
+/// [`QueryType`]: ./enum.QueryType.html
+pub struct Query {
+    handle: Handle,
+    query_type: QueryType,
+    active: Cell<bool>,
+    state: Rc<ContextState>,
+    _sendsync_optout: PhantomData<*const ()>,
+}
+
+impl Query {
+    #[inline]
+    pub fn new(query_type: QueryType, state: Rc<ContextState>) -> Query {
+        unsafe {
+            let mut handle = 0;
+            state.gl.GenQueries(1, &mut handle);
+            let handle = Handle::new(handle).expect("Invalid handle returned from OpenGL");
+
+            Query {
+                handle,
+                query_type,
+                active: Cell::new(false),
+                state,
+                _sendsync_optout: PhantomData,
+            }
+        }
+    }
+
+    #[inline]
+    pub fn query_type(&self) -> QueryType {
+        self.query_type
+    }
+
+    /// Start counting. Any result from a previous use of the query gets discarded.
+    ///
+    /// ## Panics
+    /// Panics if the query is already active, or if another query of the same type is active.
+    #[inline]
+    pub fn begin(&mut self) {
+        assert!(!self.active.get(), "query is already active");
+        unsafe {
+            let gl = &self.state.gl;
+            gl.BeginQuery(self.query_type.to_gl_enum(), self.handle.get());
+            assert_eq!(0, gl.GetError());
+        }
+        self.active.set(true);
+    }
+
+    /// Stop counting. The result becomes available once the GPU has executed every command issued
+    /// before this call.
+    ///
+    /// ## Panics
+    /// Panics if the query isn't active.
+    #[inline]
+    pub fn end(&mut self) {
+        assert!(self.active.get(), "query isn't active");
+        unsafe {
+            self.state.gl.EndQuery(self.query_type.to_gl_enum());
+        }
+        self.active.set(false);
+    }
+
+    /// Get the query's result, waiting for the GPU to finish computing it if necessary.
+    ///
+    /// ## Panics
+    /// Panics if the query is active.
+    #[inline]
+    pub fn result(&self) -> u64 {
+        assert!(
+            !self.active.get(),
+            "can't get the result of an active query"
+        );
+        let mut result = 0;
+        unsafe {
+            self.state
+                .gl
+                .GetQueryObjectui64v(self.handle.get(), gl::QUERY_RESULT, &mut result);
+        }
+        result
+    }
+
+    /// Get the query's result if the GPU has finished computing it, without blocking.
+    ///
+    /// ## Panics
+    /// Panics if the query is active.
+    #[inline]
+    pub fn try_result(&self) -> Option<u64> {
+        assert!(
+            !self.active.get(),
+            "can't get the result of an active query"
+        );
+        let mut available = 0;
+        unsafe {
+            self.state.gl.GetQueryObjectiv(
+                self.handle.get(),
+                gl::QUERY_RESULT_AVAILABLE,
+                &mut available,
+            );
+        }
+        match available as GLboolean {
+            gl::FALSE => None,
+            _ => Some(self.result()),
+        }
+    }
+}
+
+impl QueryType {
+    #[inline]
+    pub fn to_gl_enum(self) -> GLenum {
+        self as GLenum
+    }
+}
+
+impl GLObject for Query {
+    #[inline]
+    fn handle(&self) -> Handle {
+        self.handle
+    }
+    #[inline]
+    fn state(&self) -> &Rc<ContextState> {
+        &self.state
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        unsafe {
+            if self.active.get() {
+                self.state.gl.EndQuery(self.query_type.to_gl_enum());
+            }
+            self.state.gl.DeleteQueries(1, &self.handle.get());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper::CONTEXT_STATE;
+
+    #[test]
+    fn empty_query() {
+        CONTEXT_STATE.with(|state| {
+            let mut query = Query::new(QueryType::PrimitivesGenerated, state.clone());
+            query.begin();
+            query.end();
+            assert_eq!(0, query.result());
+        })
+    }
+}
@@ -89,12 +89,17 @@
 pub mod sample_parameters;
 mod raw;
 
-use crate::gl::{types::*, Gl};
+use crate::gl::{self, types::*, Gl};
 
 use self::{raw::*, sample_parameters::*};
+#[cfg(debug_assertions)]
+use crate::Diagnostic;
 use crate::{
-    geometry::{Dimension, D1, D2, D3},
-    image_format::{ConcreteImageFormat, ImageFormat},
+    geometry::{Dimension, GLVec2, GLVec3, NonNormalized, D1, D2, D3},
+    image_format::{
+        ConcreteImageFormat, DepthFormat, FormatAttributes, ImageFormat, ImageFormatRenderable,
+        Rgba,
+    },
     ContextState, GLObject, Handle,
 };
 
@@ -103,11 +108,17 @@ use crate::{
     uniform::{TextureUniformBinder, UniformType},
 };
 
-use std::{cell::Cell, error::Error, fmt, io, mem, rc::Rc};
+use std::{
+    cell::Cell,
+    error::Error,
+    fmt, io,
+    mem::{self, ManuallyDrop},
+    rc::Rc,
+};
 
 pub use self::raw::{
-    types, Dims, DimsSquare, Image, MipSelector, TextureType, TextureTypeBasicImage,
-    TextureTypeRenderable,
+    types, Dims, DimsSquare, Image, MipSelector, SubImageTarget, TextureType,
+    TextureTypeBasicImage, TextureTypeRenderable,
 };
 
 /// OpenGL Texture object.
@@ -120,11 +131,23 @@ where
 {
     raw: RawTexture<D, T>,
     state: Rc<ContextState>,
+    /// The sample parameters stored in the texture object itself.
+    sample_parameters: Cell<SampleParameters>,
 }
 
 /// Object that controls how the GPU reads from a [`Texture`].
 ///
+/// Changes to `sample_parameters`, whether made directly or through the setter methods, get
+/// uploaded the next time the sampler is used. Only the parameters that changed since the last
+/// upload get sent to OpenGL.
+///
+/// A bound sampler overrides every parameter stored in the texture object itself, which only apply
+/// when the texture is read without a sampler. See [`Texture::set_sample_parameters`].
 ///
+/// Channel swizzling isn't a sampler parameter; see [`Texture::swizzle_read`].
+///
+/// [`Texture::swizzle_read`]: ./struct.Texture.html#method.swizzle_read
+/// [`Texture::set_sample_parameters`]: ./struct.Texture.html#method.set_sample_parameters
 pub struct Sampler {
     pub sample_parameters: SampleParameters,
     old_sample_parameters: Cell<SampleParameters>,
@@ -143,6 +166,107 @@ where
     pub texture: &'a Texture<D, T>,
 }
 
+/// Tells the GPU to compare a reference value against a depth [`Texture`], as specified by a
+/// [`Sampler`] with [`compare`] set.
+///
+/// This is the type used to read shadow maps, and shows up in GLSL as a `sampler*Shadow` uniform.
+///
+/// [`compare`]: ./sample_parameters/struct.SampleParameters.html#structfield.compare
+pub struct ShadowSampledTexture<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: ?Sized + TextureType<D>,
+{
+    pub sampler: &'a Sampler,
+    pub texture: &'a Texture<D, T>,
+}
+
+/// Controls how OpenGL reads pixel data out of client memory when uploading an image.
+///
+/// By default, OpenGL assumes the uploaded image is tightly packed: each row is as long as the
+/// uploaded region's width, and each 3D slice is as tall as the uploaded region's height. Setting
+/// these parameters lets you upload a sub-region out of a larger source image without repacking
+/// the data on the CPU.
+///
+/// The parameters only apply to the upload they're passed to, and are restored to their defaults
+/// afterwards. Compressed formats don't support pixel store parameters.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PixelStore {
+    /// The width, in pixels, of a row in the source image. `0` means the row length is the width of
+    /// the uploaded region.
+    ///
+    /// Corresponds to `GL_UNPACK_ROW_LENGTH`.
+    pub row_length: u32,
+    /// The height, in pixels, of a single slice in a 3D source image. `0` means the slice height is
+    /// the height of the uploaded region.
+    ///
+    /// Corresponds to `GL_UNPACK_IMAGE_HEIGHT`.
+    pub image_height: u32,
+    /// The number of pixels skipped at the start of each row of the source image.
+    ///
+    /// Corresponds to `GL_UNPACK_SKIP_PIXELS`.
+    pub skip_pixels: u32,
+    /// The number of rows skipped at the start of each slice of the source image.
+    ///
+    /// Corresponds to `GL_UNPACK_SKIP_ROWS`.
+    pub skip_rows: u32,
+    /// The number of slices skipped at the start of a 3D source image.
+    ///
+    /// Corresponds to `GL_UNPACK_SKIP_IMAGES`.
+    pub skip_images: u32,
+    /// Whether to reverse the byte order of each multi-byte component while uploading. Use this
+    /// when the source image was produced with the opposite endianness from the host, such as
+    /// big-endian scientific or network-sourced data, to avoid swapping bytes on the CPU.
+    ///
+    /// Has no effect on formats with single-byte components.
+    ///
+    /// Corresponds to `GL_UNPACK_SWAP_BYTES`.
+    pub swap_bytes: bool,
+}
+
+/// Information about a texture mip level, as reported by OpenGL.
+///
+/// Returned by [`Texture::level_info`].
+///
+/// [`Texture::level_info`]: ./struct.Texture.html#method.level_info
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TexLevelInfo {
+    /// The internal format the driver actually used to store the level, which may differ from the
+    /// requested one.
+    ///
+    /// Corresponds to `GL_TEXTURE_INTERNAL_FORMAT`.
+    pub internal_format: GLenum,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// Whether the level is stored in a compressed format.
+    pub compressed: bool,
+    /// The size of the compressed image in bytes, or `0` if the level isn't compressed.
+    ///
+    /// Corresponds to `GL_TEXTURE_COMPRESSED_IMAGE_SIZE`.
+    pub compressed_size: usize,
+}
+
+/// How Gullery binds textures when it needs to modify them outside of a draw call.
+///
+/// Set with [`ContextState::set_texture_edit_strategy`].
+///
+/// [`ContextState::set_texture_edit_strategy`]: ../struct.ContextState.html#method.set_texture_edit_strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureEditStrategy {
+    /// Reserve the last image unit for texture modification, so shaders can use one less unit
+    /// than the context provides. This is the default, and works on every context.
+    ReserveLastUnit,
+    /// Modify textures through direct state access where possible, and let shaders use every
+    /// image unit.
+    ///
+    /// Operations that still need a bound texture, such as allocating images or modifying
+    /// cubemaps, temporarily bind to the last unit. Gullery rebinds texture uniforms on every
+    /// draw call, so this doesn't affect the textures seen by shaders. Requires OpenGL 4.5 or the
+    /// `ARB_direct_state_access` extension.
+    DirectStateAccess,
+}
+
 #[derive(Debug, Clone)]
 pub enum TextureCreateError<D, T>
 where
@@ -150,6 +274,44 @@ where
     T: TextureType<D>,
 {
     DimsExceedMax { requested: T::Dims, max: T::Dims },
+    IncompatibleInternalFormat { internal_format: GLenum },
+    /// The context doesn't support the format's compressed internal format, usually because the
+    /// driver lacks the extension that provides it. See [`supports_compressed_format`].
+    ///
+    /// [`supports_compressed_format`]: ../struct.ContextState.html#method.supports_compressed_format
+    UnsupportedCompressedFormat { internal_format: GLenum },
+    /// An image passed to [`Texture::with_images`] doesn't match the size of its mip level.
+    ///
+    /// Images don't carry their own dimensions, so the mismatch is reported as the number of
+    /// pixels (or compressed blocks) the level at `dims` needs, compared to the number given.
+    ///
+    /// [`Texture::with_images`]: ./struct.Texture.html#method.with_images
+    InvalidMipDimensions {
+        level: u8,
+        dims: T::Dims,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// Error returned by [`SampledTexture::new`] when a sampler can't be used to read a texture.
+///
+/// [`SampledTexture::new`]: ./struct.SampledTexture.html#method.new
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerMismatchError {
+    /// The texture has an integer format, but the sampler uses a filter that blends texels.
+    ///
+    /// OpenGL treats the texture as incomplete, so every sample returns zero.
+    IntegerFiltering {
+        filter_min: FilterMin,
+        filter_mag: FilterMag,
+    },
+    /// The sampler has [`compare`] set, which is only valid when reading a depth texture through
+    /// a [`ShadowSampledTexture`].
+    ///
+    /// [`compare`]: ./sample_parameters/struct.SampleParameters.html#structfield.compare
+    /// [`ShadowSampledTexture`]: ./struct.ShadowSampledTexture.html
+    CompareSampler,
 }
 
 impl<D, T> GLObject for Texture<D, T>
@@ -178,7 +340,10 @@ impl GLObject for Sampler {
     }
 }
 
-pub(crate) struct ImageUnits(RawImageUnits);
+pub(crate) struct ImageUnits {
+    raw: RawImageUnits,
+    edit_strategy: Cell<TextureEditStrategy>,
+}
 pub(crate) struct BoundTexture<'a, D, T>(RawBoundTexture<'a, D, T>)
 where
     D: Dimension<u32>,
@@ -190,7 +355,28 @@ where
     T: TextureType<D>,
     T::Format: ConcreteImageFormat,
 {
-    fn check_max_size(dims: T::Dims, state: &ContextState) -> Result<(), TextureCreateError<D, T>> {
+    fn from_raw(raw: RawTexture<D, T>, state: Rc<ContextState>) -> Texture<D, T> {
+        Texture {
+            raw,
+            state,
+            sample_parameters: Cell::new(SampleParameters::default()),
+        }
+    }
+
+    /// Check that the context can create a texture of this type with the given dimensions.
+    fn check_creatable(
+        dims: T::Dims,
+        state: &ContextState,
+    ) -> Result<(), TextureCreateError<D, T>> {
+        if let FormatAttributes::Compressed {
+            internal_format, ..
+        } = T::Format::FORMAT
+        {
+            if !state.supports_compressed_format(internal_format) {
+                return Err(TextureCreateError::UnsupportedCompressedFormat { internal_format });
+            }
+        }
+
         let max_size = T::max_size(&state);
         let (max_width, max_height, max_depth) =
             (max_size.width(), max_size.height(), max_size.depth());
@@ -206,12 +392,79 @@ where
         }
     }
 
+    /// Check that each image holds exactly enough data for the mip level it's assigned to.
+    fn check_mip_sizes<'a, I>(dims: T::Dims, images: &[I]) -> Result<(), TextureCreateError<D, T>>
+    where
+        T: TextureType<D, MipSelector = u8>,
+        I: Image<'a, D, T>,
+    {
+        for (level, image) in images.iter().enumerate() {
+            let level = level as u8;
+            let mip_dims = T::mip_dims(dims, level);
+            let expected = T::Format::blocks_for_dims(GLVec3::new(
+                mip_dims.width(),
+                mip_dims.height(),
+                mip_dims.depth(),
+            ));
+
+            let mut found = None;
+            image.variants(|_, data| {
+                if data.len() != expected && found.is_none() {
+                    found = Some(data.len());
+                }
+            });
+            if let Some(found) = found {
+                return Err(TextureCreateError::InvalidMipDimensions {
+                    level,
+                    dims: mip_dims,
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// In debug builds, report a mipmapped texture with non-power-of-two dimensions to the
+    /// diagnostic callback.
+    ///
+    /// Axes that don't shrink with the mip level, like an array texture's layer count, are ignored.
+    fn report_npot_mips(&self)
+    where
+        T: TextureType<D, MipSelector = u8>,
+    {
+        #[cfg(debug_assertions)]
+        {
+            let (dims, next) = (self.dims(), self.mip_dims(1));
+            let axes = [
+                (dims.width(), next.width()),
+                (dims.height(), next.height()),
+                (dims.depth(), next.depth()),
+            ];
+            let npot = axes
+                .iter()
+                .any(|&(size, mip_size)| size != mip_size && !size.is_power_of_two());
+            if npot {
+                self.state.report(|| Diagnostic::NpotMipmaps {
+                    texture: self.handle(),
+                    dims: (dims.width(), dims.height(), dims.depth()),
+                });
+            }
+        }
+    }
+
     /// Creates a new texture with the given number of mip levels, without uploading any data to the
     /// GPU.
     ///
     /// The exact data in the texture is unspecified, and shouldn't be relied on to be any specific
     /// value.
     ///
+    /// In debug builds, creating more than one mip level for a texture with non-power-of-two
+    /// dimensions reports [`Diagnostic::NpotMipmaps`].
+    ///
+    /// [`Diagnostic::NpotMipmaps`]: ../enum.Diagnostic.html#variant.NpotMipmaps
+    ///
     /// ## Panics
     /// Will panic if `mip_count == 0`, as a texture with no mip levels does not contain any data and
     /// thus cannot be used in any sensible way.
@@ -223,15 +476,15 @@ where
     where
         T: TextureType<D, MipSelector = u8, Samples = ()>,
     {
-        Self::check_max_size(dims, &state)?;
+        Self::check_creatable(dims, &state)?;
 
         let mut raw = RawTexture::new(dims, &state.gl);
         {
-            let last_unit = state.image_units.0.num_units() - 1;
+            let last_unit = state.image_units.raw.num_units() - 1;
             let mut bind = unsafe {
                 state
                     .image_units
-                    .0
+                    .raw
                     .bind_texture_mut(last_unit, &mut raw, &state.gl)
             };
             for level in mip_count.iter_less() {
@@ -243,7 +496,11 @@ where
             }
         }
 
-        Ok(Texture { raw, state })
+        let texture = Texture::from_raw(raw, state);
+        if mip_count > 1 {
+            texture.report_npot_mips();
+        }
+        Ok(texture)
     }
 
     /// Creates a new texture with the given images.
@@ -253,6 +510,13 @@ where
     /// minimal size on a given axis being `1`. For example, `[32x8, 16x4, 8x2, 4x1, 2x1, 1x1]`
     /// would be a valid set of image sizes, but `[16x8, 16x4, 8x2, 4x1, 2x1, 1x1]` would not.
     ///
+    /// Every image's size is checked before anything gets uploaded, and a mismatch returns
+    /// [`TextureCreateError::InvalidMipDimensions`]. In debug builds, passing more than one image
+    /// for a texture with non-power-of-two dimensions reports [`Diagnostic::NpotMipmaps`].
+    ///
+    /// [`TextureCreateError::InvalidMipDimensions`]: ./enum.TextureCreateError.html#variant.InvalidMipDimensions
+    /// [`Diagnostic::NpotMipmaps`]: ../enum.Diagnostic.html#variant.NpotMipmaps
+    ///
     /// ## Panics
     /// Will panic if no images were provided.
     pub fn with_images<'a, I, J>(
@@ -265,21 +529,23 @@ where
         I: Image<'a, D, T>,
         J: IntoIterator<Item = I>,
     {
-        Self::check_max_size(dims, &state)?;
+        Self::check_creatable(dims, &state)?;
+        let images: Vec<I> = image_mips.into_iter().collect();
+        Self::check_mip_sizes(dims, &images)?;
 
         let mut raw = RawTexture::new(dims, &state.gl);
         {
             // We use the last texture unit to make sure that a program never accidentally uses a texture bound
             // during modification. We should probably make sure programs never accidentally use that unit.
-            let last_unit = state.image_units.0.num_units() - 1;
+            let last_unit = state.image_units.raw.num_units() - 1;
             let mut bind = unsafe {
                 state
                     .image_units
-                    .0
+                    .raw
                     .bind_texture_mut(last_unit, &mut raw, &state.gl)
             };
 
-            for (level, image) in image_mips.into_iter().enumerate() {
+            for (level, image) in images.into_iter().enumerate() {
                 bind.alloc_image(level as u8, (), Some(image));
             }
 
@@ -288,7 +554,11 @@ where
             }
         }
 
-        Ok(Texture { raw, state })
+        let texture = Texture::from_raw(raw, state);
+        if texture.num_mips() > 1 {
+            texture.report_npot_mips();
+        }
+        Ok(texture)
     }
 
     /// Creates a new texture with a single image.
@@ -303,22 +573,108 @@ where
         T: TextureType<D, Samples = ()>,
         I: Image<'a, D, T>,
     {
-        Self::check_max_size(dims, &state)?;
+        Self::check_creatable(dims, &state)?;
+
+        let mut raw = RawTexture::new(dims, &state.gl);
+        {
+            let last_unit = state.image_units.raw.num_units() - 1;
+            let mut bind = unsafe {
+                state
+                    .image_units
+                    .raw
+                    .bind_texture_mut(last_unit, &mut raw, &state.gl)
+            };
+
+            bind.alloc_image(T::MipSelector::base(), (), Some(image));
+        }
+
+        Ok(Texture::from_raw(raw, state))
+    }
+
+    /// Creates a new texture with a single image, stored with the given OpenGL internal format
+    /// instead of the format type's own.
+    ///
+    /// The pixel format and type used to upload `image` still come from the image's format type,
+    /// so this can be used to, say, upload [`Rgb<u8>`] data into a `GL_RGB565` or `GL_SRGB8`
+    /// texture to save memory or get gamma-correct sampling. Every mip level allocated afterwards
+    /// uses the same internal format.
+    ///
+    /// The driver converts the image data to the internal format during upload, which can be slow
+    /// and lossy. Since Gullery's type-level format no longer describes the texture's contents, the
+    /// texture shouldn't be used as a render target or read back unless the two formats match.
+    ///
+    /// Returns an error if `internal_format` can't hold the image's data, as determined by
+    /// [`FormatAttributes::accepts_internal_format`].
+    ///
+    /// [`Rgb<u8>`]: ../image_format/struct.Rgb.html
+    /// [`FormatAttributes::accepts_internal_format`]: ../image_format/enum.FormatAttributes.html#method.accepts_internal_format
+    pub fn with_image_internal_format<'a, I>(
+        dims: T::Dims,
+        image: I,
+        internal_format: GLenum,
+        state: Rc<ContextState>,
+    ) -> Result<Texture<D, T>, TextureCreateError<D, T>>
+    where
+        T: TextureType<D, Samples = ()>,
+        I: Image<'a, D, T>,
+    {
+        Self::check_creatable(dims, &state)?;
+        if !T::Format::FORMAT.accepts_internal_format(internal_format) {
+            return Err(TextureCreateError::IncompatibleInternalFormat { internal_format });
+        }
 
         let mut raw = RawTexture::new(dims, &state.gl);
+        raw.set_internal_format(internal_format);
         {
-            let last_unit = state.image_units.0.num_units() - 1;
+            let last_unit = state.image_units.raw.num_units() - 1;
             let mut bind = unsafe {
                 state
                     .image_units
-                    .0
+                    .raw
                     .bind_texture_mut(last_unit, &mut raw, &state.gl)
             };
 
             bind.alloc_image(T::MipSelector::base(), (), Some(image));
         }
 
-        Ok(Texture { raw, state })
+        Ok(Texture::from_raw(raw, state))
+    }
+
+    /// Creates a new texture from sRGB-encoded `Rgba<u8>` data, stored as `GL_SRGB8_ALPHA8` so
+    /// that sampling the texture returns linear colors.
+    ///
+    /// Most 8-bit color images, such as PNGs and JPEGs, are sRGB-encoded. Storing them in an sRGB
+    /// texture makes the hardware decode each sample to linear color before filtering, which is
+    /// what lighting and blending math expects. The bytes are uploaded unchanged, so data that's
+    /// already linear shouldn't be uploaded this way. Each linear format has an sRGB counterpart:
+    ///
+    /// | Linear format | sRGB format | Internal format    |
+    /// |---------------|-------------|--------------------|
+    /// | `Rgb<u8>`     | [`SRgb`]    | `GL_SRGB8`         |
+    /// | `Rgba<u8>`    | [`SRgba`]   | `GL_SRGB8_ALPHA8`  |
+    ///
+    /// Using `SRgba` directly as the texture's format does the same thing, and also lets Gullery
+    /// know the texture holds sRGB data. This helper exists for data that's already typed as
+    /// `Rgba<u8>`.
+    ///
+    /// This only affects reads. [`RenderState::srgb`] controls the opposite direction, encoding
+    /// linear shader output to sRGB when writing into an sRGB render target. Enabling it doesn't
+    /// decode anything when sampling, and a texture that's sampled without sRGB storage returns
+    /// the encoded values as-is.
+    ///
+    /// [`SRgb`]: ../image_format/struct.SRgb.html
+    /// [`SRgba`]: ../image_format/struct.SRgba.html
+    /// [`RenderState::srgb`]: ../framebuffer/render_state/struct.RenderState.html#structfield.srgb
+    pub fn with_image_srgb<'a, I>(
+        dims: T::Dims,
+        image: I,
+        state: Rc<ContextState>,
+    ) -> Result<Texture<D, T>, TextureCreateError<D, T>>
+    where
+        T: TextureType<D, Samples = (), Format = Rgba<u8>>,
+        I: Image<'a, D, T>,
+    {
+        Self::with_image_internal_format(dims, image, gl::SRGB8_ALPHA8, state)
     }
 
     /// Initializes a texture to use a given number of samples when rendering.
@@ -332,22 +688,22 @@ where
     where
         T: TextureType<D, MipSelector = (), Samples = u8>,
     {
-        Self::check_max_size(dims, &state)?;
+        Self::check_creatable(dims, &state)?;
 
         let mut raw = RawTexture::new(dims, &state.gl);
         {
-            let last_unit = state.image_units.0.num_units() - 1;
+            let last_unit = state.image_units.raw.num_units() - 1;
             let mut bind = unsafe {
                 state
                     .image_units
-                    .0
+                    .raw
                     .bind_texture_mut(last_unit, &mut raw, &state.gl)
             };
 
             bind.alloc_image::<!>((), samples, None);
         }
 
-        Ok(Texture { raw, state })
+        Ok(Texture::from_raw(raw, state))
     }
 
     // You may notice that there's no function for creating a texture with both mipmaps and samples.
@@ -370,14 +726,295 @@ where
     ) where
         I: Image<'a, D, T>,
     {
-        let last_unit = self.state.image_units.0.num_units() - 1;
+        self.sub_image_pixel_store(mip_level, offset, sub_dims, image, PixelStore::default());
+    }
+
+    /// Uploads a sub-region of a larger source image, using `pixel_store` to describe where the
+    /// region lies in the source data.
+    ///
+    /// `image` must contain the entire source region described by `pixel_store`, not just the
+    /// pixels getting uploaded. For example, uploading the `8x8x8` box at `(4, 4, 4)` out of a
+    /// `32x32x32` volume looks like this:
+    ///
+    /// ```rust,no_run
+    /// # use std::rc::Rc;
+    /// use gullery::{
+    ///     # ContextState,
+    ///     image_format::Red,
+    ///     geometry::{D3, GLVec3},
+    ///     texture::{PixelStore, Texture},
+    /// };
+    /// # let context_state: Rc<ContextState> = panic!();
+    /// # let mut texture: Texture<D3, Red<u8>> = panic!();
+    /// let volume = vec![Red::new(0u8); 32 * 32 * 32];
+    /// texture.sub_image_pixel_store(
+    ///     0,
+    ///     GLVec3::new(0, 0, 0),
+    ///     GLVec3::new(8, 8, 8),
+    ///     &volume[..],
+    ///     PixelStore {
+    ///         row_length: 32,
+    ///         image_height: 32,
+    ///         skip_pixels: 4,
+    ///         skip_rows: 4,
+    ///         skip_images: 4,
+    ///         ..PixelStore::default()
+    ///     },
+    /// );
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `mip_level` hasn't been allocated, if `image` is too small to contain the described
+    /// source region, or if a non-default `pixel_store` is used with a compressed format.
+    #[inline]
+    pub fn sub_image_pixel_store<'a, I>(
+        &mut self,
+        mip_level: T::MipSelector,
+        offset: <T::Dims as Dims>::Offset,
+        sub_dims: T::Dims,
+        image: I,
+        pixel_store: PixelStore,
+    ) where
+        I: Image<'a, D, T>,
+    {
+        let last_unit = self.state.image_units.raw.num_units() - 1;
         let mut bind = unsafe {
+            self.state.image_units.raw.edit_texture_mut(
+                last_unit,
+                &mut self.raw,
+                &self.state.gl,
+                self.state.direct_state_access,
+            )
+        };
+        bind.sub_image(mip_level, offset, sub_dims, image, pixel_store);
+    }
+
+    /// Generates the texture's mipmap chain from the base mip level.
+    ///
+    /// Any mip levels that haven't been allocated yet get allocated, down to the smallest level
+    /// that doesn't have a zero-sized dimension. OpenGL can't generate mipmaps for integer textures,
+    /// so for integer formats this automatically falls back to [`generate_mipmaps_via_blit`]. In
+    /// debug builds, textures with non-power-of-two dimensions report [`Diagnostic::NpotMipmaps`].
+    ///
+    /// ## Panics
+    /// Panics if the format is an integer format and the texture is layered (a 3D, array, or
+    /// cubemap texture), as the blitting fallback only copies a single layer.
+    ///
+    /// [`generate_mipmaps_via_blit`]: ./struct.Texture.html#method.generate_mipmaps_via_blit
+    /// [`Diagnostic::NpotMipmaps`]: ../enum.Diagnostic.html#variant.NpotMipmaps
+    pub fn generate_mipmaps(&mut self)
+    where
+        T: TextureType<D, MipSelector = u8, Samples = ()>,
+        T::Format: ImageFormatRenderable,
+    {
+        self.generate_mipmaps_from(0);
+    }
+
+    /// Generates the mip levels after `base` from mip level `base`, leaving the levels up to and
+    /// including `base` untouched.
+    ///
+    /// This lets hand-authored high-resolution mip levels be combined with generated
+    /// lower-resolution ones. Mip level `base` must already have been allocated. Like
+    /// [`generate_mipmaps`], unallocated levels get allocated first, and integer formats fall
+    /// back to blitting.
+    ///
+    /// ## Panics
+    /// Panics if `base` is greater than or equal to the number of allocated mip levels, or if the
+    /// format is an integer format and the texture is layered (a 3D, array, or cubemap texture).
+    ///
+    /// [`generate_mipmaps`]: ./struct.Texture.html#method.generate_mipmaps
+    pub fn generate_mipmaps_from(&mut self, base: u8)
+    where
+        T: TextureType<D, MipSelector = u8, Samples = ()>,
+        T::Format: ImageFormatRenderable,
+    {
+        assert!(
+            base < self.num_mips(),
+            "base level {} exceeds the highest allocated mip level {}",
+            base,
+            self.num_mips().saturating_sub(1)
+        );
+        self.report_npot_mips();
+        if <T::Format as ImageFormat>::ScalarType::IS_INTEGER {
+            self.blit_mipmaps_from(base);
+            return;
+        }
+
+        let last_unit = self.state.image_units.raw.num_units() - 1;
+        unsafe {
             self.state
                 .image_units
-                .0
+                .raw
                 .bind_texture_mut(last_unit, &mut self.raw, &self.state.gl)
+                .alloc_mip_chain();
+        }
+        let mut bind = unsafe {
+            self.state.image_units.raw.edit_texture_mut(
+                last_unit,
+                &mut self.raw,
+                &self.state.gl,
+                self.state.direct_state_access,
+            )
         };
-        bind.sub_image(mip_level, offset, sub_dims, image);
+        // `glGenerateMipmap` always generates from `GL_TEXTURE_BASE_LEVEL`.
+        if base != 0 {
+            bind.set_base_level(base as GLint);
+        }
+        bind.generate_mipmaps();
+        if base != 0 {
+            bind.set_base_level(0);
+        }
+    }
+
+    /// Generates the texture's mipmap chain by blitting each mip level into the next-smallest level
+    /// with nearest-neighbor filtering.
+    ///
+    /// This works for formats `glGenerateMipmap` refuses, such as integer formats used for ID or
+    /// data textures. Like [`generate_mipmaps`], any unallocated mip levels get allocated first.
+    ///
+    /// [`generate_mipmaps`]: ./struct.Texture.html#method.generate_mipmaps
+    ///
+    /// ## Panics
+    /// Panics if the texture is layered (a 3D, array, or cubemap texture), as blitting only copies a
+    /// single layer.
+    pub fn generate_mipmaps_via_blit(&mut self)
+    where
+        T: TextureType<D, MipSelector = u8, Samples = ()>,
+        T::Format: ImageFormatRenderable,
+    {
+        self.blit_mipmaps_from(0);
+    }
+
+    fn blit_mipmaps_from(&mut self, base: u8)
+    where
+        T: TextureType<D, MipSelector = u8, Samples = ()>,
+        T::Format: ImageFormatRenderable,
+    {
+        match T::BIND_TARGET {
+            gl::TEXTURE_1D | gl::TEXTURE_2D => (),
+            _ => panic!("blit-based mipmap generation only supports non-layered textures"),
+        }
+
+        {
+            let last_unit = self.state.image_units.raw.num_units() - 1;
+            let mut bind = unsafe {
+                self.state
+                    .image_units
+                    .raw
+                    .bind_texture_mut(last_unit, &mut self.raw, &self.state.gl)
+            };
+            bind.alloc_mip_chain();
+        }
+
+        self.state
+            .framebuffer_targets
+            .blit_mip_chain(self, base, &self.state);
+    }
+}
+
+impl<C> Texture<D2, types::MultisampleTex<C>>
+where
+    C: ?Sized + ImageFormatRenderable,
+{
+    /// Get the position of the sample at `index` within a pixel.
+    ///
+    /// Both coordinates are in the range `0.0..=1.0`, with `(0.5, 0.5)` being the pixel's center.
+    /// The sample pattern is picked by the driver, so this is the only reliable way to get it when
+    /// writing a custom resolve or temporal anti-aliasing pass.
+    ///
+    /// ## Panics
+    /// Panics if `index` is greater than or equal to the texture's sample count.
+    #[inline]
+    pub fn sample_position(&self, index: u32) -> GLVec2<f32, NonNormalized> {
+        self.state
+            .framebuffer_targets
+            .sample_position(self, index, &self.state)
+    }
+}
+
+impl<C> Texture<D2, types::ArrayTex<C>>
+where
+    C: ConcreteImageFormat,
+{
+    /// Creates a new 2D array texture, with each image in `layers` assigned to an array layer.
+    ///
+    /// Every image must have the dimensions `dims`, and the texture gets `layers.len()` layers. Only
+    /// the base mip level gets allocated.
+    ///
+    /// ## Panics
+    /// Will panic if no images were provided, or if an image doesn't match `dims`.
+    pub fn with_layers<'a, I>(
+        dims: GLVec2<u32, NonNormalized>,
+        layers: &[I],
+        state: Rc<ContextState>,
+    ) -> Result<Texture<D2, types::ArrayTex<C>>, TextureCreateError<D2, types::ArrayTex<C>>>
+    where
+        I: Image<'a, D2, types::ArrayTex<C>>,
+    {
+        if layers.is_empty() {
+            panic!("layers must contain at least one image");
+        }
+
+        let array_dims = GLVec3::new(dims.x, dims.y, layers.len() as u32);
+        let mut texture = Texture::with_mip_count(array_dims, 1, state)?;
+        for (layer, image) in layers.iter().enumerate() {
+            texture.sub_image(
+                0,
+                GLVec3::new(0, 0, layer as u32),
+                GLVec3::new(dims.x, dims.y, 1),
+                *image,
+            );
+        }
+
+        Ok(texture)
+    }
+}
+
+impl<C> Texture<D2, types::CubemapTex<C>>
+where
+    C: ConcreteImageFormat,
+{
+    /// Creates a new cubemap texture from a separate mip chain for each face.
+    ///
+    /// `faces` is in OpenGL's face order: `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`. Each face's slice
+    /// holds that face's images from mip level `0` downwards, following the same size rules as
+    /// [`with_images`]. This is the layout most tools export prefiltered environment maps in;
+    /// if the images are already grouped by mip level, pass an iterator of [`CubemapImage`]s to
+    /// [`with_images`] instead.
+    ///
+    /// ## Panics
+    /// Will panic if the faces don't all have the same number of mip levels, if they have no mip
+    /// levels, or if an image has the wrong size for its mip level.
+    ///
+    /// [`with_images`]: #method.with_images
+    /// [`CubemapImage`]: ./types/struct.CubemapImage.html
+    pub fn with_face_mips(
+        dims: DimsSquare,
+        faces: [&[&[C]]; 6],
+        state: Rc<ContextState>,
+    ) -> Result<Texture<D2, types::CubemapTex<C>>, TextureCreateError<D2, types::CubemapTex<C>>> {
+        let mip_count = faces[0].len();
+        for (face, mips) in faces.iter().enumerate() {
+            if mips.len() != mip_count {
+                panic!(
+                    "cubemap face {} has {} mip levels, but face 0 has {}",
+                    face,
+                    mips.len(),
+                    mip_count
+                );
+            }
+        }
+
+        let [pos_x, neg_x, pos_y, neg_y, pos_z, neg_z] = faces;
+        let levels = (0..mip_count).map(|level| types::CubemapImage {
+            pos_x: pos_x[level],
+            neg_x: neg_x[level],
+            pos_y: pos_y[level],
+            neg_y: neg_y[level],
+            pos_z: pos_z[level],
+            neg_z: neg_z[level],
+        });
+        Texture::with_images(dims, levels, state)
     }
 }
 
@@ -398,6 +1035,116 @@ where
         self.raw.dims()
     }
 
+    /// The dimensions of the given mip level.
+    ///
+    /// Each level is half the size of the previous one on every axis, rounded down, with a minimum
+    /// size of `1`. Array layers and cubemap faces don't get halved. This doesn't check whether the
+    /// level has been allocated.
+    #[inline]
+    pub fn mip_dims(&self, level: T::MipSelector) -> T::Dims {
+        T::mip_dims(self.dims(), level)
+    }
+
+    /// Ask OpenGL for the format and size of the given mip level.
+    ///
+    /// This reflects what the driver actually allocated, so it can be used to check that a
+    /// requested internal format wasn't substituted for a different one, or that a compressed
+    /// upload has the expected size. Levels that haven't been allocated report a width, height,
+    /// and depth of `0`. For cubemaps, the positive X face's level gets queried.
+    pub fn level_info(&self, level: T::MipSelector) -> TexLevelInfo {
+        let last_unit = self.state.image_units.raw.num_units() - 1;
+        let bind = unsafe {
+            self.state
+                .image_units
+                .raw
+                .bind_texture(last_unit, &self.raw, &self.state.gl)
+        };
+        let level = level.to_glint();
+        let param = |pname| bind.level_parameter(level, pname);
+
+        let compressed = param(gl::TEXTURE_COMPRESSED) != 0;
+        TexLevelInfo {
+            internal_format: param(gl::TEXTURE_INTERNAL_FORMAT) as GLenum,
+            width: param(gl::TEXTURE_WIDTH) as u32,
+            height: param(gl::TEXTURE_HEIGHT) as u32,
+            depth: param(gl::TEXTURE_DEPTH) as u32,
+            compressed,
+            compressed_size: match compressed {
+                true => param(gl::TEXTURE_COMPRESSED_IMAGE_SIZE) as usize,
+                false => 0,
+            },
+        }
+    }
+
+    /// Unbind the texture from every image unit it's currently bound to.
+    ///
+    /// Gullery binds textures lazily and leaves them bound after a draw, so a texture can stay
+    /// attached to a unit long after the program that sampled it is done. Call this before
+    /// rendering into the texture to rule out a feedback loop, or to make sure no shader can
+    /// sample it anymore. The texture gets bound again as normal the next time it's used as a
+    /// uniform.
+    pub fn unbind_everywhere(&self) {
+        unsafe { self.raw.unbind(&self.state) }
+    }
+
+    /// Read back the compressed blocks of the given mip level.
+    ///
+    /// The data is in the level's compressed internal format, as reported by [`level_info`], and
+    /// can be written straight into a container like DDS or KTX, or re-uploaded later with
+    /// [`with_images`]. This works for textures the driver compressed at upload time, as well as
+    /// ones uploaded pre-compressed. Cubemap levels contain all six faces, in
+    /// `GL_TEXTURE_CUBE_MAP_*` enum order.
+    ///
+    /// ## Panics
+    /// Panics if the level isn't stored in a compressed format.
+    ///
+    /// [`level_info`]: #method.level_info
+    /// [`with_images`]: #method.with_images
+    pub fn get_compressed_image(&self, level: T::MipSelector) -> Vec<u8> {
+        let last_unit = self.state.image_units.raw.num_units() - 1;
+        let bind = unsafe {
+            self.state
+                .image_units
+                .raw
+                .bind_texture(last_unit, &self.raw, &self.state.gl)
+        };
+        let level = level.to_glint();
+        assert!(
+            bind.level_parameter(level, gl::TEXTURE_COMPRESSED) != 0,
+            "mip level {} isn't compressed",
+            level
+        );
+
+        let mut size = bind.level_parameter(level, gl::TEXTURE_COMPRESSED_IMAGE_SIZE) as usize;
+        if T::BIND_TARGET == gl::TEXTURE_CUBE_MAP {
+            size *= 6;
+        }
+        let mut data = vec![0; size];
+        unsafe { bind.get_compressed_image(level, &mut data) };
+        data
+    }
+
+    /// Ask OpenGL how many samples the texture has.
+    ///
+    /// Drivers round the count passed to [`with_sample_count`] up to one they support, so this
+    /// can be higher than requested. Use it to size resolves, or to tell a custom resolve shader
+    /// how many samples to read with `texelFetch`.
+    ///
+    /// [`with_sample_count`]: #method.with_sample_count
+    pub fn samples(&self) -> u8
+    where
+        T: TextureType<D, Samples = u8>,
+    {
+        let last_unit = self.state.image_units.raw.num_units() - 1;
+        let bind = unsafe {
+            self.state
+                .image_units
+                .raw
+                .bind_texture(last_unit, &self.raw, &self.state.gl)
+        };
+        bind.level_parameter(0, gl::TEXTURE_SAMPLES) as u8
+    }
+
     /// Sets the swizzle parameters for when a shader reads from a texture.
     ///
     /// Swizzling lets you change what values a shader reads from a particular texture channel without
@@ -449,18 +1196,101 @@ where
     /// FFF0 FFF5
     /// FFFA FFFF
     /// ```
+    ///
+    /// ## Per-use swizzling
+    /// The swizzle is part of the texture's state, so it applies to every read of the texture
+    /// regardless of which [`Sampler`] is used. OpenGL has no sampler-level swizzle. Texture views
+    /// (OpenGL 4.3) can alias a texture's storage with a different swizzle, but only for textures
+    /// allocated with immutable storage, which Gullery doesn't use. If the same image needs to be
+    /// read with different swizzles, either swizzle in the shader or upload it to a second texture.
+    ///
+    /// [`Sampler`]: ./struct.Sampler.html
     #[inline]
     pub fn swizzle_read(&mut self, r: Swizzle, g: Swizzle, b: Swizzle, a: Swizzle) {
-        let last_unit = self.state.image_units.0.num_units() - 1;
+        let last_unit = self.state.image_units.raw.num_units() - 1;
         let mut bind = unsafe {
-            self.state
-                .image_units
-                .0
-                .bind_texture_mut(last_unit, &mut self.raw, &self.state.gl)
+            self.state.image_units.raw.edit_texture_mut(
+                last_unit,
+                &mut self.raw,
+                &self.state.gl,
+                self.state.direct_state_access,
+            )
         };
         bind.swizzle_read(r, g, b, a);
     }
 
+    /// The sample parameters stored in the texture object.
+    ///
+    /// See [`set_sample_parameters`] for when these get used.
+    ///
+    /// [`set_sample_parameters`]: #method.set_sample_parameters
+    #[inline]
+    pub fn sample_parameters(&self) -> SampleParameters {
+        self.sample_parameters.get()
+    }
+
+    /// Set the sample parameters stored in the texture object, which control how the texture gets
+    /// read when it's passed to a shader without a [`Sampler`].
+    ///
+    /// When the texture is read through a [`SampledTexture`] or [`ShadowSampledTexture`], the
+    /// sampler's parameters are used instead and these are ignored entirely. Only the parameters
+    /// that changed since the last call get sent to OpenGL.
+    ///
+    /// [`Sampler`]: ./struct.Sampler.html
+    /// [`SampledTexture`]: ./struct.SampledTexture.html
+    /// [`ShadowSampledTexture`]: ./struct.ShadowSampledTexture.html
+    pub fn set_sample_parameters(&mut self, sample_parameters: SampleParameters) {
+        let last_unit = self.state.image_units.raw.num_units() - 1;
+        let bind = unsafe {
+            self.state.image_units.raw.edit_texture_mut(
+                last_unit,
+                &mut self.raw,
+                &self.state.gl,
+                self.state.direct_state_access,
+            )
+        };
+        bind.upload_parameters(
+            sample_parameters,
+            &self.sample_parameters,
+            self.state.max_anisotropy,
+        );
+    }
+
+    /// Restricts sampling to mip levels `0..=level`, without deallocating the levels past `level`.
+    ///
+    /// This is useful when streaming in textures: the lower-resolution mip levels can be uploaded
+    /// first, and the higher-resolution levels get exposed to sampling once they've been uploaded.
+    /// Note that mip levels must be allocated in order, starting with level `0`. Allocating another
+    /// mip level, e.g. through [`generate_mipmaps`], resets the maximum level to the last allocated
+    /// level.
+    ///
+    /// ## Panics
+    /// Panics if `level` is greater than or equal to the number of allocated mip levels.
+    ///
+    /// [`generate_mipmaps`]: ./struct.Texture.html#method.generate_mipmaps
+    #[inline]
+    pub fn set_max_level(&mut self, level: u8)
+    where
+        T: TextureType<D, MipSelector = u8>,
+    {
+        assert!(
+            level < self.num_mips(),
+            "max level {} exceeds the highest allocated mip level {}",
+            level,
+            self.num_mips().saturating_sub(1)
+        );
+        let last_unit = self.state.image_units.raw.num_units() - 1;
+        let mut bind = unsafe {
+            self.state.image_units.raw.edit_texture_mut(
+                last_unit,
+                &mut self.raw,
+                &self.state.gl,
+                self.state.direct_state_access,
+            )
+        };
+        bind.set_max_level(level as GLint);
+    }
+
     /// Returns a reference to this texture with the concrete texture type erased.
     ///
     /// Ideally this function wouldn't be necessary, and you'd be able to do this:
@@ -515,9 +1345,9 @@ where
             mem::size_of::<Texture<D, T>>(),
             mem::size_of::<Texture<D, T::Dyn>>()
         );
-        let tex = unsafe { mem::transmute_copy::<Texture<D, T>, Texture<D, T::Dyn>>(&self) };
-        mem::forget(self);
-        tex
+        // Wrap `self` before copying so it can't get dropped alongside the copy.
+        let this = ManuallyDrop::new(self);
+        unsafe { mem::transmute_copy::<Texture<D, T>, Texture<D, T::Dyn>>(&*this) }
     }
 
     /// Returns a reference to this texture with the concrete texture type erased, that's usable as
@@ -586,10 +1416,9 @@ where
             mem::size_of::<Texture<D, T>>(),
             mem::size_of::<Texture<D, T::DynRenderable>>()
         );
-        let tex =
-            unsafe { mem::transmute_copy::<Texture<D, T>, Texture<D, T::DynRenderable>>(&self) };
-        mem::forget(self);
-        tex
+        // Wrap `self` before copying so it can't get dropped alongside the copy.
+        let this = ManuallyDrop::new(self);
+        unsafe { mem::transmute_copy::<Texture<D, T>, Texture<D, T::DynRenderable>>(&*this) }
     }
 }
 
@@ -610,11 +1439,43 @@ impl Sampler {
         }
     }
 
+    /// Set the wrapping behavior on each of the texture's axes.
+    #[inline]
+    pub fn set_wrap(&mut self, texture_wrap: TextureWrap) {
+        self.sample_parameters.texture_wrap = texture_wrap;
+    }
+
+    /// Set the minification and magnification filters.
+    #[inline]
+    pub fn set_filter(&mut self, filter_min: FilterMin, filter_mag: FilterMag) {
+        self.sample_parameters.filter_min = filter_min;
+        self.sample_parameters.filter_mag = filter_mag;
+    }
+
+    /// Set the maximum number of samples used for anisotropic filtering.
+    ///
+    /// See [`SampleParameters::with_anisotropy`] for how this interacts with driver support.
+    ///
+    /// [`SampleParameters::with_anisotropy`]: ./sample_parameters/struct.SampleParameters.html#method.with_anisotropy
+    #[inline]
+    pub fn set_anisotropy_max(&mut self, anisotropy_max: f32) {
+        self.sample_parameters.anisotropy_max = anisotropy_max;
+    }
+
+    /// Set the LOD sampling parameters.
+    #[inline]
+    pub fn set_lod(&mut self, lod: Lod) {
+        self.sample_parameters.lod = lod;
+    }
+
     #[inline]
     pub(crate) fn upload_parameters(&self) {
         if self.sample_parameters != self.old_sample_parameters.get() {
-            (&self.state.gl, &self.raw)
-                .upload_parameters(self.sample_parameters, &self.old_sample_parameters);
+            (&self.state.gl, &self.raw).upload_parameters(
+                self.sample_parameters,
+                &self.old_sample_parameters,
+                self.state.max_anisotropy,
+            );
         }
     }
 }
@@ -622,7 +1483,34 @@ impl Sampler {
 impl ImageUnits {
     #[inline]
     pub fn new(gl: &Gl) -> ImageUnits {
-        ImageUnits(RawImageUnits::new(gl))
+        ImageUnits {
+            raw: RawImageUnits::new(gl),
+            edit_strategy: Cell::new(TextureEditStrategy::ReserveLastUnit),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn edit_strategy(&self) -> TextureEditStrategy {
+        self.edit_strategy.get()
+    }
+
+    #[inline]
+    pub(crate) fn set_edit_strategy(&self, edit_strategy: TextureEditStrategy) {
+        self.edit_strategy.set(edit_strategy);
+    }
+
+    /// The number of image units shaders can bind textures to.
+    #[inline]
+    pub(crate) fn usable_unit_count(&self) -> u32 {
+        match self.edit_strategy.get() {
+            TextureEditStrategy::ReserveLastUnit => self.raw.num_units() - 1,
+            TextureEditStrategy::DirectStateAccess => self.raw.num_units(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn invalidate(&self) {
+        self.raw.invalidate();
     }
 
     #[inline]
@@ -637,11 +1525,11 @@ impl ImageUnits {
         D: Dimension<u32>,
         T: ?Sized + TextureType<D>,
     {
-        let tex_bind = self.0.bind_texture(unit, &tex.raw, gl);
+        let tex_bind = self.raw.bind_texture(unit, &tex.raw, gl);
         match sampler {
-            Some(sampler) => self.0.bind_sampler(unit, &sampler.raw, gl),
+            Some(sampler) => self.raw.bind_sampler(unit, &sampler.raw, gl),
             None => {
-                self.0.unbind_sampler_from_unit(unit, gl);
+                self.raw.unbind_sampler_from_unit(unit, gl);
             }
         }
         BoundTexture(tex_bind)
@@ -726,6 +1614,42 @@ where
     }
 }
 
+macro_rules! shadow_texture_uniform {
+    ($(
+        impl ShadowSampledTexture<$d:ty, $texture_type:ty> = $tag_ident:ident;
+    )*) => {$(
+        unsafe impl<'a, C> UniformType for ShadowSampledTexture<'a, $d, $texture_type>
+            where C: ?Sized + ImageFormatRenderable<FormatType = DepthFormat>,
+                  $texture_type: TextureType<$d>
+        {
+            #[inline]
+            fn uniform_tag() -> TypeTag {
+                TypeTag::Single(TypeTagSingle::$tag_ident)
+            }
+            #[inline]
+            unsafe fn upload(&self, loc: GLint, binder: &mut TextureUniformBinder, gl: &Gl) {
+                debug_assert!(
+                    self.sampler.sample_parameters.compare.is_some(),
+                    "shadow textures must be sampled with a sampler that has `compare` set"
+                );
+                let unit = binder.bind(self.texture, Some(self.sampler), gl);
+                gl.Uniform1i(loc, unit as GLint);
+            }
+        }
+    )*};
+}
+
+shadow_texture_uniform! {
+    impl ShadowSampledTexture<D1, C> = Sampler1DShadow;
+    impl ShadowSampledTexture<D2, C> = Sampler2DShadow;
+
+    impl ShadowSampledTexture<D1, types::ArrayTex<C>> = Sampler1DArrayShadow;
+    impl ShadowSampledTexture<D2, types::ArrayTex<C>> = Sampler2DArrayShadow;
+
+    impl ShadowSampledTexture<D2, types::CubemapTex<C>> = SamplerCubeShadow;
+    impl ShadowSampledTexture<D2, types::RectTex<C>> = Sampler2DRectShadow;
+}
+
 impl<D, T> From<TextureCreateError<D, T>> for io::Error
 where
     D: Dimension<u32>,
@@ -755,6 +1679,84 @@ where
                 "requested dimensions {} exceed OpenGL implementation's maximum dimensions {}",
                 requested, max,
             ),
+            TextureCreateError::IncompatibleInternalFormat { internal_format } => write!(
+                f,
+                "internal format {:#06x} can't store the image's pixel format",
+                internal_format,
+            ),
+            TextureCreateError::UnsupportedCompressedFormat { internal_format } => write!(
+                f,
+                "compressed format {:#06x} isn't supported by the OpenGL implementation",
+                internal_format,
+            ),
+            TextureCreateError::InvalidMipDimensions {
+                level,
+                dims,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mip level {} with dimensions {} needs {} blocks of data, but was given {}",
+                level, dims, expected, found,
+            ),
+        }
+    }
+}
+
+impl<'a, D, T> SampledTexture<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: ?Sized + TextureType<D>,
+{
+    /// Pair a sampler with a texture, checking that the sampler's parameters are valid for the
+    /// texture's format.
+    ///
+    /// Invalid pairings aren't errors in OpenGL; they just make every sample return zero (or
+    /// undefined values), which is much harder to track down than an error at the point the pair
+    /// gets made.
+    pub fn new(
+        sampler: &'a Sampler,
+        texture: &'a Texture<D, T>,
+    ) -> Result<SampledTexture<'a, D, T>, SamplerMismatchError> {
+        let params = &sampler.sample_parameters;
+        if params.compare.is_some() {
+            return Err(SamplerMismatchError::CompareSampler);
+        }
+        if <T::Format as ImageFormat>::ScalarType::IS_INTEGER && !params.integer_compatible() {
+            return Err(SamplerMismatchError::IntegerFiltering {
+                filter_min: params.filter_min,
+                filter_mag: params.filter_mag,
+            });
+        }
+
+        Ok(SampledTexture { sampler, texture })
+    }
+}
+
+impl From<SamplerMismatchError> for io::Error {
+    fn from(err: SamplerMismatchError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl Error for SamplerMismatchError {}
+
+impl fmt::Display for SamplerMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            SamplerMismatchError::IntegerFiltering {
+                filter_min,
+                filter_mag,
+            } => write!(
+                f,
+                "integer textures must be sampled with `Nearest` or `NearestMipNearest` filters, \
+                 but the sampler uses {:?} and {:?}",
+                filter_min, filter_mag,
+            ),
+            SamplerMismatchError::CompareSampler => write!(
+                f,
+                "samplers with `compare` set can only be used through `ShadowSampledTexture`"
+            ),
         }
     }
 }
@@ -778,3 +1780,277 @@ where
     T: ?Sized + TextureType<D>,
 {
 }
+
+impl<'a, D, T> Clone for ShadowSampledTexture<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: ?Sized + TextureType<D>,
+{
+    fn clone(&self) -> Self {
+        ShadowSampledTexture {
+            sampler: self.sampler,
+            texture: self.texture,
+        }
+    }
+}
+
+impl<'a, D, T> Copy for ShadowSampledTexture<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: ?Sized + TextureType<D>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometry::{GLVec2, GLVec3},
+        image_format::{
+            compressed::{DXT1, RGTC},
+            Red, Rgb,
+        },
+        test_helper::CONTEXT_STATE,
+    };
+
+    unsafe fn bound_names(unit: u32, gl: &Gl) -> (GLuint, GLuint) {
+        let (mut texture, mut sampler) = (0, 0);
+        gl.ActiveTexture(gl::TEXTURE0 + unit);
+        gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut texture);
+        gl.GetIntegerv(gl::SAMPLER_BINDING, &mut sampler);
+        (texture as GLuint, sampler as GLuint)
+    }
+
+    #[test]
+    fn pixel_store_pixels_for_dims() {
+        let tight = PixelStore::default();
+        assert_eq!(4 * 3 * 2, tight.pixels_for_dims(GLVec3::new(4, 3, 2)));
+        assert_eq!(0, tight.pixels_for_dims(GLVec3::new(4, 0, 2)));
+
+        // A 4x3 region starting at (2, 1) of a 10-pixel-wide image. The last row only needs to
+        // extend to the end of the region, not the end of the source row.
+        let sub_rect = PixelStore {
+            row_length: 10,
+            skip_pixels: 2,
+            skip_rows: 1,
+            ..PixelStore::default()
+        };
+        assert_eq!(
+            3 * 10 + 2 + 4,
+            sub_rect.pixels_for_dims(GLVec3::new(4, 3, 1))
+        );
+
+        // Two 2x2 slices out of 2x5 slices, skipping the first slice.
+        let sub_volume = PixelStore {
+            image_height: 5,
+            skip_images: 1,
+            ..PixelStore::default()
+        };
+        assert_eq!(
+            2 * 2 * 5 + 2 + 2,
+            sub_volume.pixels_for_dims(GLVec3::new(2, 2, 2))
+        );
+    }
+
+    #[test]
+    fn lod_bias_uploads_to_texture_and_sampler() {
+        CONTEXT_STATE.with(|state| {
+            let gl = &state.gl;
+            let mut texture: Texture<D2, Rgba<u8>> =
+                Texture::with_mip_count(GLVec2::new(4, 4), 1, state.clone()).unwrap();
+            let mut sampler = Sampler::new(state.clone());
+            let mut sample_parameters = SampleParameters::default();
+            sample_parameters.lod.bias = 1.5;
+
+            texture.set_sample_parameters(sample_parameters);
+            sampler.sample_parameters = sample_parameters;
+            sampler.upload_parameters();
+
+            let (mut texture_bias, mut sampler_bias) = (0.0, 0.0);
+            unsafe {
+                state.image_units.bind(0, &texture, None, gl);
+                gl.GetTexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_LOD_BIAS, &mut texture_bias);
+                gl.GetSamplerParameterfv(
+                    sampler.handle().get(),
+                    gl::TEXTURE_LOD_BIAS,
+                    &mut sampler_bias,
+                );
+            }
+            assert_eq!(1.5, texture_bias);
+            assert_eq!(1.5, sampler_bias);
+            assert_eq!(sample_parameters, texture.sample_parameters());
+        })
+    }
+
+    #[test]
+    fn with_image_srgb_uses_srgb_storage() {
+        CONTEXT_STATE.with(|state| {
+            let image = [Rgba::new(255, 128, 0, 255); 4 * 4];
+            let texture: Texture<D2, Rgba<u8>> =
+                Texture::with_image_srgb(GLVec2::new(4, 4), &image[..], state.clone()).unwrap();
+            assert_eq!(gl::SRGB8_ALPHA8, texture.level_info(0).internal_format);
+        })
+    }
+
+    #[test]
+    fn with_image_internal_format_checks_compatibility() {
+        CONTEXT_STATE.with(|state| {
+            let image = [Rgb::new(255, 128, 0); 4 * 4];
+            let with_format = |internal_format| {
+                Texture::<D2, Rgb<u8>>::with_image_internal_format(
+                    GLVec2::new(4, 4),
+                    &image[..],
+                    internal_format,
+                    state.clone(),
+                )
+            };
+
+            let texture = with_format(gl::RGB16).unwrap();
+            assert_eq!(gl::RGB16, texture.level_info(0).internal_format);
+
+            // Normalized color data can't be stored as integer or depth data.
+            for &internal_format in &[gl::RGB8UI, gl::DEPTH_COMPONENT24] {
+                match with_format(internal_format) {
+                    Err(TextureCreateError::IncompatibleInternalFormat {
+                        internal_format: rejected,
+                    }) => assert_eq!(internal_format, rejected),
+                    Err(e) => panic!("unexpected error: {:?}", e),
+                    Ok(_) => panic!("{:#x} wasn't rejected", internal_format),
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn npot_mip_dims_round_down() {
+        CONTEXT_STATE.with(|state| {
+            let texture: Texture<D2, Rgba<u8>> =
+                Texture::with_mip_count(GLVec2::new(17, 5), 5, state.clone()).unwrap();
+            let expected = [(17, 5), (8, 2), (4, 1), (2, 1), (1, 1)];
+
+            for (level, &(width, height)) in expected.iter().enumerate() {
+                let level = level as u8;
+                assert_eq!(GLVec2::new(width, height), texture.mip_dims(level));
+
+                let info = texture.level_info(level);
+                assert_eq!((width, height), (info.width, info.height), "level {}", level);
+            }
+        })
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn npot_mipmaps_are_reported() {
+        use std::cell::RefCell;
+
+        CONTEXT_STATE.with(|state| {
+            let reports = Rc::new(RefCell::new(Vec::new()));
+            let callback_reports = reports.clone();
+            state.set_diagnostic_callback(Some(Box::new(move |diagnostic| {
+                callback_reports.borrow_mut().push(diagnostic.clone())
+            })));
+
+            let _: Texture<D2, Rgba<u8>> =
+                Texture::with_mip_count(GLVec2::new(16, 4), 5, state.clone()).unwrap();
+            let _: Texture<D2, Rgba<u8>> =
+                Texture::with_mip_count(GLVec2::new(17, 5), 1, state.clone()).unwrap();
+            assert!(reports.borrow().is_empty());
+
+            let texture: Texture<D2, Rgba<u8>> =
+                Texture::with_mip_count(GLVec2::new(17, 5), 5, state.clone()).unwrap();
+            state.set_diagnostic_callback(None);
+            assert_eq!(
+                vec![Diagnostic::NpotMipmaps {
+                    texture: texture.handle(),
+                    dims: (17, 5, 1),
+                }],
+                *reports.borrow()
+            );
+        })
+    }
+
+    #[test]
+    fn compressed_image_round_trips() {
+        CONTEXT_STATE.with(|state| {
+            let blocks: Vec<u8> = (0..16).collect();
+            let texture: Texture<D2, DXT1<Rgba>> = match Texture::with_image(
+                GLVec2::new(8, 4),
+                DXT1::from_raw_slice(&blocks),
+                state.clone(),
+            ) {
+                Ok(texture) => texture,
+                Err(TextureCreateError::UnsupportedCompressedFormat { .. }) => return,
+                Err(e) => panic!("{:?}", e),
+            };
+
+            assert_eq!(blocks, texture.get_compressed_image(0));
+        })
+    }
+
+    #[test]
+    fn rgtc_textures_are_always_creatable() {
+        CONTEXT_STATE.with(|state| {
+            // RGTC is core, and drivers aren't required to list it in GL_COMPRESSED_TEXTURE_FORMATS.
+            let blocks: Vec<u8> = (0..16).collect();
+            let texture: Texture<D2, RGTC<Red<u8>>> = Texture::with_image(
+                GLVec2::new(8, 4),
+                RGTC::from_raw_slice(&blocks),
+                state.clone(),
+            )
+            .unwrap();
+
+            assert_eq!(blocks, texture.get_compressed_image(0));
+            assert_eq!(gl::NO_ERROR, unsafe { state.gl.GetError() });
+        })
+    }
+
+    #[test]
+    fn unbind_everywhere_clears_every_unit() {
+        CONTEXT_STATE.with(|state| {
+            let gl = &state.gl;
+            let texture: Texture<D2, Rgba<u8>> =
+                Texture::with_mip_count(GLVec2::new(4, 4), 1, state.clone()).unwrap();
+            unsafe {
+                state.image_units.bind(0, &texture, None, gl);
+                state.image_units.bind(1, &texture, None, gl);
+                texture.unbind_everywhere();
+                // The cache has to forget the texture too, or this bind would get skipped.
+                state.image_units.bind(1, &texture, None, gl);
+
+                let bound = (bound_names(0, gl).0, bound_names(1, gl).0);
+                state.image_units.invalidate();
+                assert_eq!((0, texture.handle().get()), bound);
+            }
+        })
+    }
+
+    #[test]
+    fn deleted_names_are_not_treated_as_bound() {
+        CONTEXT_STATE.with(|state| {
+            let dims = GLVec2::new(4, 4);
+            let gl = &state.gl;
+            unsafe {
+                let old_texture: Texture<D2, Rgba<u8>> =
+                    Texture::with_mip_count(dims, 1, state.clone()).unwrap();
+                let old_sampler = Sampler::new(state.clone());
+                state.image_units.bind(0, &old_texture, Some(&old_sampler), gl);
+                drop(old_texture);
+                drop(old_sampler);
+
+                // OpenGL usually reuses the freed names here. If the image unit cache still held
+                // the old names, binding the new objects would be skipped.
+                let new_texture: Texture<D2, Rgba<u8>> =
+                    Texture::with_mip_count(dims, 1, state.clone()).unwrap();
+                let new_sampler = Sampler::new(state.clone());
+                state.image_units.bind(0, &new_texture, Some(&new_sampler), gl);
+
+                let bound = bound_names(0, gl);
+                state.image_units.invalidate();
+                assert_eq!(
+                    (new_texture.handle().get(), new_sampler.handle().get()),
+                    bound
+                );
+            }
+        })
+    }
+}
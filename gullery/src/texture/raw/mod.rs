@@ -35,7 +35,7 @@ use std::{
     ptr,
 };
 
-use super::sample_parameters::*;
+use super::{sample_parameters::*, PixelStore};
 
 #[repr(C)]
 pub struct RawTexture<D, T>
@@ -46,6 +46,7 @@ where
     handle: Handle,
     dims: T::Dims,
     num_mips: T::MipSelector,
+    internal_format: Option<GLenum>,
     _sendsync_optout: PhantomData<*const ()>,
 }
 
@@ -69,11 +70,16 @@ struct ImageUnit {
     sampler: Cell<Option<Handle>>,
 }
 
+/// Tracks the textures and samplers bound to each image unit.
+///
+/// Textures modified outside of draw calls get bound to the last unit, which is reserved for that
+/// purpose unless the context uses `TextureEditStrategy::DirectStateAccess`.
 pub struct RawImageUnits {
     /// The number of image units is never going to change, so storing this as `Box<[]>` means we
     /// don't have to deal with storing the capacity.
     image_units: Box<[ImageUnit]>,
-    active_unit: Cell<u32>,
+    /// `None` if the active unit is unknown.
+    active_unit: Cell<Option<u32>>,
 }
 
 #[repr(C)]
@@ -94,6 +100,19 @@ where
 {
     tex: &'a mut RawTexture<D, T>,
     gl: &'a Gl,
+    /// If `true`, the texture isn't actually bound and modifications go through direct state
+    /// access.
+    direct: bool,
+}
+
+/// The image that a sub-image upload gets written into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubImageTarget {
+    /// The image at the given bind point of the texture bound to the active image unit. This is
+    /// the texture's bind target, or the face's bind point for cubemaps.
+    Bound(GLenum),
+    /// The texture with the given name, written through direct state access.
+    Direct(GLuint),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -111,6 +130,14 @@ pub trait Dims: 'static + Copy {
     fn mip_dims(self, mip_level: GLint) -> Self;
 }
 
+/// The size of a texture axis at the given mip level, following OpenGL's
+/// `max(1, floor(size / 2^level))` rule.
+#[inline]
+fn mip_axis(size: u32, mip_level: GLint) -> u32 {
+    let halved = size.checked_shr(mip_level as u32).unwrap_or(0);
+    halved.max(size.min(1))
+}
+
 pub trait DimsArray: Dims {
     fn max_size_array(state: &ContextState) -> Self;
     fn mip_dims_array(self, mip_level: GLint) -> Self;
@@ -135,11 +162,12 @@ pub unsafe trait TextureType<D: Dimension<u32>>: 'static {
         samples: Self::Samples,
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat;
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -206,7 +234,8 @@ where
             RawTexture {
                 handle,
                 dims,
-                num_mips: mem::zeroed(),
+                num_mips: T::MipSelector::base(),
+                internal_format: None,
                 _sendsync_optout: PhantomData,
             }
         }
@@ -217,6 +246,14 @@ where
         self.dims
     }
 
+    /// Override the internal format used when allocating images, in place of the format type's
+    /// own internal format. Must be set before allocating any images, so that every mip level
+    /// shares the same internal format.
+    #[inline]
+    pub fn set_internal_format(&mut self, internal_format: GLenum) {
+        self.internal_format = Some(internal_format);
+    }
+
     #[inline]
     pub fn num_mips(&self) -> u8 {
         match mem::size_of::<T::MipSelector>() {
@@ -233,13 +270,18 @@ where
         self.handle
     }
 
-    pub unsafe fn delete(&mut self, state: &ContextState) {
-        state.gl.DeleteTextures(1, &self.handle.get());
+    /// Unbind the texture from every image unit it's bound to.
+    pub unsafe fn unbind(&self, state: &ContextState) {
         state
             .image_units
-            .0
+            .raw
             .unbind_texture(self.handle, T::BIND_TARGET, &state.gl);
     }
+
+    pub unsafe fn delete(&mut self, state: &ContextState) {
+        state.gl.DeleteTextures(1, &self.handle.get());
+        self.unbind(state);
+    }
 }
 
 impl RawSampler {
@@ -265,7 +307,7 @@ impl RawSampler {
         state.gl.DeleteSamplers(1, &self.handle.get());
         state
             .image_units
-            .0
+            .raw
             .unbind_sampler_from_all(self.handle, &state.gl);
     }
 }
@@ -282,7 +324,7 @@ impl RawImageUnits {
 
         RawImageUnits {
             image_units: vec![ImageUnit::default(); max_tex_units as usize].into_boxed_slice(),
-            active_unit: Cell::new(0),
+            active_unit: Cell::new(Some(0)),
         }
     }
 
@@ -291,6 +333,15 @@ impl RawImageUnits {
     //     self.active_unit.get()
     // }
 
+    /// Forget which textures and samplers are bound, and which unit is active.
+    pub fn invalidate(&self) {
+        for unit in self.image_units.iter() {
+            unit.texture.set(None);
+            unit.sampler.set(None);
+        }
+        self.active_unit.set(None);
+    }
+
     #[inline]
     pub fn num_units(&self) -> u32 {
         self.image_units.len() as u32
@@ -316,8 +367,8 @@ impl RawImageUnits {
             );
         }
 
-        if unit != self.active_unit.get() {
-            self.active_unit.set(unit);
+        if Some(unit) != self.active_unit.get() {
+            self.active_unit.set(Some(unit));
             gl.ActiveTexture(gl::TEXTURE0 + unit);
         }
 
@@ -342,7 +393,39 @@ impl RawImageUnits {
         T: 'a + ?Sized + TextureType<D>,
     {
         self.bind_texture(unit, tex, gl);
-        RawBoundTextureMut { tex, gl }
+        RawBoundTextureMut {
+            tex,
+            gl,
+            direct: false,
+        }
+    }
+
+    /// Get a handle for modifying `tex` without binding it, if `direct_state_access` is
+    /// supported. Otherwise, the texture gets bound to `unit` like in `bind_texture_mut`.
+    ///
+    /// Cubemaps always get bound, since direct state access addresses their faces as layers
+    /// instead of through the face bind points. Images can't be allocated through the returned
+    /// handle.
+    #[inline]
+    pub unsafe fn edit_texture_mut<'a, D, T>(
+        &'a self,
+        unit: u32,
+        tex: &'a mut RawTexture<D, T>,
+        gl: &'a Gl,
+        direct_state_access: bool,
+    ) -> RawBoundTextureMut<'a, D, T>
+    where
+        D: Dimension<u32>,
+        T: 'a + ?Sized + TextureType<D>,
+    {
+        match direct_state_access && T::BIND_TARGET != gl::TEXTURE_CUBE_MAP {
+            true => RawBoundTextureMut {
+                tex,
+                gl,
+                direct: true,
+            },
+            false => self.bind_texture_mut(unit, tex, gl),
+        }
     }
 
     #[inline]
@@ -358,17 +441,20 @@ impl RawImageUnits {
 
         let active_image_unit = &self.image_units[unit as usize];
         if active_image_unit.sampler.get() != Some(sampler.handle) {
+            active_image_unit.sampler.set(Some(sampler.handle));
             gl.BindSampler(unit, sampler.handle.get());
         }
     }
 
+    /// Clear every unit's cached reference to a deleted texture. OpenGL may hand the deleted name
+    /// out to the next texture, which would otherwise be treated as already bound.
     unsafe fn unbind_texture(&self, handle: Handle, target: GLuint, gl: &Gl) {
         for (unit_index, unit) in self.image_units.iter().enumerate() {
             if unit.texture.get() == Some(handle) {
                 gl.ActiveTexture(gl::TEXTURE0 + unit_index as GLuint);
                 gl.BindTexture(target, 0);
                 unit.texture.set(None);
-                self.active_unit.set(unit_index as GLuint);
+                self.active_unit.set(Some(unit_index as GLuint));
             }
         }
     }
@@ -399,8 +485,49 @@ where
     }
 }
 
+impl<'a, D, T> RawBoundTexture<'a, D, T>
+where
+    D: Dimension<u32>,
+    T: ?Sized + TextureType<D>,
+{
+    /// Query a `glGetTexLevelParameteriv` parameter. Cubemaps get queried through their positive X
+    /// face, since every face has the same level parameters.
+    pub fn level_parameter(&self, level: GLint, pname: GLenum) -> GLint {
+        let target = match T::BIND_TARGET {
+            gl::TEXTURE_CUBE_MAP => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            target => target,
+        };
+        let mut value = 0;
+        unsafe {
+            self.gl.GetTexLevelParameteriv(target, level, pname, &mut value);
+        }
+        value
+    }
+
+    /// Read a compressed mip level's blocks into `buf`, which must be exactly as large as the
+    /// level's `GL_TEXTURE_COMPRESSED_IMAGE_SIZE`. Cubemap faces get read in `GL_TEXTURE_CUBE_MAP_*`
+    /// enum order, one after the other, so `buf` must hold six faces.
+    pub unsafe fn get_compressed_image(&self, level: GLint, buf: &mut [u8]) {
+        match T::BIND_TARGET {
+            gl::TEXTURE_CUBE_MAP => {
+                let face_size = buf.len() / 6;
+                for (face, chunk) in buf.chunks_exact_mut(face_size).enumerate() {
+                    self.gl.GetCompressedTexImage(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum,
+                        level,
+                        chunk.as_mut_ptr() as *mut GLvoid,
+                    );
+                }
+            }
+            target => {
+                self.gl
+                    .GetCompressedTexImage(target, level, buf.as_mut_ptr() as *mut GLvoid)
+            }
+        }
+    }
+}
+
 pub trait ParameterUploader {
-    fn gl(&self) -> &Gl;
     fn float(&self, pname: GLenum, param: f32);
     fn int(&self, pname: GLenum, param: i32);
 
@@ -409,6 +536,7 @@ pub trait ParameterUploader {
         &self,
         parameters: SampleParameters,
         old_parameters_cell: &Cell<SampleParameters>,
+        max_anisotropy: Option<f32>,
     ) {
         let old_parameters = old_parameters_cell.get();
 
@@ -429,11 +557,9 @@ pub trait ParameterUploader {
         upload! {
             filter_min => self.int(gl::TEXTURE_MIN_FILTER, GLenum::from(filter_min) as i32);
             filter_mag => self.int(gl::TEXTURE_MAG_FILTER, GLenum::from(filter_mag) as i32);
-            anisotropy_max => {
-                let mut max_ma = 256.0; // arbitrarily large number
-                unsafe{ self.gl().GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_ma) };
-
-                self.float(gl::TEXTURE_MAX_ANISOTROPY_EXT, anisotropy_max.max(1.0).min(max_ma));
+            anisotropy_max => if let Some(max_anisotropy) = max_anisotropy {
+                let anisotropy_max = anisotropy_max.max(1.0).min(max_anisotropy);
+                self.float(gl::TEXTURE_MAX_ANISOTROPY, anisotropy_max);
             };
             texture_wrap => {
                 self.int(gl::TEXTURE_WRAP_S, GLenum::from(texture_wrap.s) as i32);
@@ -445,6 +571,13 @@ pub trait ParameterUploader {
                 self.float(gl::TEXTURE_MAX_LOD, lod.max);
                 self.float(gl::TEXTURE_LOD_BIAS, lod.bias);
             };
+            compare => match compare {
+                Some(func) => {
+                    self.int(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+                    self.int(gl::TEXTURE_COMPARE_FUNC, GLenum::from(func) as i32);
+                }
+                None => self.int(gl::TEXTURE_COMPARE_MODE, gl::NONE as i32),
+            };
         }
         old_parameters_cell.set(parameters);
     }
@@ -467,12 +600,32 @@ where
         unsafe {
             let mip_level = level.to_glint();
 
+            // Skipping a mip level would leave the texture incomplete, since `TEXTURE_MAX_LEVEL`
+            // would cover the skipped level.
+            assert!(
+                mip_level <= self.tex.num_mips() as GLint,
+                "attempted to allocate mip level {} before allocating mip level {}",
+                mip_level,
+                self.tex.num_mips()
+            );
             if mip_level >= self.tex.num_mips() as GLint {
                 self.tex.num_mips = level.try_increment();
                 self.gl
                     .TexParameteri(T::BIND_TARGET, gl::TEXTURE_MAX_LEVEL, mip_level);
             }
 
+            let mut format = T::Format::FORMAT;
+            if let (
+                Some(internal_override),
+                FormatAttributes::Uncompressed {
+                    ref mut internal_format,
+                    ..
+                },
+            ) = (self.tex.internal_format, &mut format)
+            {
+                *internal_format = internal_override;
+            }
+
             let mip_dims = T::mip_dims(self.tex.dims(), level);
             let num_blocks_expected = T::Format::blocks_for_dims(GLVec3::new(
                 mip_dims.width(),
@@ -493,6 +646,7 @@ where
                             samples,
                             data.as_ptr() as *const GLvoid,
                             data_bytes_len as GLsizei,
+                            format,
                         );
                     } else {
                         panic!(
@@ -510,6 +664,7 @@ where
                         samples,
                         ptr::null(),
                         0,
+                        format,
                     )
                 }),
             }
@@ -524,30 +679,54 @@ where
         offset: <T::Dims as Dims>::Offset,
         sub_dims: T::Dims,
         image: I,
+        pixel_store: PixelStore,
     ) where
         I: Image<'b, D, T>,
         T::Format: ConcreteImageFormat,
     {
         unsafe {
             let mip_level = level.to_glint();
+            assert!(
+                mip_level < self.tex.num_mips() as GLint,
+                "attempted to upload to unallocated mip level {}",
+                mip_level
+            );
 
-            if mip_level >= self.tex.num_mips() as GLint {
-                self.tex.num_mips = level.try_increment();
-                self.gl
-                    .TexParameteri(T::BIND_TARGET, gl::TEXTURE_MAX_LEVEL, mip_level);
-            }
+            let sub_dims_3d = GLVec3::new(sub_dims.width(), sub_dims.height(), sub_dims.depth());
+            let num_blocks_expected = match T::Format::FORMAT {
+                FormatAttributes::Uncompressed { .. } => pixel_store.pixels_for_dims(sub_dims_3d),
+                FormatAttributes::Compressed { .. } => {
+                    assert_eq!(
+                        PixelStore::default(),
+                        pixel_store,
+                        "pixel store parameters are not supported for compressed formats"
+                    );
+                    T::Format::blocks_for_dims(sub_dims_3d)
+                }
+            };
 
-            let dims = self.tex.dims();
-            let num_blocks_expected =
-                T::Format::blocks_for_dims(GLVec3::new(dims.width(), dims.height(), dims.depth()));
+            let set_pixel_store = pixel_store != PixelStore::default();
+            if set_pixel_store {
+                pixel_store.set_unpack(self.gl);
+            }
 
             image.variants(|image_bind, data| {
                 let num_blocks = data.len();
-                if num_blocks == num_blocks_expected {
+                // A non-default pixel store may only read part of the source image, so any extra
+                // trailing data is allowed.
+                let size_matches = match set_pixel_store {
+                    true => num_blocks >= num_blocks_expected,
+                    false => num_blocks == num_blocks_expected,
+                };
+                if size_matches {
                     let data_bytes_len = data.len() * mem::size_of::<T::Format>();
+                    let target = match self.direct {
+                        true => SubImageTarget::Direct(self.tex.handle.get()),
+                        false => SubImageTarget::Bound(image_bind),
+                    };
                     T::sub_image(
                         self.gl,
-                        image_bind,
+                        target,
                         offset,
                         sub_dims,
                         level,
@@ -562,9 +741,36 @@ where
                 }
             });
 
+            if set_pixel_store {
+                PixelStore::default().set_unpack(self.gl);
+            }
+
             assert_eq!(0, self.gl.GetError());
         }
     }
+
+    /// Allocate every mip level after the last allocated level, stopping after the level where
+    /// every mipmapped axis has shrunk to `1`.
+    pub fn alloc_mip_chain(&mut self)
+    where
+        T: TextureType<D, MipSelector = u8, Samples = ()>,
+        T::Format: ConcreteImageFormat,
+    {
+        let dims = self.tex.dims();
+        let level_size = |level| {
+            let mip_dims = T::mip_dims(dims, level);
+            (mip_dims.width(), mip_dims.height(), mip_dims.depth())
+        };
+
+        let mut level = self.tex.num_mips();
+        while level < u8::max_value() {
+            if level > 0 && level_size(level) == level_size(level - 1) {
+                break;
+            }
+            self.alloc_image::<!>(level, (), None);
+            level += 1;
+        }
+    }
 }
 
 impl<'a, D, T> RawBoundTextureMut<'a, D, T>
@@ -572,6 +778,47 @@ where
     D: Dimension<u32>,
     T: ?Sized + TextureType<D>,
 {
+    #[inline]
+    pub fn generate_mipmaps(&mut self) {
+        unsafe {
+            match self.direct {
+                true => self.gl.GenerateTextureMipmap(self.tex.handle.get()),
+                false => self.gl.GenerateMipmap(T::BIND_TARGET),
+            }
+            assert_eq!(0, self.gl.GetError());
+        }
+    }
+
+    #[inline]
+    pub fn set_base_level(&mut self, level: GLint) {
+        unsafe {
+            match self.direct {
+                true => {
+                    self.gl
+                        .TextureParameteri(self.tex.handle.get(), gl::TEXTURE_BASE_LEVEL, level)
+                }
+                false => self
+                    .gl
+                    .TexParameteri(T::BIND_TARGET, gl::TEXTURE_BASE_LEVEL, level),
+            }
+        }
+    }
+
+    #[inline]
+    pub fn set_max_level(&mut self, level: GLint) {
+        unsafe {
+            match self.direct {
+                true => {
+                    self.gl
+                        .TextureParameteri(self.tex.handle.get(), gl::TEXTURE_MAX_LEVEL, level)
+                }
+                false => self
+                    .gl
+                    .TexParameteri(T::BIND_TARGET, gl::TEXTURE_MAX_LEVEL, level),
+            }
+        }
+    }
+
     #[inline]
     pub fn swizzle_read(&mut self, r: Swizzle, g: Swizzle, b: Swizzle, a: Swizzle) {
         let mask = [
@@ -581,36 +828,47 @@ where
             GLenum::from(a) as i32,
         ];
         unsafe {
-            self.gl
-                .TexParameteriv(T::BIND_TARGET, gl::TEXTURE_SWIZZLE_RGBA, mask.as_ptr())
+            match self.direct {
+                true => self.gl.TextureParameteriv(
+                    self.tex.handle.get(),
+                    gl::TEXTURE_SWIZZLE_RGBA,
+                    mask.as_ptr(),
+                ),
+                false => {
+                    self.gl
+                        .TexParameteriv(T::BIND_TARGET, gl::TEXTURE_SWIZZLE_RGBA, mask.as_ptr())
+                }
+            }
         };
     }
 }
 
-impl<'a, D, T> ParameterUploader for RawBoundTexture<'a, D, T>
+impl<'a, D, T> ParameterUploader for RawBoundTextureMut<'a, D, T>
 where
     D: Dimension<u32>,
     T: ?Sized + TextureType<D>,
 {
-    #[inline]
-    fn gl(&self) -> &Gl {
-        self.gl
-    }
     #[inline]
     fn float(&self, pname: GLenum, param: f32) {
-        unsafe { self.gl.TexParameterf(T::BIND_TARGET, pname, param) };
+        unsafe {
+            match self.direct {
+                true => self.gl.TextureParameterf(self.tex.handle.get(), pname, param),
+                false => self.gl.TexParameterf(T::BIND_TARGET, pname, param),
+            }
+        }
     }
     #[inline]
     fn int(&self, pname: GLenum, param: i32) {
-        unsafe { self.gl.TexParameteri(T::BIND_TARGET, pname, param) };
+        unsafe {
+            match self.direct {
+                true => self.gl.TextureParameteri(self.tex.handle.get(), pname, param),
+                false => self.gl.TexParameteri(T::BIND_TARGET, pname, param),
+            }
+        }
     }
 }
 
 impl<'a> ParameterUploader for (&'a Gl, &'a RawSampler) {
-    #[inline]
-    fn gl(&self) -> &Gl {
-        self.0
-    }
     #[inline]
     fn float(&self, pname: GLenum, param: f32) {
         unsafe { self.0.SamplerParameterf(self.1.handle.get(), pname, param) };
@@ -670,6 +928,36 @@ impl MipSelector for u8 {
     }
 }
 
+impl PixelStore {
+    /// The number of pixels the source image needs to contain for an upload of `dims` pixels.
+    pub(crate) fn pixels_for_dims(&self, dims: GLVec3<u32, NonNormalized>) -> usize {
+        if dims.x == 0 || dims.y == 0 || dims.z == 0 {
+            return 0;
+        }
+        let row_length = match self.row_length {
+            0 => dims.x,
+            row_length => row_length,
+        } as usize;
+        let image_height = match self.image_height {
+            0 => dims.y,
+            image_height => image_height,
+        } as usize;
+
+        (self.skip_images + dims.z - 1) as usize * row_length * image_height
+            + (self.skip_rows + dims.y - 1) as usize * row_length
+            + (self.skip_pixels + dims.x) as usize
+    }
+
+    pub(crate) unsafe fn set_unpack(&self, gl: &Gl) {
+        gl.PixelStorei(gl::UNPACK_ROW_LENGTH, self.row_length as GLint);
+        gl.PixelStorei(gl::UNPACK_IMAGE_HEIGHT, self.image_height as GLint);
+        gl.PixelStorei(gl::UNPACK_SKIP_PIXELS, self.skip_pixels as GLint);
+        gl.PixelStorei(gl::UNPACK_SKIP_ROWS, self.skip_rows as GLint);
+        gl.PixelStorei(gl::UNPACK_SKIP_IMAGES, self.skip_images as GLint);
+        gl.PixelStorei(gl::UNPACK_SWAP_BYTES, self.swap_bytes as GLint);
+    }
+}
+
 impl DimsSquare {
     #[inline]
     pub fn new(side: u32) -> DimsSquare {
@@ -705,8 +993,7 @@ impl Dims for u32 {
         }
     }
     fn mip_dims(self, mip_level: GLint) -> Self {
-        let dim_divisor = 2u32.pow(mip_level as u32);
-        self / dim_divisor
+        mip_axis(self, mip_level)
     }
 }
 
@@ -737,8 +1024,10 @@ impl Dims for GLVec2<u32, NonNormalized> {
         }
     }
     fn mip_dims(self, mip_level: GLint) -> Self {
-        let dim_divisor = 2u32.pow(mip_level as u32);
-        GLVec2::new(self.width() / dim_divisor, self.height() / dim_divisor)
+        GLVec2::new(
+            mip_axis(self.width(), mip_level),
+            mip_axis(self.height(), mip_level),
+        )
     }
 }
 impl DimsArray for GLVec2<u32, NonNormalized> {
@@ -754,8 +1043,7 @@ impl DimsArray for GLVec2<u32, NonNormalized> {
         }
     }
     fn mip_dims_array(self, mip_level: GLint) -> Self {
-        let dim_divisor = 2u32.pow(mip_level as u32);
-        GLVec2::new(self.width() / dim_divisor, self.height())
+        GLVec2::new(mip_axis(self.width(), mip_level), self.height())
     }
 }
 
@@ -786,8 +1074,7 @@ impl Dims for DimsSquare {
         }
     }
     fn mip_dims(self, mip_level: GLint) -> Self {
-        let dim_divisor = 2u32.pow(mip_level as u32);
-        DimsSquare::new(self.side / dim_divisor)
+        DimsSquare::new(mip_axis(self.side, mip_level))
     }
 }
 impl Dims for GLVec3<u32, NonNormalized> {
@@ -817,11 +1104,10 @@ impl Dims for GLVec3<u32, NonNormalized> {
         }
     }
     fn mip_dims(self, mip_level: GLint) -> Self {
-        let dim_divisor = 2u32.pow(mip_level as u32);
         GLVec3::new(
-            self.width() / dim_divisor,
-            self.height() / dim_divisor,
-            self.depth() / dim_divisor,
+            mip_axis(self.width(), mip_level),
+            mip_axis(self.height(), mip_level),
+            mip_axis(self.depth(), mip_level),
         )
     }
 }
@@ -838,10 +1124,9 @@ impl DimsArray for GLVec3<u32, NonNormalized> {
         }
     }
     fn mip_dims_array(self, mip_level: GLint) -> Self {
-        let dim_divisor = 2u32.pow(mip_level as u32);
         GLVec3::new(
-            self.width() / dim_divisor,
-            self.height() / dim_divisor,
+            mip_axis(self.width(), mip_level),
+            mip_axis(self.height(), mip_level),
             self.depth(),
         )
     }
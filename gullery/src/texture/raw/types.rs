@@ -21,7 +21,7 @@
 use super::*;
 use crate::{
     geometry::Dimension,
-    geometry::{GLVec2, GLVec3, NonNormalized, ScalarType},
+    geometry::{GLVec2, GLVec3, NonNormalized, ScalarType, TransparentType, TypeTagSingle},
 };
 
 /// Stores multiple logical textures in a single texture object.
@@ -157,11 +157,31 @@ where
 /// for this type use *non-normalized* texture coordinate, not normalized texture coordinates. This
 /// means that sampling a `32x64` texture with `texture({rectTexture}, vec2(0.5, 0.5))` will sample
 /// halfway between the `(0, 0)` and `(1, 1)` pixels, instead of halfway into the texture.
+///
+/// Use [`RectTexCoord`] in vertex types to pass coordinates for this texture to the shader.
+///
+/// [`RectTexCoord`]: ./struct.RectTexCoord.html
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RectTex<C>(PhantomData<*const C>)
 where
     C: ?Sized + ImageFormat;
 
+/// A texture coordinate into a [`RectTex`], measured in pixels rather than in the `0.0..=1.0`
+/// range.
+///
+/// `(0.0, 0.0)` is the bottom-left corner of the texture and `(width, height)` is the top-right
+/// corner. Pixel centers sit on half-integer coordinates, so the center of the bottom-left pixel
+/// is `(0.5, 0.5)`.
+///
+/// This has the same layout as a `GLVec2<f32>` and shows up in GLSL as a `vec2`, so it can be used
+/// in vertex types in place of one. Its purpose is to make it clear, at the type level, that the
+/// coordinate isn't normalized.
+///
+/// [`RectTex`]: ./struct.RectTex.html
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct RectTexCoord(pub GLVec2<f32, NonNormalized>);
+
 // #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 // pub struct BufferTex;
 
@@ -230,6 +250,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -242,12 +263,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -258,7 +279,7 @@ where
     {
         sub_image_2d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -294,6 +315,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -306,12 +328,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -322,7 +344,7 @@ where
     {
         sub_image_3d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -368,6 +390,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -380,12 +403,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -396,7 +419,7 @@ where
     {
         sub_image_1d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -439,6 +462,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -451,12 +475,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -467,7 +491,7 @@ where
     {
         sub_image_2d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -510,6 +534,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -522,12 +547,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -538,7 +563,7 @@ where
     {
         sub_image_3d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -582,6 +607,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -595,12 +621,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -612,7 +638,7 @@ where
         let sub_dims = GLVec2::new(sub_dims.side, sub_dims.side);
         sub_image_2d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -633,6 +659,50 @@ where
 
 // TRAIT IMPLEMENTATIONS FOR RectTex
 
+impl RectTexCoord {
+    #[inline]
+    pub fn new(x: f32, y: f32) -> RectTexCoord {
+        RectTexCoord(GLVec2::new(x, y))
+    }
+
+    /// Convert a normalized texture coordinate into a pixel coordinate for a texture with the
+    /// given dimensions.
+    #[inline]
+    pub fn from_normalized(coord: GLVec2<f32>, dims: GLVec2<u32>) -> RectTexCoord {
+        RectTexCoord::new(coord.x * dims.x as f32, coord.y * dims.y as f32)
+    }
+
+    /// Convert this pixel coordinate into a normalized texture coordinate for a texture with the
+    /// given dimensions.
+    #[inline]
+    pub fn to_normalized(self, dims: GLVec2<u32>) -> GLVec2<f32> {
+        GLVec2::new(self.0.x / dims.x as f32, self.0.y / dims.y as f32)
+    }
+}
+
+impl From<GLVec2<f32>> for RectTexCoord {
+    #[inline]
+    fn from(coord: GLVec2<f32>) -> RectTexCoord {
+        RectTexCoord(coord)
+    }
+}
+
+impl From<RectTexCoord> for GLVec2<f32> {
+    #[inline]
+    fn from(coord: RectTexCoord) -> GLVec2<f32> {
+        coord.0
+    }
+}
+
+unsafe impl TransparentType for RectTexCoord {
+    type Normalization = NonNormalized;
+    type Scalar = f32;
+    #[inline]
+    fn prim_tag() -> TypeTagSingle {
+        GLVec2::<f32>::prim_tag()
+    }
+}
+
 unsafe impl<C> TextureTypeBasicImage<D2> for RectTex<C> where C: ?Sized + ImageFormat {}
 unsafe impl<C> TextureType<D2> for RectTex<C>
 where
@@ -660,6 +730,7 @@ where
         _samples: (),
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -672,12 +743,12 @@ where
             mip_level,
             data_ptr,
             data_len,
-            Self::Format::FORMAT,
+            format,
         );
     }
     unsafe fn sub_image(
         gl: &Gl,
-        image_bind: GLenum,
+        target: SubImageTarget,
         sub_offset: <Self::Dims as Dims>::Offset,
         sub_dims: Self::Dims,
         mip_level: Self::MipSelector,
@@ -688,7 +759,7 @@ where
     {
         sub_image_2d(
             gl,
-            image_bind,
+            target,
             sub_offset,
             sub_dims,
             mip_level.to_glint(),
@@ -747,6 +818,7 @@ where
         _: u8,
         _: *const GLvoid,
         _: GLsizei,
+        _: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
@@ -755,7 +827,7 @@ where
 
     unsafe fn sub_image(
         _: &Gl,
-        _: GLenum,
+        _: SubImageTarget,
         _: <Self::Dims as Dims>::Offset,
         _: Self::Dims,
         _: Self::MipSelector,
@@ -791,12 +863,13 @@ where
         samples: u8,
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
         assert_eq!(data_ptr, ptr::null());
         assert_eq!(data_len, 0);
-        match Self::Format::FORMAT {
+        match format {
             FormatAttributes::Uncompressed {
                 internal_format, ..
             } => gl.TexImage2DMultisample(
@@ -817,7 +890,7 @@ where
     /// The current implementation panics unconditionally.
     unsafe fn sub_image(
         _: &Gl,
-        _: GLenum,
+        _: SubImageTarget,
         _: <Self::Dims as Dims>::Offset,
         _: Self::Dims,
         _: Self::MipSelector,
@@ -866,12 +939,13 @@ where
         samples: u8,
         data_ptr: *const GLvoid,
         data_len: GLsizei,
+        format: FormatAttributes,
     ) where
         Self::Format: ConcreteImageFormat,
     {
         assert_eq!(data_ptr, ptr::null());
         assert_eq!(data_len, 0);
-        match Self::Format::FORMAT {
+        match format {
             FormatAttributes::Uncompressed {
                 internal_format, ..
             } => gl.TexImage3DMultisample(
@@ -893,7 +967,7 @@ where
     /// The current implementation panics unconditionally.
     unsafe fn sub_image(
         _: &Gl,
-        _: GLenum,
+        _: SubImageTarget,
         _: <Self::Dims as Dims>::Offset,
         _: Self::Dims,
         _: Self::MipSelector,
@@ -1048,7 +1122,7 @@ unsafe fn alloc_image_3d(
 
 unsafe fn sub_image_1d(
     gl: &Gl,
-    image_bind: GLenum,
+    target: SubImageTarget,
     sub_offset: u32,
     sub_dims: u32,
     mip_level: GLint,
@@ -1061,15 +1135,26 @@ unsafe fn sub_image_1d(
             pixel_format,
             pixel_type,
             ..
-        } => gl.TexSubImage1D(
-            image_bind,
-            mip_level,
-            sub_offset as GLint,
-            sub_dims as GLsizei,
-            pixel_format,
-            pixel_type,
-            data_ptr,
-        ),
+        } => match target {
+            SubImageTarget::Bound(image_bind) => gl.TexSubImage1D(
+                image_bind,
+                mip_level,
+                sub_offset as GLint,
+                sub_dims as GLsizei,
+                pixel_format,
+                pixel_type,
+                data_ptr,
+            ),
+            SubImageTarget::Direct(texture) => gl.TextureSubImage1D(
+                texture,
+                mip_level,
+                sub_offset as GLint,
+                sub_dims as GLsizei,
+                pixel_format,
+                pixel_type,
+                data_ptr,
+            ),
+        },
         FormatAttributes::Compressed {
             internal_format,
             block_dims,
@@ -1077,22 +1162,33 @@ unsafe fn sub_image_1d(
             assert_eq!(sub_offset % block_dims.width(), 0);
             assert_eq!(sub_dims.width() % block_dims.width(), 0);
 
-            gl.CompressedTexSubImage1D(
-                image_bind,
-                mip_level,
-                sub_offset as GLint,
-                sub_dims as GLsizei,
-                internal_format,
-                data_len,
-                data_ptr,
-            )
+            match target {
+                SubImageTarget::Bound(image_bind) => gl.CompressedTexSubImage1D(
+                    image_bind,
+                    mip_level,
+                    sub_offset as GLint,
+                    sub_dims as GLsizei,
+                    internal_format,
+                    data_len,
+                    data_ptr,
+                ),
+                SubImageTarget::Direct(texture) => gl.CompressedTextureSubImage1D(
+                    texture,
+                    mip_level,
+                    sub_offset as GLint,
+                    sub_dims as GLsizei,
+                    internal_format,
+                    data_len,
+                    data_ptr,
+                ),
+            }
         }
     }
 }
 
 unsafe fn sub_image_2d(
     gl: &Gl,
-    image_bind: GLenum,
+    target: SubImageTarget,
     sub_offset: GLVec2<u32, NonNormalized>,
     sub_dims: GLVec2<u32, NonNormalized>,
     mip_level: GLint,
@@ -1105,17 +1201,30 @@ unsafe fn sub_image_2d(
             pixel_format,
             pixel_type,
             ..
-        } => gl.TexSubImage2D(
-            image_bind,
-            mip_level,
-            sub_offset.x as GLint,
-            sub_offset.y as GLint,
-            sub_dims.width() as GLsizei,
-            sub_dims.height() as GLsizei,
-            pixel_format,
-            pixel_type,
-            data_ptr,
-        ),
+        } => match target {
+            SubImageTarget::Bound(image_bind) => gl.TexSubImage2D(
+                image_bind,
+                mip_level,
+                sub_offset.x as GLint,
+                sub_offset.y as GLint,
+                sub_dims.width() as GLsizei,
+                sub_dims.height() as GLsizei,
+                pixel_format,
+                pixel_type,
+                data_ptr,
+            ),
+            SubImageTarget::Direct(texture) => gl.TextureSubImage2D(
+                texture,
+                mip_level,
+                sub_offset.x as GLint,
+                sub_offset.y as GLint,
+                sub_dims.width() as GLsizei,
+                sub_dims.height() as GLsizei,
+                pixel_format,
+                pixel_type,
+                data_ptr,
+            ),
+        },
         FormatAttributes::Compressed {
             internal_format,
             block_dims,
@@ -1125,24 +1234,37 @@ unsafe fn sub_image_2d(
             assert_eq!(sub_dims.width() % block_dims.width(), 0);
             assert_eq!(sub_dims.height() % block_dims.height(), 0);
 
-            gl.CompressedTexSubImage2D(
-                image_bind,
-                mip_level,
-                sub_offset.x as GLint,
-                sub_offset.y as GLint,
-                sub_dims.width() as GLsizei,
-                sub_dims.height() as GLsizei,
-                internal_format,
-                data_len,
-                data_ptr,
-            )
+            match target {
+                SubImageTarget::Bound(image_bind) => gl.CompressedTexSubImage2D(
+                    image_bind,
+                    mip_level,
+                    sub_offset.x as GLint,
+                    sub_offset.y as GLint,
+                    sub_dims.width() as GLsizei,
+                    sub_dims.height() as GLsizei,
+                    internal_format,
+                    data_len,
+                    data_ptr,
+                ),
+                SubImageTarget::Direct(texture) => gl.CompressedTextureSubImage2D(
+                    texture,
+                    mip_level,
+                    sub_offset.x as GLint,
+                    sub_offset.y as GLint,
+                    sub_dims.width() as GLsizei,
+                    sub_dims.height() as GLsizei,
+                    internal_format,
+                    data_len,
+                    data_ptr,
+                ),
+            }
         }
     }
 }
 
 unsafe fn sub_image_3d(
     gl: &Gl,
-    image_bind: GLenum,
+    target: SubImageTarget,
     sub_offset: GLVec3<u32, NonNormalized>,
     sub_dims: GLVec3<u32, NonNormalized>,
     mip_level: GLint,
@@ -1155,19 +1277,34 @@ unsafe fn sub_image_3d(
             pixel_format,
             pixel_type,
             ..
-        } => gl.TexSubImage3D(
-            image_bind,
-            mip_level,
-            sub_offset.x as GLint,
-            sub_offset.y as GLint,
-            sub_offset.z as GLint,
-            sub_dims.width() as GLsizei,
-            sub_dims.height() as GLsizei,
-            sub_dims.depth() as GLsizei,
-            pixel_format,
-            pixel_type,
-            data_ptr,
-        ),
+        } => match target {
+            SubImageTarget::Bound(image_bind) => gl.TexSubImage3D(
+                image_bind,
+                mip_level,
+                sub_offset.x as GLint,
+                sub_offset.y as GLint,
+                sub_offset.z as GLint,
+                sub_dims.width() as GLsizei,
+                sub_dims.height() as GLsizei,
+                sub_dims.depth() as GLsizei,
+                pixel_format,
+                pixel_type,
+                data_ptr,
+            ),
+            SubImageTarget::Direct(texture) => gl.TextureSubImage3D(
+                texture,
+                mip_level,
+                sub_offset.x as GLint,
+                sub_offset.y as GLint,
+                sub_offset.z as GLint,
+                sub_dims.width() as GLsizei,
+                sub_dims.height() as GLsizei,
+                sub_dims.depth() as GLsizei,
+                pixel_format,
+                pixel_type,
+                data_ptr,
+            ),
+        },
         FormatAttributes::Compressed {
             internal_format,
             block_dims,
@@ -1179,19 +1316,34 @@ unsafe fn sub_image_3d(
             assert_eq!(sub_dims.height() % block_dims.height(), 0);
             assert_eq!(sub_dims.depth() % block_dims.depth(), 0);
 
-            gl.CompressedTexSubImage3D(
-                image_bind,
-                mip_level,
-                sub_offset.x as GLint,
-                sub_offset.y as GLint,
-                sub_offset.z as GLint,
-                sub_dims.width() as GLsizei,
-                sub_dims.height() as GLsizei,
-                sub_dims.depth() as GLsizei,
-                internal_format,
-                data_len,
-                data_ptr,
-            )
+            match target {
+                SubImageTarget::Bound(image_bind) => gl.CompressedTexSubImage3D(
+                    image_bind,
+                    mip_level,
+                    sub_offset.x as GLint,
+                    sub_offset.y as GLint,
+                    sub_offset.z as GLint,
+                    sub_dims.width() as GLsizei,
+                    sub_dims.height() as GLsizei,
+                    sub_dims.depth() as GLsizei,
+                    internal_format,
+                    data_len,
+                    data_ptr,
+                ),
+                SubImageTarget::Direct(texture) => gl.CompressedTextureSubImage3D(
+                    texture,
+                    mip_level,
+                    sub_offset.x as GLint,
+                    sub_offset.y as GLint,
+                    sub_offset.z as GLint,
+                    sub_dims.width() as GLsizei,
+                    sub_dims.height() as GLsizei,
+                    sub_dims.depth() as GLsizei,
+                    internal_format,
+                    data_len,
+                    data_ptr,
+                ),
+            }
         }
     }
 }
@@ -1,6 +1,9 @@
 //! Parameters used to control how a texture gets sampled by shaders.
 
-use crate::gl::{self, types::*};
+use crate::{
+    framebuffer::render_state::DepthStencilFunc,
+    gl::{self, types::*},
+};
 
 /// Value read from texture, when swizzled.
 #[repr(u8)]
@@ -119,6 +122,9 @@ pub struct TextureWrap {
 pub struct Lod {
     /// A bias that gets added to the LOD sample parameter. This gets applied before the range
     /// bounding from `min` and `max` gets applied.
+    ///
+    /// Corresponds to `GL_TEXTURE_LOD_BIAS`, which is set on the sampler object when the texture is
+    /// read through a `Sampler` and on the texture object otherwise.
     pub bias: f32,
     /// The minimum value for the LOD sample parameter.
     pub min: f32,
@@ -134,13 +140,26 @@ pub struct SampleParameters {
     /// The texture's magnification filter.
     pub filter_mag: FilterMag,
     /// The maximum number of samples used for [anisotropic filtering](https://en.wikipedia.org/wiki/Anisotropic_filtering).
+    ///
+    /// Defaults to `1.0`, which disables anisotropic filtering. See [`with_anisotropy`] for how
+    /// this gets clamped.
+    ///
+    /// [`with_anisotropy`]: #method.with_anisotropy
     pub anisotropy_max: f32,
     /// The texture's wrapping behavior on each axis.
     pub texture_wrap: TextureWrap,
     /// The texture's LOD sampling parameters.
     pub lod: Lod,
+    /// The function used to compare depth textures against a reference value, or `None` to read
+    /// depth values directly.
+    ///
+    /// When this is `Some`, sampling returns the result of comparing the reference value against
+    /// the texture's depth value, which is used for shadow mapping. The texture must be read
+    /// through a `sampler*Shadow` GLSL uniform.
+    ///
+    /// Corresponds to `GL_TEXTURE_COMPARE_MODE` and `GL_TEXTURE_COMPARE_FUNC`.
+    pub compare: Option<DepthStencilFunc>,
     // pub border_color: Option<Rgba<f32>>,
-    // TODO: GL_TEXTURE_COMPARE_MODE
 }
 
 impl Default for FilterMin {
@@ -150,6 +169,85 @@ impl Default for FilterMin {
     }
 }
 
+impl FilterMin {
+    /// Whether the filter blends together multiple texels or mipmaps.
+    ///
+    /// Integer textures can't be sampled with blending filters.
+    #[inline]
+    pub fn is_blending(self) -> bool {
+        match self {
+            FilterMin::Nearest | FilterMin::NearestMipNearest => false,
+            FilterMin::Linear
+            | FilterMin::LinearMipNearest
+            | FilterMin::NearestMipLinear
+            | FilterMin::LinearMipLinear => true,
+        }
+    }
+}
+
+impl FilterMag {
+    /// Whether the filter blends together multiple texels.
+    ///
+    /// Integer textures can't be sampled with blending filters.
+    #[inline]
+    pub fn is_blending(self) -> bool {
+        self == FilterMag::Linear
+    }
+}
+
+impl SampleParameters {
+    /// Whether these parameters can be used to sample an integer texture.
+    ///
+    /// OpenGL requires integer textures to be sampled with non-blending filters. If they aren't,
+    /// the texture is treated as incomplete and every sample returns zero.
+    #[inline]
+    pub fn integer_compatible(&self) -> bool {
+        !self.filter_min.is_blending() && !self.filter_mag.is_blending()
+    }
+
+    /// Parameters for crisp pixel art that still uses mipmaps to reduce shimmering when minified.
+    ///
+    /// Magnification uses `Nearest` filtering so texels stay sharp-edged, minification uses
+    /// `NearestMipNearest`, and every axis clamps to the edge so neighboring sprites in an atlas
+    /// don't bleed into each other.
+    ///
+    /// The filters are independent, so other combinations are valid too. `Nearest` magnification
+    /// with `LinearMipLinear` minification keeps close-up texels sharp while smoothing distant
+    /// ones, at the cost of some blurriness in between. A negative [`lod`] bias pushes sampling
+    /// towards the larger mip levels, which keeps minified art crisper but brings back some
+    /// shimmering; a positive bias does the opposite.
+    ///
+    /// [`lod`]: #structfield.lod
+    #[inline]
+    pub fn pixel_art() -> SampleParameters {
+        SampleParameters {
+            filter_min: FilterMin::NearestMipNearest,
+            filter_mag: FilterMag::Nearest,
+            texture_wrap: TextureWrap {
+                s: TextureWrapAxis::ClampToEdge,
+                t: TextureWrapAxis::ClampToEdge,
+                r: TextureWrapAxis::ClampToEdge,
+            },
+            ..SampleParameters::default()
+        }
+    }
+
+    /// Request anisotropic filtering with up to `level` samples.
+    ///
+    /// The level gets clamped to [`ContextState::max_anisotropy`] when the parameters are
+    /// uploaded. Contexts without anisotropic filtering support ignore the request entirely, so
+    /// this is safe to use unconditionally.
+    ///
+    /// [`ContextState::max_anisotropy`]: ../../struct.ContextState.html#method.max_anisotropy
+    #[inline]
+    pub fn with_anisotropy(self, level: f32) -> SampleParameters {
+        SampleParameters {
+            anisotropy_max: level,
+            ..self
+        }
+    }
+}
+
 impl Default for FilterMag {
     #[inline(always)]
     fn default() -> FilterMag {
@@ -173,6 +271,7 @@ impl Default for SampleParameters {
             lod: Lod::default(),
             anisotropy_max: 1.0,
             texture_wrap: TextureWrap::default(),
+            compare: None,
         }
     }
 }
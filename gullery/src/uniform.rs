@@ -17,10 +17,51 @@ use crate::gl::{self, types::*, Gl};
 use crate::{
     geometry::Dimension,
     geometry::*,
-    image_format::{Red, Rg, Rgb, Rgba},
+    image_format::{ImageFormat, Red, Rg, Rgb, Rgba},
     texture::{ImageUnits, Sampler, Texture, TextureType},
+    ContextState,
 };
-use std::marker::PhantomData;
+use std::{any::Any, marker::PhantomData};
+
+/// The maximum number of scalar components the default uniform block of a vertex shader can
+/// hold. Each `float`, `int`, or `bool` takes one component, a `vec4` takes four, and a `mat4`
+/// takes sixteen, though drivers may round smaller types up when packing.
+///
+/// Corresponds to `GL_MAX_VERTEX_UNIFORM_COMPONENTS`. OpenGL guarantees this is at least 1024.
+/// Programs whose uniforms exceed it fail to link.
+pub fn max_vertex_uniform_components(state: &ContextState) -> usize {
+    let mut max = 0;
+    unsafe {
+        state.gl.GetIntegerv(gl::MAX_VERTEX_UNIFORM_COMPONENTS, &mut max);
+    }
+    max as usize
+}
+
+/// The maximum number of scalar components the default uniform block of a fragment shader can
+/// hold, counted the same way as [`max_vertex_uniform_components`].
+///
+/// Corresponds to `GL_MAX_FRAGMENT_UNIFORM_COMPONENTS`. OpenGL guarantees this is at least 1024.
+///
+/// [`max_vertex_uniform_components`]: ./fn.max_vertex_uniform_components.html
+pub fn max_fragment_uniform_components(state: &ContextState) -> usize {
+    let mut max = 0;
+    unsafe {
+        state.gl.GetIntegerv(gl::MAX_FRAGMENT_UNIFORM_COMPONENTS, &mut max);
+    }
+    max as usize
+}
+
+/// The maximum size, in bytes, of a single uniform block. Uniform sets too large for the default
+/// uniform block usually still fit in a uniform buffer.
+///
+/// Corresponds to `GL_MAX_UNIFORM_BLOCK_SIZE`. OpenGL guarantees this is at least 16384.
+pub fn max_uniform_block_size(state: &ContextState) -> usize {
+    let mut max = 0;
+    unsafe {
+        state.gl.GetIntegerv(gl::MAX_UNIFORM_BLOCK_SIZE, &mut max);
+    }
+    max as usize
+}
 
 pub struct TextureUniformBinder<'a> {
     pub(crate) image_units: &'a ImageUnits,
@@ -39,11 +80,26 @@ impl<'a> TextureUniformBinder<'a> {
         T: ?Sized + TextureType<D>,
     {
         if let Some(sampler) = sampler {
+            debug_assert!(
+                !<T::Format as ImageFormat>::ScalarType::IS_INTEGER
+                    || sampler.sample_parameters.integer_compatible(),
+                "integer textures must be sampled with `Nearest` or `NearestMipNearest` filters; \
+                 sampler uses {:?} and {:?}, which would cause every sample to return zero",
+                sampler.sample_parameters.filter_min,
+                sampler.sample_parameters.filter_mag,
+            );
             sampler.upload_parameters();
         } /* else {
               tex.upload_parameters();
           }*/
 
+        let usable_units = self.image_units.usable_unit_count();
+        assert!(
+            *self.unit < usable_units,
+            "uniforms use more than the {} usable texture units",
+            usable_units
+        );
+
         let ret = *self.unit;
         self.image_units.bind(*self.unit, tex, sampler, gl);
         *self.unit += 1;
@@ -54,8 +110,25 @@ impl<'a> TextureUniformBinder<'a> {
 pub unsafe trait UniformType: Copy {
     fn uniform_tag() -> TypeTag;
     unsafe fn upload(&self, loc: GLint, tex_uniform_binder: &mut TextureUniformBinder, gl: &Gl);
+
+    /// Store `self` in `cache`, returning `false` if it's equal to the value already there.
+    ///
+    /// Used by programs with uniform change tracking to skip uploading unchanged values. The
+    /// default always returns `true`, which is what texture uniforms need, since uploading them
+    /// also binds the texture to an image unit.
+    #[inline]
+    fn update_cache(&self, cache: &mut Option<Box<dyn Any>>) -> bool {
+        let _ = cache;
+        true
+    }
 }
 
+/// A collection of `UniformType`s. Should be derived.
+///
+/// Every member gets uploaded with its own `glUniform*` call, since OpenGL can only set several
+/// values in one call if they're elements of the same GLSL array. If a shader takes many values of
+/// the same type, declaring them as an array and passing a `[T; N]` member uploads all of them at
+/// once.
 pub trait Uniforms: Sized + Copy {
     type ULC: UniformLocContainer;
     type Static: 'static + Uniforms<ULC = Self::ULC>;
@@ -84,6 +157,33 @@ pub trait Uniforms: Sized + Copy {
     }
 }
 
+/// Whether any of `U`'s members are made of 64-bit integers, which can only be uploaded with
+/// `ARB_gpu_shader_int64`.
+pub(crate) fn has_int64_members<U: Uniforms>() -> bool {
+    struct Int64Finder<'a, U>(&'a mut bool, PhantomData<U>);
+    impl<'a, U: Uniforms> UniformsMemberRegistry for Int64Finder<'a, U> {
+        type Uniforms = U;
+        #[inline]
+        fn add_member<T>(&mut self, _: &str, _: fn(&Self::Uniforms) -> T)
+        where
+            T: UniformType,
+        {
+            use crate::geometry::TypeTagSingle::*;
+            let tag = match T::uniform_tag() {
+                TypeTag::Single(tag) | TypeTag::Array(tag, _) => tag,
+            };
+            *self.0 |= matches!(
+                tag,
+                Int64 | I64Vec2 | I64Vec3 | I64Vec4 | UInt64 | U64Vec2 | U64Vec3 | U64Vec4
+            );
+        }
+    }
+
+    let mut found = false;
+    U::members(Int64Finder::<U>(&mut found, PhantomData));
+    found
+}
+
 pub trait UniformLocContainer: AsRef<[GLint]> + AsMut<[GLint]> {
     fn new_zeroed() -> Self;
 }
@@ -117,6 +217,10 @@ macro_rules! impl_glsl_type_uniform {
                 let $self = *self;
                 $expr
             }
+            #[inline]
+            fn update_cache(&self, cache: &mut Option<Box<dyn Any>>) -> bool {
+                update_cache(self, cache)
+            }
         }
 
         impl_glsl_type_uniform!($($rest)*);
@@ -131,12 +235,35 @@ macro_rules! impl_glsl_type_uniform {
                 let $self = *self;
                 $expr
             }
+            #[inline]
+            fn update_cache(&self, cache: &mut Option<Box<dyn Any>>) -> bool {
+                update_cache(self, cache)
+            }
         }
 
         impl_glsl_type_uniform!($($rest)*);
     };
 }
 
+/// Shared implementation of `UniformType::update_cache` for plain-data uniforms, which compares
+/// values with `PartialEq` rather than by their bytes.
+fn update_cache<T: 'static + Copy + PartialEq>(
+    value: &T,
+    cache: &mut Option<Box<dyn Any>>,
+) -> bool {
+    match cache.as_mut().and_then(|c| c.downcast_mut::<T>()) {
+        Some(cached) if cached == value => false,
+        Some(cached) => {
+            *cached = *value;
+            true
+        }
+        None => {
+            *cache = Some(Box::new(*value));
+            true
+        }
+    }
+}
+
 #[inline(always)]
 fn nu8(i: u8) -> f32 {
     (i as f32 / u8::max_value() as f32) as f32
@@ -235,6 +362,15 @@ impl_glsl_type_uniform! {
     GLVec4<i16, Normalized>, (v, loc, gl) => gl.Uniform4f(loc, ni16(v.x), ni16(v.y), ni16(v.z), ni16(v.w)),
     GLVec4<i32, Normalized>, (v, loc, gl) => gl.Uniform4f(loc, ni32(v.x), ni32(v.y), ni32(v.z), ni32(v.w)),
 
+    i64, (i, loc, gl) => gl.Uniform1i64ARB(loc, i),
+    GLVec2<i64>, (v, loc, gl) => gl.Uniform2i64ARB(loc, v.x, v.y),
+    GLVec3<i64>, (v, loc, gl) => gl.Uniform3i64ARB(loc, v.x, v.y, v.z),
+    GLVec4<i64>, (v, loc, gl) => gl.Uniform4i64ARB(loc, v.x, v.y, v.z, v.w),
+    u64, (u, loc, gl) => gl.Uniform1ui64ARB(loc, u),
+    GLVec2<u64>, (v, loc, gl) => gl.Uniform2ui64ARB(loc, v.x, v.y),
+    GLVec3<u64>, (v, loc, gl) => gl.Uniform3ui64ARB(loc, v.x, v.y, v.z),
+    GLVec4<u64>, (v, loc, gl) => gl.Uniform4ui64ARB(loc, v.x, v.y, v.z, v.w),
+
     Rgba<f32>, (c, loc, gl) => gl.Uniform4f(loc, c.r, c.g, c.b, c.a),
     Rgba<u8, Normalized>, (c, loc, gl) => gl.Uniform4f(loc, nu8(c.r), nu8(c.g), nu8(c.b), nu8(c.a)),
     Rgba<u16, Normalized>, (c, loc, gl) => gl.Uniform4f(loc, nu16(c.r), nu16(c.g), nu16(c.b), nu16(c.a)),
@@ -254,18 +390,28 @@ impl_glsl_type_uniform! {
 
     [f32], (a, loc, gl) => gl.Uniform1fv(loc, a.len() as _, a.as_ptr()),
     [GLVec2<f32>], (a, loc, gl) => gl.Uniform2fv(loc, a.len() as _, a.as_ptr() as *const f32),
-    [GLVec3<f32>], (a, loc, gl) => gl.Uniform2fv(loc, a.len() as _, a.as_ptr() as *const f32),
-    [GLVec4<f32>], (a, loc, gl) => gl.Uniform2fv(loc, a.len() as _, a.as_ptr() as *const f32),
+    [GLVec3<f32>], (a, loc, gl) => gl.Uniform3fv(loc, a.len() as _, a.as_ptr() as *const f32),
+    [GLVec4<f32>], (a, loc, gl) => gl.Uniform4fv(loc, a.len() as _, a.as_ptr() as *const f32),
 
     [i32], (a, loc, gl) => gl.Uniform1iv(loc, a.len() as _, a.as_ptr()),
     [GLVec2<i32>], (a, loc, gl) => gl.Uniform2iv(loc, a.len() as _, a.as_ptr() as *const i32),
-    [GLVec3<i32>], (a, loc, gl) => gl.Uniform2iv(loc, a.len() as _, a.as_ptr() as *const i32),
-    [GLVec4<i32>], (a, loc, gl) => gl.Uniform2iv(loc, a.len() as _, a.as_ptr() as *const i32),
+    [GLVec3<i32>], (a, loc, gl) => gl.Uniform3iv(loc, a.len() as _, a.as_ptr() as *const i32),
+    [GLVec4<i32>], (a, loc, gl) => gl.Uniform4iv(loc, a.len() as _, a.as_ptr() as *const i32),
 
     [u32], (a, loc, gl) => gl.Uniform1uiv(loc, a.len() as _, a.as_ptr()),
     [GLVec2<u32>], (a, loc, gl) => gl.Uniform2uiv(loc, a.len() as _, a.as_ptr() as *const u32),
-    [GLVec3<u32>], (a, loc, gl) => gl.Uniform2uiv(loc, a.len() as _, a.as_ptr() as *const u32),
-    [GLVec4<u32>], (a, loc, gl) => gl.Uniform2uiv(loc, a.len() as _, a.as_ptr() as *const u32),
+    [GLVec3<u32>], (a, loc, gl) => gl.Uniform3uiv(loc, a.len() as _, a.as_ptr() as *const u32),
+    [GLVec4<u32>], (a, loc, gl) => gl.Uniform4uiv(loc, a.len() as _, a.as_ptr() as *const u32),
+
+    [i64], (a, loc, gl) => gl.Uniform1i64vARB(loc, a.len() as _, a.as_ptr()),
+    [GLVec2<i64>], (a, loc, gl) => gl.Uniform2i64vARB(loc, a.len() as _, a.as_ptr() as *const i64),
+    [GLVec3<i64>], (a, loc, gl) => gl.Uniform3i64vARB(loc, a.len() as _, a.as_ptr() as *const i64),
+    [GLVec4<i64>], (a, loc, gl) => gl.Uniform4i64vARB(loc, a.len() as _, a.as_ptr() as *const i64),
+
+    [u64], (a, loc, gl) => gl.Uniform1ui64vARB(loc, a.len() as _, a.as_ptr()),
+    [GLVec2<u64>], (a, loc, gl) => gl.Uniform2ui64vARB(loc, a.len() as _, a.as_ptr() as *const u64),
+    [GLVec3<u64>], (a, loc, gl) => gl.Uniform3ui64vARB(loc, a.len() as _, a.as_ptr() as *const u64),
+    [GLVec4<u64>], (a, loc, gl) => gl.Uniform4ui64vARB(loc, a.len() as _, a.as_ptr() as *const u64),
 
     [GLMat2r2c<f32>], (a, loc, gl) => gl.UniformMatrix2fv(loc, a.len() as _, gl::FALSE, a.as_ptr() as *const f32),
     [GLMat3r3c<f32>], (a, loc, gl) => gl.UniformMatrix3fv(loc, a.len() as _, gl::FALSE, a.as_ptr() as *const f32),
@@ -0,0 +1,95 @@
+// Copyright 2018 Osspial
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    buffer::{Buffer, BufferUsage},
+    framebuffer::{render_state::RenderState, DrawMode, Framebuffer},
+    program::Program,
+    uniform::Uniforms,
+    vertex::{Index, Vertex, VertexArrayObject},
+    ContextState,
+};
+
+use std::rc::Rc;
+
+/// A vertex array object created from vertex and index data, for the common case of uploading a
+/// mesh once and drawing all of it.
+///
+/// This is a thin wrapper around [`VertexArrayObject`]; use [`vao`] or [`vao_mut`] for anything
+/// beyond drawing the whole mesh, like updating the vertex data or drawing a sub-range.
+///
+/// [`VertexArrayObject`]: ./struct.VertexArrayObject.html
+/// [`vao`]: #method.vao
+/// [`vao_mut`]: #method.vao_mut
+pub struct Mesh<V: Vertex, I: Index> {
+    vao: VertexArrayObject<V, I>,
+}
+
+impl<V: Vertex, I: Index> Mesh<V, I> {
+    /// Upload `vertices` and `indices` into new buffers, and create a VAO that reads from them.
+    ///
+    /// ## Panics
+    /// Panics if the GPU is out of memory, or for the same reasons as
+    /// [`VertexArrayObject::new`].
+    ///
+    /// [`VertexArrayObject::new`]: ./struct.VertexArrayObject.html#method.new
+    pub fn new(
+        vertices: &[V],
+        indices: Option<&[I]>,
+        usage: BufferUsage,
+        state: Rc<ContextState>,
+    ) -> Mesh<V, I> {
+        let index_buffer = indices.map(|indices| Buffer::with_data(usage, indices, state.clone()));
+        let vertex_buffer = Buffer::with_data(usage, vertices, state);
+        Mesh {
+            vao: VertexArrayObject::new(vertex_buffer, index_buffer),
+        }
+    }
+
+    #[inline]
+    pub fn vao(&self) -> &VertexArrayObject<V, I> {
+        &self.vao
+    }
+
+    #[inline]
+    pub fn vao_mut(&mut self) -> &mut VertexArrayObject<V, I> {
+        &mut self.vao
+    }
+
+    /// Draw every vertex in the mesh, or every index if the mesh has indices.
+    ///
+    /// This is shorthand for calling [`Framebuffer::draw`] with the full range.
+    ///
+    /// [`Framebuffer::draw`]: ../framebuffer/trait.Framebuffer.html#method.draw
+    #[inline]
+    pub fn draw_with<F, U>(
+        &self,
+        framebuffer: &mut F,
+        mode: DrawMode,
+        program: &Program<V, U::Static, F::AttachmentsStatic>,
+        uniforms: &U,
+        render_state: &RenderState,
+    ) where
+        F: Framebuffer,
+        U: Uniforms,
+    {
+        framebuffer.draw(mode, .., &self.vao, program, uniforms, render_state);
+    }
+
+    /// Destroy the mesh, returning the vertex and index buffers.
+    #[inline]
+    pub fn unwrap(self) -> (Buffer<V>, Option<Buffer<I>>) {
+        self.vao.unwrap()
+    }
+}
@@ -12,12 +12,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod mesh;
 pub(crate) mod vao;
-pub use self::vao::VertexArrayObject;
+pub use self::{
+    mesh::Mesh,
+    vao::{IndexOutOfBounds, TooManyAttributes, VertexArrayObject},
+};
 
 use crate::{
-    gl::types::{GLenum, GLint},
+    gl::{
+        self,
+        types::{GLenum, GLint},
+    },
     geometry::{ScalarBase, TransparentType},
+    ContextState,
 };
 use std::marker::PhantomData;
 
@@ -45,13 +53,101 @@ unsafe impl Index for u32 {
     }
 }
 
+/// The maximum number of vertex attribute locations a vertex shader can read from.
+///
+/// Corresponds to `GL_MAX_VERTEX_ATTRIBS`. OpenGL guarantees this is at least 16. Matrix members
+/// consume one location per column, so a `GLMat4r4c` member takes up four of these.
+pub fn max_vertex_attribs(state: &ContextState) -> usize {
+    let mut max = 0;
+    unsafe {
+        state.gl.GetIntegerv(gl::MAX_VERTEX_ATTRIBS, &mut max);
+    }
+    max as usize
+}
+
+/// Concatenate a set of triangle or line strips into a single index list, separating each strip
+/// with `restart`.
+///
+/// Drawing the result as a strip requires enabling primitive restart with the same index, by
+/// setting [`RenderState::primitive_restart_index`] to `Some(restart as u32)`.
+///
+/// ## Panics
+/// Will panic if any strip contains `restart`, as that index would split the strip instead of
+/// referencing a vertex.
+///
+/// [`RenderState::primitive_restart_index`]: ../framebuffer/render_state/struct.RenderState.html#structfield.primitive_restart_index
+pub fn join_strips<I: Index + PartialEq>(strips: &[&[I]], restart: I) -> Vec<I> {
+    let len = strips.iter().map(|s| s.len()).sum::<usize>() + strips.len().saturating_sub(1);
+    let mut indices = Vec::with_capacity(len);
+
+    for (i, strip) in strips.iter().enumerate() {
+        if let Some(pos) = strip.iter().position(|&index| index == restart) {
+            panic!(
+                "strip {} contains the restart index {} at position {}",
+                i,
+                restart.as_glint(),
+                pos
+            );
+        }
+
+        if i != 0 {
+            indices.push(restart);
+        }
+        indices.extend_from_slice(strip);
+    }
+
+    indices
+}
+
+/// A [`TransparentType`] that can be read from a vertex buffer as an attribute.
+///
+/// Implemented for every transparent type except the ones made of `i64` or `u64`, which GLSL
+/// only accepts as uniforms. Using one of those as a vertex member fails to compile:
+///
+/// ```rust,compile_fail
+/// # use gullery::vertex::{Vertex, VertexMemberRegistry};
+/// #[derive(Clone, Copy)]
+/// struct WideVertex {
+///     id: i64,
+/// }
+///
+/// impl Vertex for WideVertex {
+///     fn members<M>(mut reg: M)
+///     where
+///         M: VertexMemberRegistry<Group = Self>,
+///     {
+///         reg.add_member("id", |t| unsafe { &(*t).id });
+///     }
+/// }
+/// ```
+///
+/// [`TransparentType`]: ../geometry/trait.TransparentType.html
+pub trait VertexAttribute: TransparentType {}
+impl<T> VertexAttribute for T
+where
+    T: TransparentType,
+    T::Scalar: AttributeScalar,
+{
+}
+
+/// A scalar that vertex attributes can be made of.
+pub trait AttributeScalar: ScalarBase {}
+impl AttributeScalar for u8 {}
+impl AttributeScalar for u16 {}
+impl AttributeScalar for u32 {}
+impl AttributeScalar for i8 {}
+impl AttributeScalar for i16 {}
+impl AttributeScalar for i32 {}
+impl AttributeScalar for f32 {}
+impl AttributeScalar for bool {}
+
 pub trait VertexMemberRegistry {
     type Group: Vertex;
     /// Add a member to the registry. Note that the value pointed to by `get_type` is allowed to be
     /// instantiated with `mem::zeroed()`, and any references inside should not be dereferenced.
     fn add_member<T>(&mut self, name: &str, get_type: fn(*const Self::Group) -> *const T)
     where
-        T: TransparentType;
+        T: VertexAttribute;
 }
 
 pub trait Vertex: 'static + Copy {
@@ -67,7 +163,7 @@ pub trait Vertex: 'static + Copy {
             #[inline]
             fn add_member<T>(&mut self, _: &str, _: fn(*const G) -> *const T)
             where
-                T: TransparentType,
+                T: VertexAttribute,
             {
                 *self.0 += 1;
             }
@@ -77,4 +173,58 @@ pub trait Vertex: 'static + Copy {
         Self::members(MemberCounter::<Self>(&mut num, PhantomData));
         num
     }
+
+    /// The number of attribute locations the members take up. This is the same as
+    /// [`num_members`](#method.num_members), except that matrix members take up one location per
+    /// column.
+    #[inline]
+    fn num_attrib_slots() -> usize {
+        struct SlotCounter<'a, G>(&'a mut usize, PhantomData<G>);
+        impl<'a, G: Vertex> VertexMemberRegistry for SlotCounter<'a, G> {
+            type Group = G;
+            #[inline]
+            fn add_member<T>(&mut self, _: &str, _: fn(*const G) -> *const T)
+            where
+                T: VertexAttribute,
+            {
+                *self.0 += T::prim_tag().num_attrib_slots();
+            }
+        }
+
+        let mut num = 0;
+        Self::members(SlotCounter::<Self>(&mut num, PhantomData));
+        num
+    }
+}
+
+/// A vertex type without any members. This is the instance type of vertex array objects that
+/// don't have per-instance attributes.
+impl Vertex for () {
+    #[inline]
+    fn members<M>(_: M)
+    where
+        M: VertexMemberRegistry<Group = Self>,
+    {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_strips_separates_with_restart() {
+        let strips: &[&[u16]] = &[&[0, 1, 2, 3], &[4, 5, 6], &[7, 8, 9]];
+        assert_eq!(
+            join_strips(strips, u16::MAX),
+            [0, 1, 2, 3, !0, 4, 5, 6, !0, 7, 8, 9]
+        );
+        assert_eq!(join_strips::<u16>(&[], 0), []);
+    }
+
+    #[test]
+    #[should_panic]
+    fn join_strips_restart_collision() {
+        join_strips::<u16>(&[&[0, 1, 2], &[3, 4, 5]], 4);
+    }
 }
@@ -17,19 +17,27 @@ use self::raw::*;
 
 use crate::{
     buffer::Buffer,
-    vertex::{Index, Vertex},
+    vertex::{self, Index, Vertex, VertexAttribute, VertexMemberRegistry},
     ContextState, GLObject, Handle,
 };
 
-use std::{mem, ptr, rc::Rc};
+use std::{error::Error, fmt, io, marker::PhantomData, mem::ManuallyDrop, ptr, rc::Rc};
 
-pub struct VertexArrayObject<V: Vertex, I: Index> {
+/// Reads vertices out of a vertex buffer, and optionally indices out of an index buffer and
+/// per-instance attributes out of an instance buffer.
+///
+/// `N` is the type of the per-instance attributes, which is `()` for VAOs created without an
+/// instance buffer. See [`with_instances`] for how instance attributes reach the shader.
+///
+/// [`with_instances`]: #method.with_instances
+pub struct VertexArrayObject<V: Vertex, I: Index, N: Vertex = ()> {
     raw: RawVAO<V>,
     vertex_buffer: Buffer<V>,
     index_buffer: Option<Buffer<I>>,
+    instance_buffer: Option<Buffer<N>>,
 }
 
-impl<V: Vertex, I: Index> GLObject for VertexArrayObject<V, I> {
+impl<V: Vertex, I: Index, N: Vertex> GLObject for VertexArrayObject<V, I, N> {
     fn handle(&self) -> Handle {
         self.raw.handle()
     }
@@ -39,32 +47,190 @@ impl<V: Vertex, I: Index> GLObject for VertexArrayObject<V, I> {
     }
 }
 
+/// Error returned by [`VertexArrayObject::validate_indices`] when the index buffer references a
+/// vertex past the end of the vertex buffer.
+///
+/// [`VertexArrayObject::validate_indices`]: ./struct.VertexArrayObject.html#method.validate_indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOutOfBounds {
+    /// The position of the offending index within the index buffer.
+    pub position: usize,
+    pub index: usize,
+    pub vertex_count: usize,
+}
+
+/// Error returned by [`VertexArrayObject::try_new`] when the vertex type needs more attribute
+/// locations than the implementation provides.
+///
+/// [`VertexArrayObject::try_new`]: ./struct.VertexArrayObject.html#method.try_new
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TooManyAttributes {
+    /// The name of the first member that doesn't fit.
+    pub member: String,
+    /// The number of attribute locations needed by every member up to and including `member`.
+    pub locations_needed: usize,
+    /// The value of `GL_MAX_VERTEX_ATTRIBS`.
+    pub max_attribs: usize,
+}
+
 pub(crate) struct VAOTarget(RawVAOTarget);
-pub(crate) struct BoundVAO<'a, V: Vertex, I: Index> {
-    vao: &'a VertexArrayObject<V, I>,
+pub(crate) struct BoundVAO<'a, V: Vertex, I: Index, N: Vertex = ()> {
+    vao: &'a VertexArrayObject<V, I, N>,
     _bind: RawBoundVAO<'a, V>,
 }
 
 impl<V: Vertex, I: Index> VertexArrayObject<V, I> {
+    /// Create a vertex array object that reads vertices from `vertex_buffer`, and optionally
+    /// indices from `index_buffer`.
+    ///
+    /// The attribute layout comes from `V`'s [`Vertex`] implementation, so `vertex_buffer` must
+    /// hold `V`s. Pairing a buffer with the wrong vertex type is a compile error:
+    ///
+    /// ```rust,compile_fail
+    /// # #![feature(never_type)]
+    /// # extern crate gullery;
+    /// # #[macro_use]
+    /// # extern crate gullery_macros;
+    /// # use std::rc::Rc;
+    /// use gullery::{
+    ///     # ContextState,
+    ///     buffer::{Buffer, BufferUsage},
+    ///     geometry::{GLVec2, GLVec3},
+    ///     vertex::VertexArrayObject,
+    /// };
+    ///
+    /// #[derive(Vertex, Clone, Copy)]
+    /// struct Vertex2D {
+    ///     pos: GLVec2<f32>,
+    /// }
+    ///
+    /// #[derive(Vertex, Clone, Copy)]
+    /// struct Vertex3D {
+    ///     pos: GLVec3<f32>,
+    /// }
+    ///
+    /// # fn main() {
+    /// # let state: Rc<ContextState> = panic!();
+    /// let vertices = [Vertex3D { pos: GLVec3::new(0.0, 0.0, 0.0) }];
+    /// let buffer = Buffer::with_data(BufferUsage::StaticDraw, &vertices, state);
+    /// let vao: VertexArrayObject<Vertex2D, !> = VertexArrayObject::new(buffer, None);
+    /// # }
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if the buffers were created with contexts that don't share objects, or if `V` needs
+    /// more attribute locations than the implementation provides. See [`try_new`] for a
+    /// non-panicking version of the latter check.
+    ///
+    /// [`Vertex`]: ../trait.Vertex.html
+    /// [`try_new`]: #method.try_new
     pub fn new(
         vertex_buffer: Buffer<V>,
         index_buffer: Option<Buffer<I>>,
     ) -> VertexArrayObject<V, I> {
-        let vertex_buffer_context_ptr = vertex_buffer.state().as_ref() as *const _;
-        let index_buffer_context_ptr = index_buffer
-            .as_ref()
-            .map(|ib| ib.state().as_ref() as *const _)
-            .unwrap_or(vertex_buffer_context_ptr);
-
-        if vertex_buffer_context_ptr != index_buffer_context_ptr {
-            panic!("vertex buffer and index buffer using different contexts");
+        match VertexArrayObject::try_new(vertex_buffer, index_buffer) {
+            Ok(vao) => vao,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a vertex array object, checking that `V`'s members fit within
+    /// [`max_vertex_attribs`].
+    ///
+    /// Each member takes up one attribute location, except for matrices which take up one
+    /// location per column. A `GLMat4r4c` member therefore uses four locations.
+    ///
+    /// ## Panics
+    /// Panics if the buffers were created with contexts that don't share objects.
+    ///
+    /// [`max_vertex_attribs`]: ../fn.max_vertex_attribs.html
+    pub fn try_new(
+        vertex_buffer: Buffer<V>,
+        index_buffer: Option<Buffer<I>>,
+    ) -> Result<VertexArrayObject<V, I>, TooManyAttributes> {
+        VertexArrayObject::try_build(vertex_buffer, None, index_buffer)
+    }
+
+    pub fn unwrap(self) -> (Buffer<V>, Option<Buffer<I>>) {
+        let (vertex_buffer, _, index_buffer) = self.unwrap_instanced();
+        (vertex_buffer, index_buffer)
+    }
+}
+
+impl<V: Vertex, I: Index, N: Vertex> VertexArrayObject<V, I, N> {
+    /// Create a vertex array object that reads per-vertex attributes from `vertex_buffer` and
+    /// per-instance attributes from `instance_buffer`, for use with instanced draw calls.
+    ///
+    /// Each instance reads the next element of `instance_buffer`, so a per-instance model matrix
+    /// can be stored as a `GLMat4r4c<f32>` member of `N`. Matrix members take up one attribute
+    /// location per column, and each column is set up with its own offset.
+    ///
+    /// Instance attributes use the locations directly after `V`'s, starting at
+    /// [`V::num_attrib_slots`]. Programs only bind the names of `V`'s members, so the vertex
+    /// shader must declare the instance attributes with explicit locations:
+    ///
+    /// ```glsl
+    /// in vec3 position;                  // The only member of `V`, at location 0.
+    /// layout(location = 1) in mat4 model; // `N`'s model matrix, at locations 1 through 4.
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if the buffers were created with contexts that don't share objects, or if `V` and
+    /// `N` together need more attribute locations than the implementation provides.
+    ///
+    /// [`V::num_attrib_slots`]: ../trait.Vertex.html#method.num_attrib_slots
+    pub fn with_instances(
+        vertex_buffer: Buffer<V>,
+        instance_buffer: Buffer<N>,
+        index_buffer: Option<Buffer<I>>,
+    ) -> VertexArrayObject<V, I, N> {
+        match VertexArrayObject::try_build(vertex_buffer, Some(instance_buffer), index_buffer) {
+            Ok(vao) => vao,
+            Err(err) => panic!("{}", err),
         }
+    }
 
-        VertexArrayObject {
+    fn try_build(
+        vertex_buffer: Buffer<V>,
+        instance_buffer: Option<Buffer<N>>,
+        index_buffer: Option<Buffer<I>>,
+    ) -> Result<VertexArrayObject<V, I, N>, TooManyAttributes> {
+        if let Some(ref ib) = index_buffer {
+            if !vertex_buffer.state().shares_objects_with(ib.state()) {
+                panic!("vertex buffer and index buffer using different contexts");
+            }
+        }
+        if let Some(ref nb) = instance_buffer {
+            if !vertex_buffer.state().shares_objects_with(nb.state()) {
+                panic!("vertex buffer and instance buffer using different contexts");
+            }
+        }
+
+        let max_attribs = vertex::max_vertex_attribs(vertex_buffer.state());
+        let mut counter = AttribSlotCounter::<V> {
+            locations: 0,
+            max_attribs,
+            overflow: None,
+            _marker: PhantomData,
+        };
+        V::members(&mut counter);
+        let mut counter = AttribSlotCounter::<N> {
+            locations: counter.locations,
+            max_attribs,
+            overflow: counter.overflow,
+            _marker: PhantomData,
+        };
+        N::members(&mut counter);
+        if let Some(err) = counter.overflow {
+            return Err(err);
+        }
+
+        Ok(VertexArrayObject {
             raw: RawVAO::new(&vertex_buffer.state().gl),
             vertex_buffer,
             index_buffer,
-        }
+            instance_buffer,
+        })
     }
 
     #[inline]
@@ -87,17 +253,84 @@ impl<V: Vertex, I: Index> VertexArrayObject<V, I> {
         &mut self.index_buffer
     }
 
-    pub fn unwrap(mut self) -> (Buffer<V>, Option<Buffer<I>>) {
-        unsafe {
-            self.destroy_in_place();
-            let buffer = (
-                ptr::read(&self.vertex_buffer),
-                ptr::read(&self.index_buffer),
-            );
+    #[inline]
+    pub fn instance_buffer(&self) -> Option<&Buffer<N>> {
+        self.instance_buffer.as_ref()
+    }
 
-            mem::forget(self);
+    #[inline]
+    pub fn instance_buffer_mut(&mut self) -> Option<&mut Buffer<N>> {
+        self.instance_buffer.as_mut()
+    }
 
-            buffer
+    /// The number of instances in the instance buffer, or `None` if the VAO doesn't have one.
+    ///
+    /// Instanced draws panic if they draw more instances than this.
+    #[inline]
+    pub fn instance_count(&self) -> Option<usize> {
+        self.instance_buffer.as_ref().map(|nb| nb.len())
+    }
+
+    /// The number of vertices in the vertex buffer.
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_buffer.len()
+    }
+
+    /// The number of indices in the index buffer, or `None` if the VAO doesn't have one.
+    ///
+    /// When this is `Some`, draw ranges index into the index buffer instead of the vertex buffer.
+    #[inline]
+    pub fn index_count(&self) -> Option<usize> {
+        self.index_buffer.as_ref().map(|ib| ib.len())
+    }
+
+    /// Download the index buffer and check that every index refers to a vertex in the vertex
+    /// buffer, returning the first index that doesn't.
+    ///
+    /// OpenGL doesn't bounds-check indices, so drawing with an out-of-bounds index reads past the
+    /// end of the vertex buffer, which can crash the driver or the GPU. This stalls until the GPU
+    /// has finished writing the index buffer and then reads the whole thing back, so it's meant to
+    /// be called once after setting up the buffers, typically only in debug builds. Returns `Ok`
+    /// if the VAO doesn't have an index buffer.
+    ///
+    /// Primitive restart indices aren't treated specially, so a buffer that uses them will fail
+    /// validation unless the restart index happens to be in bounds.
+    pub fn validate_indices(&self) -> Result<(), IndexOutOfBounds> {
+        let index_buffer = match (I::INDEX_GL_ENUM, &self.index_buffer) {
+            (Some(_), Some(index_buffer)) => index_buffer,
+            _ => return Ok(()),
+        };
+
+        // `I` is an integer type here, so whatever the buffer contains is a valid value.
+        let indices = unsafe { index_buffer.get_data_vec() };
+
+        let vertex_count = self.vertex_count();
+        match indices
+            .iter()
+            .map(|i| i.as_glint() as usize)
+            .enumerate()
+            .find(|&(_, index)| index >= vertex_count)
+        {
+            Some((position, index)) => Err(IndexOutOfBounds {
+                position,
+                index,
+                vertex_count,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Destroy the VAO, returning the vertex, instance, and index buffers.
+    pub fn unwrap_instanced(self) -> (Buffer<V>, Option<Buffer<N>>, Option<Buffer<I>>) {
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            this.destroy_in_place();
+            (
+                ptr::read(&this.vertex_buffer),
+                ptr::read(&this.instance_buffer),
+                ptr::read(&this.index_buffer),
+            )
         }
     }
 
@@ -114,10 +347,19 @@ impl VAOTarget {
     }
 
     #[inline]
-    pub unsafe fn bind<'a, V, I>(&'a self, vao: &'a VertexArrayObject<V, I>) -> BoundVAO<'a, V, I>
+    pub fn invalidate(&self) {
+        self.0.invalidate();
+    }
+
+    #[inline]
+    pub unsafe fn bind<'a, V, I, N>(
+        &'a self,
+        vao: &'a VertexArrayObject<V, I, N>,
+    ) -> BoundVAO<'a, V, I, N>
     where
         V: Vertex,
         I: Index,
+        N: Vertex,
     {
         BoundVAO {
             vao,
@@ -125,19 +367,82 @@ impl VAOTarget {
                 &vao.raw,
                 &vao.vertex_buffer,
                 &vao.index_buffer,
-                &vao.vertex_buffer.state().gl,
+                &vao.instance_buffer,
+                vao.vertex_buffer.state(),
             ),
         }
     }
 }
 
-impl<'a, V: Vertex, I: Index> BoundVAO<'a, V, I> {
-    pub fn vao(&self) -> &VertexArrayObject<V, I> {
+impl<'a, V: Vertex, I: Index, N: Vertex> BoundVAO<'a, V, I, N> {
+    pub fn vao(&self) -> &VertexArrayObject<V, I, N> {
         self.vao
     }
 }
 
-impl<V: Vertex, I: Index> Drop for VertexArrayObject<V, I> {
+impl From<IndexOutOfBounds> for io::Error {
+    fn from(err: IndexOutOfBounds) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl Error for IndexOutOfBounds {}
+
+impl fmt::Display for IndexOutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "index {} at position {} is out of bounds for {} vertices",
+            self.index, self.position, self.vertex_count
+        )
+    }
+}
+
+impl From<TooManyAttributes> for io::Error {
+    fn from(err: TooManyAttributes) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl Error for TooManyAttributes {}
+
+impl fmt::Display for TooManyAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "vertex member `{}` needs attribute locations up to {}, but the GL implementation \
+             only supports {}",
+            self.member, self.locations_needed, self.max_attribs
+        )
+    }
+}
+
+struct AttribSlotCounter<V: Vertex> {
+    locations: usize,
+    max_attribs: usize,
+    overflow: Option<TooManyAttributes>,
+    _marker: PhantomData<*const V>,
+}
+
+impl<V: Vertex> VertexMemberRegistry for &mut AttribSlotCounter<V> {
+    type Group = V;
+
+    fn add_member<T>(&mut self, name: &str, _: fn(*const V) -> *const T)
+    where
+        T: VertexAttribute,
+    {
+        self.locations += T::prim_tag().num_attrib_slots();
+        if self.locations > self.max_attribs && self.overflow.is_none() {
+            self.overflow = Some(TooManyAttributes {
+                member: name.to_string(),
+                locations_needed: self.locations,
+                max_attribs: self.max_attribs,
+            });
+        }
+    }
+}
+
+impl<V: Vertex, I: Index, N: Vertex> Drop for VertexArrayObject<V, I, N> {
     fn drop(&mut self) {
         unsafe { self.destroy_in_place() }
     }
@@ -148,8 +453,56 @@ mod tests {
     use super::*;
     use crate::{
         buffer::BufferUsage,
+        framebuffer::{render_state::RenderState, DrawMode},
+        geometry::{GLVec2, GLVec3},
+        program::{FeedbackPrimitive, Program, Shader},
         test_helper::{TestVertex, CONTEXT_STATE},
+        vertex::VertexMemberRegistry,
     };
+    use std::mem;
+
+    /// A vertex whose members aren't tightly packed, so attribute offsets and the stride only
+    /// line up if they come from the struct's real layout.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct PaddedVertex {
+        id: u8,
+        // 3 bytes of padding
+        pos: GLVec2<f32>,
+        flag: u16,
+        // 2 bytes of padding
+        value: f32,
+    }
+
+    impl Vertex for PaddedVertex {
+        fn members<M>(mut reg: M)
+        where
+            M: VertexMemberRegistry<Group = Self>,
+        {
+            reg.add_member("id", |t| unsafe { &(*t).id });
+            reg.add_member("pos", |t| unsafe { &(*t).pos });
+            reg.add_member("flag", |t| unsafe { &(*t).flag });
+            reg.add_member("value", |t| unsafe { &(*t).value });
+        }
+    }
+
+    const PADDED_VERTEX_SHADER: &str = r#"
+        #version 330
+
+        in uint id;
+        in vec2 pos;
+        in uint flag;
+        in float value;
+
+        out vec4 captured;
+        out float captured_flag;
+
+        void main() {
+            captured = vec4(float(id), pos, value);
+            captured_flag = float(flag);
+            gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+        }
+    "#;
 
     quickcheck! {
         fn make_vao_noindex(buffer_data: Vec<TestVertex>) -> () {
@@ -159,4 +512,146 @@ mod tests {
             });
         }
     }
+
+    #[test]
+    fn validate_indices() {
+        CONTEXT_STATE.with(|context_state| {
+            let vertex = TestVertex {
+                pos: GLVec2::new(0.0, 0.0),
+                color: GLVec3::new(0.0, 0.0, 0.0),
+            };
+            let vertices = [vertex; 3];
+            let vertex_buffer =
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, context_state.clone());
+            let index_buffer = Buffer::with_data(
+                BufferUsage::StaticDraw,
+                &[0u16, 1, 2, 3],
+                context_state.clone(),
+            );
+            let mut vao = VertexArrayObject::new(vertex_buffer, Some(index_buffer));
+            assert_eq!(
+                Err(IndexOutOfBounds {
+                    position: 3,
+                    index: 3,
+                    vertex_count: 3
+                }),
+                vao.validate_indices()
+            );
+
+            vao.index_buffer_mut().as_mut().unwrap().sub_data(3, &[2]);
+            assert_eq!(Ok(()), vao.validate_indices());
+        });
+    }
+
+    #[test]
+    fn padded_vertex_attributes() {
+        CONTEXT_STATE.with(|state| {
+            assert_eq!(20, mem::size_of::<PaddedVertex>());
+            let vertices = [
+                PaddedVertex {
+                    id: 1,
+                    pos: GLVec2::new(2.0, 3.0),
+                    flag: 4,
+                    value: 5.0,
+                },
+                PaddedVertex {
+                    id: 6,
+                    pos: GLVec2::new(7.0, 8.0),
+                    flag: 9,
+                    value: 10.0,
+                },
+            ];
+            let vertex_buffer =
+                Buffer::with_data(BufferUsage::StaticDraw, &vertices, state.clone());
+            let vao: VertexArrayObject<PaddedVertex, !> =
+                VertexArrayObject::new(vertex_buffer, None);
+
+            let vertex_shader = Shader::new(PADDED_VERTEX_SHADER, state.clone()).unwrap();
+            let (program, _) = Program::<PaddedVertex, (), ()>::new_transform_feedback(
+                &vertex_shader,
+                None,
+                &["captured", "captured_flag"],
+            )
+            .unwrap();
+
+            let mut output: Buffer<[f32; 5]> =
+                Buffer::with_size(BufferUsage::StreamRead, vertices.len(), state.clone());
+            let render_state = RenderState {
+                rasterizer_discard: true,
+                ..RenderState::default()
+            };
+            program.draw_feedback(
+                FeedbackPrimitive::Points,
+                DrawMode::Points,
+                ..,
+                &vao,
+                &(),
+                &render_state,
+                &mut output,
+            );
+
+            let mut captured = [[0.0; 5]; 2];
+            unsafe { output.get_data(0, &mut captured) };
+            assert_eq!(
+                [[1.0, 2.0, 3.0, 5.0, 4.0], [6.0, 7.0, 8.0, 10.0, 9.0]],
+                captured
+            );
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn attribute_locations_are_reported() {
+        use crate::{gl::types::GLint, Diagnostic};
+        use std::{cell::RefCell, rc::Rc};
+
+        CONTEXT_STATE.with(|state| {
+            let reports = Rc::new(RefCell::new(Vec::new()));
+            let callback_reports = reports.clone();
+            state.set_diagnostic_callback(Some(Box::new(move |diagnostic| {
+                if let Diagnostic::AttributeLocation {
+                    ref member,
+                    location,
+                    ..
+                } = *diagnostic
+                {
+                    callback_reports
+                        .borrow_mut()
+                        .push((member.clone(), location));
+                }
+            })));
+
+            let vertex = PaddedVertex {
+                id: 0,
+                pos: GLVec2::new(0.0, 0.0),
+                flag: 0,
+                value: 0.0,
+            };
+            let vertex_buffer =
+                Buffer::with_data(BufferUsage::StaticDraw, &[vertex], state.clone());
+            let vao: VertexArrayObject<PaddedVertex, !> =
+                VertexArrayObject::new(vertex_buffer, None);
+            unsafe { state.vao_target.bind(&vao) };
+            state.set_diagnostic_callback(None);
+
+            let vertex_shader = Shader::new(PADDED_VERTEX_SHADER, state.clone()).unwrap();
+            let (program, _) = Program::<PaddedVertex, (), ()>::new_transform_feedback(
+                &vertex_shader,
+                None,
+                &["captured", "captured_flag"],
+            )
+            .unwrap();
+
+            let reports = reports.borrow();
+            assert_eq!(4, reports.len());
+            for (member, location) in reports.iter() {
+                assert_eq!(
+                    Some(*location as GLint),
+                    program.attribute_location(member),
+                    "{}",
+                    member
+                );
+            }
+        });
+    }
 }
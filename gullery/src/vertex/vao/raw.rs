@@ -14,10 +14,12 @@
 
 use crate::gl::{self, types::*, Gl};
 
+#[cfg(debug_assertions)]
+use crate::Diagnostic;
 use crate::{
     buffer::Buffer,
-    geometry::{Scalar, ScalarBase, ScalarType, TransparentType, TypeTagSingle},
-    vertex::{Index, Vertex, VertexMemberRegistry},
+    geometry::{Scalar, ScalarBase, ScalarType, TypeTagSingle},
+    vertex::{Index, Vertex, VertexAttribute, VertexMemberRegistry},
     ContextState, GLObject, Handle,
 };
 
@@ -29,6 +31,8 @@ pub struct RawVAO<V: Vertex> {
     vbuf: Cell<Option<Handle>>,
     /// Handle of the bound index buffer
     ibuf: Cell<Option<Handle>>,
+    /// Handle of the bound instance buffer
+    nbuf: Cell<Option<Handle>>,
     _sendsync_optout: PhantomData<(*const (), V)>,
 }
 
@@ -40,9 +44,13 @@ pub struct RawVAOTarget {
 pub struct RawBoundVAO<'a, V: Vertex>(PhantomData<(&'a RawVAO<V>, *const ())>);
 
 struct VertexAttribBuilder<'a, V: Vertex> {
+    #[cfg(debug_assertions)]
+    vao: Handle,
     attrib_loc: u32,
     max_attribs: u32,
-    gl: &'a Gl,
+    /// `0` for per-vertex attributes, and `1` for per-instance attributes.
+    divisor: GLuint,
+    state: &'a ContextState,
     _marker: PhantomData<*const V>,
 }
 
@@ -58,6 +66,7 @@ impl<V: Vertex> RawVAO<V> {
                 handle,
                 vbuf: Cell::new(None),
                 ibuf: Cell::new(None),
+                nbuf: Cell::new(None),
                 _sendsync_optout: PhantomData,
             }
         }
@@ -87,17 +96,20 @@ impl RawVAOTarget {
     }
 
     #[inline]
-    pub unsafe fn bind<'a, V, I>(
+    pub unsafe fn bind<'a, V, I, N>(
         &'a self,
         vao: &'a RawVAO<V>,
         vbuf: &Buffer<V>,
         ibuf: &Option<Buffer<I>>,
-        gl: &Gl,
+        nbuf: &Option<Buffer<N>>,
+        state: &ContextState,
     ) -> RawBoundVAO<'a, V>
     where
         V: Vertex,
         I: Index,
+        N: Vertex,
     {
+        let gl = &state.gl;
         if self.bound_vao.get() != Some(vao.handle) {
             gl.BindVertexArray(vao.handle.get());
             self.bound_vao.set(Some(vao.handle));
@@ -113,12 +125,36 @@ impl RawVAOTarget {
 
             // Set the vertex attributes to point to the newly bound vertex buffer
             V::members(VertexAttribBuilder {
+                #[cfg(debug_assertions)]
+                vao: vao.handle,
                 attrib_loc: 0,
                 max_attribs: max_attribs as u32,
-                gl,
+                divisor: 0,
+                state,
                 _marker: PhantomData,
             })
         }
+        let nbuf_handle_opt = nbuf.as_ref().map(|nb| nb.handle());
+        if nbuf_handle_opt != vao.nbuf.get() {
+            if let Some(nbuf_handle) = nbuf_handle_opt {
+                gl.BindBuffer(gl::ARRAY_BUFFER, nbuf_handle.get());
+
+                let mut max_attribs = 0;
+                gl.GetIntegerv(gl::MAX_VERTEX_ATTRIBS, &mut max_attribs);
+
+                // Instance attributes get the locations directly after the vertex attributes.
+                N::members(VertexAttribBuilder {
+                    #[cfg(debug_assertions)]
+                    vao: vao.handle,
+                    attrib_loc: V::num_attrib_slots() as u32,
+                    max_attribs: max_attribs as u32,
+                    divisor: 1,
+                    state,
+                    _marker: PhantomData,
+                })
+            }
+            vao.nbuf.set(nbuf_handle_opt);
+        }
         let ibuf_handle_opt = ibuf.as_ref().map(|ib| ib.handle());
         if ibuf_handle_opt != vao.ibuf.get() {
             gl.BindBuffer(
@@ -131,6 +167,11 @@ impl RawVAOTarget {
         RawBoundVAO(PhantomData)
     }
 
+    #[inline]
+    pub fn invalidate(&self) {
+        self.bound_vao.set(None);
+    }
+
     #[inline]
     pub unsafe fn reset_bind(&self, gl: &Gl) {
         self.bound_vao.set(None);
@@ -143,9 +184,9 @@ impl<'a, V: Vertex> VertexMemberRegistry for VertexAttribBuilder<'a, V> {
 
     fn add_member<T>(&mut self, name: &str, get_type: fn(*const V) -> *const T)
     where
-        T: TransparentType,
+        T: VertexAttribute,
     {
-        let gl = self.gl;
+        let gl = &self.state.gl;
         let vertex = unsafe { mem::zeroed() };
 
         let attrib_ptr = get_type(&vertex) as *const T;
@@ -164,11 +205,14 @@ impl<'a, V: Vertex> VertexMemberRegistry for VertexAttribBuilder<'a, V> {
         assert!(attrib_size <= mem::size_of::<T>());
 
         unsafe {
-            if self.attrib_loc < self.max_attribs {
+            if self.attrib_loc + ty_attrib_slots as u32 <= self.max_attribs {
                 // Enable all vertex attributes necessary. For matrices, there will be more than one
                 // attribute so that's why this loop is needed.
                 for slot in 0..ty_attrib_slots as u32 {
                     gl.EnableVertexAttribArray(self.attrib_loc + slot);
+                    if self.divisor != 0 {
+                        gl.VertexAttribDivisor(self.attrib_loc + slot, self.divisor);
+                    }
                     let slot_offset = slot as usize * attrib_size;
 
                     match <T::Scalar as Scalar<T::Normalization>>::ScalarType::PRIM_TAG {
@@ -202,6 +246,12 @@ impl<'a, V: Vertex> VertexMemberRegistry for VertexAttribBuilder<'a, V> {
                     }
                 }
 
+                #[cfg(debug_assertions)]
+                self.state.report(|| Diagnostic::AttributeLocation {
+                    vao: self.vao,
+                    member: name.to_string(),
+                    location: self.attrib_loc,
+                });
                 self.attrib_loc += ty_attrib_slots as u32;
             } else {
                 panic!(
@@ -16,7 +16,16 @@ extern crate gullery;
 #[macro_use]
 extern crate gullery_macros;
 
-use gullery::geometry::{GLVec3, GLVec4, TransparentType};
+use gullery::{
+    framebuffer::attachments::TextureAttachment,
+    geometry::{GLVec3, GLVec4, D2, D3},
+    image_format::{Depth32F, Rgba},
+    texture::{
+        types::{ArrayTex, RectTexCoord},
+        Texture,
+    },
+    vertex::VertexAttribute,
+};
 
 #[derive(Vertex, Clone, Copy)]
 pub struct TestBlock {
@@ -25,7 +34,20 @@ pub struct TestBlock {
 }
 
 #[derive(Vertex, Clone, Copy)]
-pub struct TestBlockGeneric<T: TransparentType> {
+pub struct TestBlockGeneric<T: VertexAttribute> {
     pub glsl_type: T,
     pub float: f32,
 }
+
+#[derive(Vertex, Clone, Copy)]
+pub struct TestBlockRect {
+    pub pos: GLVec3<f32>,
+    pub rect_coord: RectTexCoord,
+}
+
+#[derive(Attachments)]
+pub struct TestAttachmentsLayered<'a> {
+    pub color: &'a mut Texture<D2, ArrayTex<Rgba<u8>>>,
+    pub slice: TextureAttachment<'a, D3, Rgba<u8>>,
+    pub cascade: TextureAttachment<'a, D2, ArrayTex<Depth32F>>,
+}
@@ -22,9 +22,22 @@ fn main() {
 
     let extensions = [
         "GL_EXT_texture_filter_anisotropic",
+        "GL_ARB_texture_filter_anisotropic",
         "GL_EXT_texture_sRGB",
         "GL_EXT_texture_compression_s3tc",
         "GL_KHR_debug",
+        "GL_ARB_get_program_binary",
+        "GL_ARB_sample_shading",
+        "GL_ARB_shader_atomic_counters",
+        "GL_ARB_direct_state_access",
+        "GL_NV_conservative_raster",
+        "GL_INTEL_conservative_rasterization",
+        "GL_ARB_transform_feedback3",
+        "GL_ARB_buffer_storage",
+        "GL_ARB_base_instance",
+        "GL_ARB_gpu_shader_int64",
+        "GL_ARB_viewport_array",
+        "GL_ARB_clear_buffer_object",
     ];
     Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, extensions)
         .write_bindings(StructGenerator, &mut file)
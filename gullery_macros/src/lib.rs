@@ -128,18 +128,13 @@ fn impl_attachments(derive_input: &DeriveInput) -> proc_macro2::TokenStream {
             let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
             let static_type_generics = static_type_generics(generics);
             let idents = idents(variant.fields.iter().cloned());
-            let types = variant.fields.iter().cloned().map(|mut variant| {
-                if let Type::Reference(TypeReference {
-                    ref mut lifetime, ..
-                }) = variant.ty
-                {
-                    if let Some(_) = *lifetime {
-                        *lifetime = None;
-                    }
-                }
-                variant.ty
-            });
-            let types_1 = types.clone();
+            // The depth attachment check happens in a `const`, which can't refer to the struct's
+            // lifetime parameters, so all lifetimes get replaced with `'static` there.
+            let types = variant
+                .fields
+                .iter()
+                .map(|variant| static_lifetimes(variant.ty.to_token_stream()));
+            let types_1 = variant.fields.iter().map(|variant| &variant.ty);
             let num_members = variant.fields.iter().len();
 
             quote! {
@@ -148,8 +143,8 @@ fn impl_attachments(derive_input: &DeriveInput) -> proc_macro2::TokenStream {
                     extern crate gullery as _gullery;
 
                     impl #impl_generics #ident #ty_generics #where_clause {
-                        /// Check to see that we have at no more than one depth attachment type. If we do,
-                        /// we fail to compile.
+                        /// Check to see that we have at no more than one depth or depth-stencil attachment
+                        /// type. If we do, we fail to compile.
                         ///
                         /// Thanks to static_assertions crate and rust #49450 for inspiration on how to
                         /// do this.
@@ -161,9 +156,9 @@ fn impl_attachments(derive_input: &DeriveInput) -> proc_macro2::TokenStream {
                             }
                             const NUM_DEPTH_ATTACHMENTS: usize = 0
                                 #(+ unsafe {
-                                    Transmute{ from: <<#types as _gullery::framebuffer::attachments::AttachmentType>::Format as _gullery::image_format::ImageFormatRenderable>::FormatType::FORMAT_TYPE }.to
-                                    ==
-                                    Transmute{ from: _gullery::image_format::FormatTypeTag::Depth}.to
+                                    let format_type = Transmute{ from: <<<#types as _gullery::framebuffer::attachments::AttachmentType>::Format as _gullery::image_format::ImageFormatRenderable>::FormatType as _gullery::image_format::FormatType>::FORMAT_TYPE }.to;
+                                    format_type == Transmute{ from: _gullery::image_format::FormatTypeTag::Depth}.to
+                                        || format_type == Transmute{ from: _gullery::image_format::FormatTypeTag::DepthStencil}.to
                                  } as usize)*;
                             let _has_at_least_one_color_attachment = [(); 0 - (NUM_DEPTH_ATTACHMENTS > 1) as usize];
                         }
@@ -171,7 +166,7 @@ fn impl_attachments(derive_input: &DeriveInput) -> proc_macro2::TokenStream {
 
                     #[automatically_derived]
                     impl #impl_generics _gullery::framebuffer::attachments::Attachments for #ident #ty_generics #where_clause {
-                        type AHC = [Option<_gullery::Handle>; #num_members];
+                        type AHC = [Option<_gullery::framebuffer::attachments::AttachedImage>; #num_members];
                         type Static = #ident #static_type_generics;
                         #[inline]
                         fn members<M>(mut reg: M)
@@ -221,3 +216,33 @@ fn static_type_generics(generics: &Generics) -> proc_macro2::TokenStream {
     let (_, type_generics, _) = static_generics.split_for_impl();
     type_generics.into_token_stream()
 }
+
+fn static_lifetimes(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    use proc_macro2::{Group, Ident, Spacing, TokenTree};
+
+    let mut after_apostrophe = false;
+    tokens
+        .into_iter()
+        .map(|token| {
+            let token = match token {
+                TokenTree::Group(group) => {
+                    let mut new_group =
+                        Group::new(group.delimiter(), static_lifetimes(group.stream()));
+                    new_group.set_span(group.span());
+                    TokenTree::Group(new_group)
+                }
+                TokenTree::Ident(ref ident) if after_apostrophe => {
+                    TokenTree::Ident(Ident::new("static", ident.span()))
+                }
+                token => token,
+            };
+            after_apostrophe = match token {
+                TokenTree::Punct(ref punct) => {
+                    punct.as_char() == '\'' && punct.spacing() == Spacing::Joint
+                }
+                _ => false,
+            };
+            token
+        })
+        .collect()
+}